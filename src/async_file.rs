@@ -0,0 +1,142 @@
+use crate::{file::Eu4File, models::Eu4Save, Encoding, Eu4Error, MeltOptions, MeltedDocument};
+use jomini::binary::TokenResolver;
+use std::{io, sync::Arc};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// A save whose data was read asynchronously. Parsing and melting are
+/// CPU bound and run on tokio's blocking thread pool so that they don't stall
+/// the runtime.
+#[derive(Debug, Clone)]
+pub struct Eu4AsyncFile {
+    data: Arc<[u8]>,
+    encoding: Encoding,
+}
+
+impl Eu4File {
+    /// Reads the save from an async reader without blocking the runtime
+    pub async fn from_async_reader<R>(mut reader: R) -> Result<Eu4AsyncFile, Eu4Error>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        Eu4AsyncFile::from_bytes(data)
+    }
+}
+
+impl Eu4AsyncFile {
+    /// Wraps save data that is already in memory
+    pub fn from_bytes(data: impl Into<Arc<[u8]>>) -> Result<Self, Eu4Error> {
+        let data = data.into();
+        let encoding = Eu4File::from_slice(&data)?.encoding();
+        Ok(Eu4AsyncFile { data, encoding })
+    }
+
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub fn get_ref(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub async fn parse_save<Resolver>(&self, resolver: Resolver) -> Result<Eu4Save, Eu4Error>
+    where
+        Resolver: TokenResolver + Send + 'static,
+    {
+        let data = Arc::clone(&self.data);
+        blocking(move || Eu4File::from_slice(&data)?.parse_save(resolver)).await
+    }
+
+    /// Melts the save into a plaintext document that is returned alongside
+    /// the melt details
+    pub async fn melt<Resolver>(
+        &self,
+        options: MeltOptions,
+        resolver: Resolver,
+    ) -> Result<(Vec<u8>, MeltedDocument), Eu4Error>
+    where
+        Resolver: TokenResolver + Send + 'static,
+    {
+        let data = Arc::clone(&self.data);
+        blocking(move || {
+            let mut output = Vec::with_capacity(data.len());
+            let doc = Eu4File::from_slice(&data)?.melt(options, resolver, &mut output)?;
+            Ok((output, doc))
+        })
+        .await
+    }
+}
+
+async fn blocking<T, F>(f: F) -> Result<T, Eu4Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Eu4Error> + Send + 'static,
+{
+    match tokio::task::spawn_blocking(f).await {
+        Ok(result) => result,
+        Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+        Err(e) => Err(io::Error::other(e).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SegmentedResolver;
+
+    #[test]
+    fn test_async_melt_text() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let data: &[u8] = b"EU4txt\ndate=1444.11.11\nplayer=\"ENG\"";
+        let (melted, encoding) = runtime.block_on(async {
+            let file = Eu4File::from_async_reader(data).await.unwrap();
+            let encoding = file.encoding();
+            let (melted, _) = file
+                .melt(MeltOptions::new(), SegmentedResolver::empty())
+                .await
+                .unwrap();
+            (melted, encoding)
+        });
+
+        assert_eq!(encoding, Encoding::Text);
+        assert!(melted.starts_with(b"EU4txt"));
+        assert!(melted.ends_with(b"player=\"ENG\""));
+    }
+
+    #[test]
+    fn test_async_parse_save() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let data: &[u8] = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"SWE\"\n\
+displayed_country_name=\"Sweden\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\nchecksum=\"abc123\"\n\
+current_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\ntrade={ }\n\
+religion_instance_data={ }\nreligions={ }\nprovinces={ }\nincome_statistics={ }\n\
+nation_size_statistics={ }\nscore_statistics={ }\ninflation_statistics={ }\n\
+gameplaysettings={ setgameplayoptions={ 1 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\ndiplomacy={ }\n";
+        let save = runtime.block_on(async {
+            let file = Eu4File::from_async_reader(data).await.unwrap();
+            file.parse_save(SegmentedResolver::empty()).await.unwrap()
+        });
+
+        assert_eq!(save.meta.player.as_str(), "SWE");
+        assert_eq!(save.game.current_age, "age_of_discovery");
+    }
+
+    #[test]
+    fn test_async_unknown_header() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+
+        let data: &[u8] = b"not a save";
+        let result = runtime.block_on(Eu4File::from_async_reader(data));
+        assert!(result.is_err());
+    }
+}
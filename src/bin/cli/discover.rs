@@ -0,0 +1,33 @@
+use eu4save::{discover::TokenDiscovery, Eu4File, SegmentedResolver};
+use std::{
+    error::Error,
+    io::{BufWriter, Write},
+};
+
+pub fn run(binary_path: &str, text_path: &str) -> Result<(), Box<dyn Error>> {
+    let binary_data = std::fs::read(binary_path)?;
+    let binary = Eu4File::from_slice(&binary_data)?;
+    let text_data = std::fs::read(text_path)?;
+    let text = Eu4File::from_slice(&text_data)?;
+
+    let file_data = std::fs::read("assets/eu4.txt").unwrap_or_default();
+    let resolver_builder = SegmentedResolver::parse(file_data.as_slice())?;
+    let resolver = resolver_builder.resolver();
+
+    let mut discovery = TokenDiscovery::new(resolver);
+    discovery.align_files(&binary, &text)?;
+
+    let stdout = std::io::stdout();
+    let mut writer = BufWriter::new(stdout.lock());
+    for candidate in discovery.candidates() {
+        writeln!(writer, "{}", candidate)?;
+        if candidate.votes != candidate.total {
+            eprintln!(
+                "0x{:04x}: {} of {} alignments agreed on {}",
+                candidate.token, candidate.votes, candidate.total, candidate.name
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,6 @@
 use eu4save::{
     file::{Eu4FileEntryName, Eu4FsFileKind, Eu4ParsedText},
-    BasicTokenResolver, Eu4File,
+    BasicTokenResolver, Eu4File, JsonMeltOptions,
 };
 use std::{error::Error, io::Read};
 
@@ -17,6 +17,8 @@ pub fn run(path: &str) -> Result<(), Box<dyn Error>> {
     let resolver = BasicTokenResolver::from_text_lines(file_data.as_slice())?;
 
     let melt_options = eu4save::MeltOptions::new().verbatim(true);
+    let json_options = JsonMeltOptions::new();
+    let stdout = std::io::stdout();
     match file.kind_mut() {
         Eu4FsFileKind::Text(x) => {
             let mut buf = Vec::new();
@@ -25,22 +27,21 @@ pub fn run(path: &str) -> Result<(), Box<dyn Error>> {
             json_to_stdout(&text);
         }
         Eu4FsFileKind::Binary(x) => {
-            let mut buf = Vec::new();
-            x.as_ref().melt(melt_options, resolver, &mut buf)?;
-            let text = Eu4ParsedText::from_slice(&buf)?;
-            json_to_stdout(&text);
+            let out = std::io::BufWriter::new(stdout.lock());
+            x.as_ref()
+                .melt_json(melt_options, json_options, resolver, out)?;
         }
         Eu4FsFileKind::Zip(x) => {
             let mut meta = x.get(Eu4FileEntryName::Meta)?;
-            let mut data = Vec::new();
             if x.encoding().is_binary() {
-                meta.melt(melt_options, resolver, &mut data)?;
+                let out = std::io::BufWriter::new(stdout.lock());
+                meta.melt_json(melt_options, json_options, resolver, out)?;
             } else {
+                let mut data = Vec::new();
                 meta.read_to_end(&mut data)?;
+                let text = Eu4ParsedText::from_slice(&data)?;
+                json_to_stdout(&text);
             }
-
-            let text = Eu4ParsedText::from_slice(&data)?;
-            json_to_stdout(&text);
         }
     }
 
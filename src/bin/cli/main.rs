@@ -3,9 +3,11 @@ use std::{env, error::Error};
 mod csv;
 mod debug_save;
 mod deducer;
+mod discover;
 mod fmt;
 mod json;
 mod melt;
+mod unmelt;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args: Vec<String> = env::args().collect();
@@ -14,9 +16,15 @@ fn main() -> Result<(), Box<dyn Error>> {
         "csv" => csv::run(args[2].as_str()),
         "debug" => debug_save::run(args[2].as_str()),
         "deducer" => deducer::run(args[2].as_str()),
+        "discover" if args.len() < 4 => {
+            eprintln!("usage: {} discover <binary save> <text save>", args[0]);
+            std::process::exit(1);
+        }
+        "discover" => discover::run(args[2].as_str(), args[3].as_str()),
         "fmt" => fmt::run(args[2].as_str()),
         "json" => json::run(args[2].as_str()),
         "melt" => melt::run(args[2].as_str()),
+        "unmelt" => unmelt::run(args[2].as_str()),
         x => panic!("unrecognized argument: {}", x),
     }?;
 
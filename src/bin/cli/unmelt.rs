@@ -0,0 +1,20 @@
+use eu4save::{Eu4File, SegmentedResolver};
+use std::{error::Error, io::BufWriter};
+
+pub fn run(path: &str) -> Result<(), Box<dyn Error>> {
+    let data = std::fs::read(path)?;
+    let file = Eu4File::from_slice(&data)?;
+
+    let stdout = std::io::stdout();
+    let handle = stdout.lock();
+    let mut writer = BufWriter::new(handle);
+
+    // Without the token table every field would be written as a string
+    let file_data = std::fs::read("assets/eu4.txt")
+        .map_err(|e| format!("unable to read token table assets/eu4.txt: {}", e))?;
+    let resolver_builder = SegmentedResolver::parse(file_data.as_slice())?;
+    let resolver = resolver_builder.resolver();
+    file.unmelt(resolver.encoder(), &mut writer)?;
+
+    Ok(())
+}
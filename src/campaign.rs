@@ -0,0 +1,146 @@
+use crate::{
+    models::{Country, Eu4Save},
+    query::{CountryIncomeLedger, CountryManaUsage, Query},
+    CountryTag, Eu4Date, Eu4Error, Eu4ErrorKind, TagResolver,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CountryTechnologyPoint {
+    pub adm: u8,
+    pub dip: u8,
+    pub mil: u8,
+}
+
+/// The state of a country as recorded in one of the campaign's saves
+#[derive(Debug, Serialize)]
+pub struct CountryPoint {
+    pub date: Eu4Date,
+
+    /// The tag the country had at the time of the save
+    pub tag: CountryTag,
+    pub treasury: f32,
+    pub development: f32,
+    pub manpower: f32,
+    pub income: CountryIncomeLedger,
+    pub mana_spent: CountryManaUsage,
+    pub technology: CountryTechnologyPoint,
+}
+
+/// A chronological series of saves from the same playthrough
+///
+/// Country time series are keyed by the tag the country has in the latest
+/// save, so a country's data from before a tag switch is found under its
+/// current tag.
+#[derive(Debug)]
+pub struct Campaign {
+    saves: Vec<Query>,
+    tag_resolver: TagResolver,
+}
+
+impl Campaign {
+    /// Creates a campaign from saves given in any order. All saves must share
+    /// the same campaign id.
+    pub fn new(saves: Vec<Eu4Save>) -> Result<Self, Eu4Error> {
+        if let Some(first) = saves.first() {
+            let id = &first.meta.campaign_id;
+            if let Some(other) = saves.iter().find(|x| &x.meta.campaign_id != id) {
+                return Err(Eu4Error::new(Eu4ErrorKind::CampaignMismatch(
+                    id.clone(),
+                    other.meta.campaign_id.clone(),
+                )));
+            }
+        }
+
+        let mut saves = saves;
+        saves.sort_by_key(|x| x.meta.date);
+        let saves = saves.into_iter().map(Query::from_save).collect::<Vec<_>>();
+
+        // Later saves know more of a country's history, so a country's
+        // events come from the latest save that has them. Earlier saves
+        // contribute the countries that no longer exist in later saves.
+        let mut nation_events = Vec::new();
+        let mut seen = HashSet::new();
+        for query in saves.iter().rev() {
+            let events = query.nation_events(&query.province_owners());
+            nation_events.extend(events.into_iter().filter(|x| seen.insert(x.initial)));
+        }
+        let tag_resolver = TagResolver::create(&nation_events);
+
+        Ok(Campaign {
+            saves,
+            tag_resolver,
+        })
+    }
+
+    /// The saves ordered by date
+    pub fn saves(&self) -> &[Query] {
+        &self.saves
+    }
+
+    /// Tag resolver constructed from the nation events of every save
+    pub fn tag_resolver(&self) -> &TagResolver {
+        &self.tag_resolver
+    }
+
+    /// Returns the time series of the country that has the given tag in the
+    /// latest save
+    pub fn country_series(&self, tag: &CountryTag) -> Vec<CountryPoint> {
+        self.alive_countries()
+            .filter(|(current, ..)| current == tag)
+            .map(|(_, query, stored, country)| country_point(query, stored, country))
+            .collect()
+    }
+
+    /// Returns the time series of every country that was alive in at least
+    /// one save
+    pub fn series(&self) -> HashMap<CountryTag, Vec<CountryPoint>> {
+        let mut result: HashMap<CountryTag, Vec<CountryPoint>> = HashMap::new();
+        for (current, query, stored, country) in self.alive_countries() {
+            let point = country_point(query, stored, country);
+            result.entry(current).or_default().push(point);
+        }
+        result
+    }
+
+    /// Iterates over the countries with provinces in each save along with the
+    /// tag that the country has in the latest save
+    fn alive_countries(
+        &self,
+    ) -> impl Iterator<Item = (CountryTag, &Query, CountryTag, &Country)> + '_ {
+        self.saves.iter().flat_map(move |query| {
+            let date = query.save().meta.date;
+            query
+                .save()
+                .game
+                .countries
+                .iter()
+                .filter(|(_, country)| country.num_of_cities > 0)
+                .map(move |(tag, country)| {
+                    let current = self
+                        .tag_resolver
+                        .resolve(*tag, date)
+                        .map_or(*tag, |x| x.current);
+                    (current, query, *tag, country)
+                })
+        })
+    }
+}
+
+fn country_point(query: &Query, tag: CountryTag, country: &Country) -> CountryPoint {
+    CountryPoint {
+        date: query.save().meta.date,
+        tag,
+        treasury: country.treasury,
+        development: country.development,
+        manpower: country.manpower,
+        income: query.country_income_breakdown(country),
+        mana_spent: query.country_mana_breakdown(country),
+        technology: CountryTechnologyPoint {
+            adm: country.technology.adm_tech,
+            dip: country.technology.dip_tech,
+            mil: country.technology.mil_tech,
+        },
+    }
+}
@@ -59,3 +59,27 @@ where
         marker: PhantomData,
     })
 }
+
+/// Alternating key values for structs that can't use `deserialize_with`
+/// (ie: those that borrow)
+#[derive(Debug)]
+pub(crate) struct AlternatingKeyValues<K, V>(pub HashMap<K, V>);
+
+impl<K, V> Default for AlternatingKeyValues<K, V> {
+    fn default() -> Self {
+        AlternatingKeyValues(HashMap::new())
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for AlternatingKeyValues<K, V>
+where
+    K: Deserialize<'de> + Hash + Eq,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_alternating_key_values(deserializer).map(AlternatingKeyValues)
+    }
+}
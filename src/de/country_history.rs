@@ -1,3 +1,4 @@
+use super::Lenient;
 use crate::{
     models::{CountryEvent, CountryHistory},
     Eu4Date,
@@ -260,7 +261,9 @@ impl<'de> de::DeserializeSeed<'de> for ExtendVec<'_> {
                         }
                         Chdf::ChangedTagFrom => CountryEvent::ChangedTagFrom(map.next_value()?),
                         Chdf::Religion => CountryEvent::Religion(map.next_value()?),
-                        Chdf::NationalFocus => CountryEvent::NationalFocus(map.next_value()?),
+                        Chdf::NationalFocus => {
+                            CountryEvent::NationalFocus(map.next_value::<Lenient<_>>()?.0)
+                        }
                         Chdf::PrimaryCulture => CountryEvent::PrimaryCulture(map.next_value()?),
                         Chdf::AddAcceptedCulture => {
                             CountryEvent::AddAcceptedCulture(map.next_value()?)
@@ -0,0 +1,116 @@
+use serde::{de, Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A string that borrows from the input when the deserializer allows it
+pub(crate) struct CowStr<'a>(pub Cow<'a, str>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for CowStr<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CowStrVisitor;
+
+        impl<'de> de::Visitor<'de> for CowStrVisitor {
+            type Value = CowStr<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(CowStr(Cow::Borrowed(v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(CowStr(Cow::Owned(v.to_string())))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(CowStr(Cow::Owned(v)))
+            }
+        }
+
+        deserializer.deserialize_str(CowStrVisitor)
+    }
+}
+
+pub(crate) fn deserialize_cow_str<'de: 'a, 'a, D>(deserializer: D) -> Result<Cow<'a, str>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    CowStr::deserialize(deserializer).map(|x| x.0)
+}
+
+pub(crate) fn deserialize_option_cow_str<'de: 'a, 'a, D>(
+    deserializer: D,
+) -> Result<Option<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_cow_str(deserializer).map(Some)
+}
+
+pub(crate) fn deserialize_cow_vec<'de: 'a, 'a, D>(
+    deserializer: D,
+) -> Result<Vec<Cow<'a, str>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values: Vec<CowStr<'a>> = Vec::deserialize(deserializer)?;
+    Ok(values.into_iter().map(|x| x.0).collect())
+}
+
+pub(crate) fn deserialize_cow_vec_pair<'de: 'a, 'a, D, V>(
+    deserializer: D,
+) -> Result<Vec<(Cow<'a, str>, V)>, D::Error>
+where
+    D: Deserializer<'de>,
+    V: Deserialize<'de>,
+{
+    let pairs: Vec<(CowStr<'a>, V)> = super::deserialize_vec_pair(deserializer)?;
+    Ok(pairs.into_iter().map(|(k, v)| (k.0, v)).collect())
+}
+
+pub(crate) fn deserialize_cow_yes_map<'de: 'a, 'a, D>(
+    deserializer: D,
+) -> Result<HashMap<Cow<'a, str>, bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct YesMapVisitor;
+
+    impl<'de> de::Visitor<'de> for YesMapVisitor {
+        type Value = HashMap<Cow<'de, str>, bool>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a yes map")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: de::MapAccess<'de>,
+        {
+            let mut values = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(key) = map.next_key::<CowStr>()? {
+                let _: de::IgnoredAny = map.next_value()?;
+                values.insert(key.0, true);
+            }
+
+            Ok(values)
+        }
+    }
+
+    deserializer.deserialize_map(YesMapVisitor)
+}
@@ -1,3 +1,4 @@
+use super::Lenient;
 use crate::models::{GameDifficulty, GameplayOptions, TaxManpowerModifier};
 use serde::{de, Deserialize, Deserializer};
 use std::fmt;
@@ -20,17 +21,10 @@ impl<'de> Deserialize<'de> for GameplayOptions {
             where
                 A: de::SeqAccess<'de>,
             {
-                let difficulty = seq.next_element::<i32>()?;
-                let difficulty =
-                    difficulty.ok_or_else(|| de::Error::custom("missing difficulty setting"))?;
-                let difficulty = match difficulty {
-                    -1 => GameDifficulty::VeryEasy,
-                    0 => GameDifficulty::Easy,
-                    1 => GameDifficulty::Normal,
-                    2 => GameDifficulty::Hard,
-                    3 => GameDifficulty::VeryHard,
-                    _ => return Err(de::Error::custom("unrecognized difficulty setting")),
-                };
+                let difficulty = seq.next_element::<Lenient<Difficulty>>()?;
+                let Difficulty(difficulty) = difficulty
+                    .ok_or_else(|| de::Error::custom("missing difficulty setting"))?
+                    .0;
 
                 let _handicap = seq.next_element::<i32>()?;
                 let _lucky_nations = seq.next_element::<i32>()?;
@@ -46,15 +40,10 @@ impl<'de> Deserialize<'de> for GameplayOptions {
                 let _custom_nation_difficulty = seq.next_element::<i32>()?;
                 let _nations = seq.next_element::<i32>()?;
 
-                let tax_manpower_modifier = seq.next_element::<i32>()?;
-                let tax_manpower_modifier = tax_manpower_modifier
-                    .ok_or_else(|| de::Error::custom("missing difficulty setting"))?;
-                let tax_manpower_modifier = match tax_manpower_modifier {
-                    0 => TaxManpowerModifier::Historical,
-                    1 => TaxManpowerModifier::Random,
-                    2 => TaxManpowerModifier::Equal,
-                    _ => return Err(de::Error::custom("unrecognized difficulty setting")),
-                };
+                let tax_manpower_modifier = seq.next_element::<Lenient<TaxManpower>>()?;
+                let TaxManpower(tax_manpower_modifier) = tax_manpower_modifier
+                    .ok_or_else(|| de::Error::custom("missing difficulty setting"))?
+                    .0;
 
                 while seq.next_element::<de::IgnoredAny>()?.is_some() {}
 
@@ -68,3 +57,77 @@ impl<'de> Deserialize<'de> for GameplayOptions {
         deserializer.deserialize_seq(GameplayOptionsVisitor)
     }
 }
+
+/// Maps the integer of a gameplay setting to its variant. The mapping is
+/// done while visiting so that a lenient parse can record the failure.
+struct SettingVisitor<T> {
+    name: &'static str,
+    setting: fn(i64) -> Option<T>,
+}
+
+impl<T> de::Visitor<'_> for SettingVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "an integer {} setting", self.name)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        (self.setting)(v)
+            .ok_or_else(|| de::Error::custom(format!("unrecognized {} setting: {}", self.name, v)))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visit_i64(i64::try_from(v).unwrap_or(i64::MAX))
+    }
+}
+
+#[derive(Default)]
+struct Difficulty(GameDifficulty);
+
+impl<'de> Deserialize<'de> for Difficulty {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = SettingVisitor {
+            name: "difficulty",
+            setting: |x| match x {
+                -1 => Some(GameDifficulty::VeryEasy),
+                0 => Some(GameDifficulty::Easy),
+                1 => Some(GameDifficulty::Normal),
+                2 => Some(GameDifficulty::Hard),
+                3 => Some(GameDifficulty::VeryHard),
+                _ => None,
+            },
+        };
+        deserializer.deserialize_i32(visitor).map(Difficulty)
+    }
+}
+
+#[derive(Default)]
+struct TaxManpower(TaxManpowerModifier);
+
+impl<'de> Deserialize<'de> for TaxManpower {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let visitor = SettingVisitor {
+            name: "tax and manpower",
+            setting: |x| match x {
+                0 => Some(TaxManpowerModifier::Historical),
+                1 => Some(TaxManpowerModifier::Random),
+                2 => Some(TaxManpowerModifier::Equal),
+                _ => None,
+            },
+        };
+        deserializer.deserialize_i32(visitor).map(TaxManpower)
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{de, Deserialize, Deserializer};
+use std::{fmt, marker::PhantomData};
+
+/// The newtype name a [`PathDeserializer`](super::PathDeserializer) looks
+/// for to know that the value may fall back to its default
+pub(crate) const LENIENT: &str = "$eu4save::private::Lenient";
+
+/// A value that, when parsing leniently, is replaced with its default if it
+/// fails to deserialize. The failure is recorded as a diagnostic, so the
+/// value must fail while it is being visited.
+///
+/// Only suitable for scalars, as a container that fails partway through
+/// would leave the rest of its contents unread.
+#[derive(Debug, Default)]
+pub(crate) struct Lenient<T>(pub T);
+
+impl<'de, T> Deserialize<'de> for Lenient<T>
+where
+    T: Deserialize<'de> + Default,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct LenientVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> de::Visitor<'de> for LenientVisitor<T>
+        where
+            T: Deserialize<'de> + Default,
+        {
+            type Value = Lenient<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a value")
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Lenient)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                Ok(Lenient(T::deserialize(deserializer).unwrap_or_default()))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(LENIENT, LenientVisitor(PhantomData))
+    }
+}
+
+pub(crate) fn deserialize_lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Lenient::deserialize(deserializer).map(|x| x.0)
+}
+
+/// A value under a key that the model doesn't recognize. It always fails to
+/// deserialize, so wrapped in a [`Lenient`] the key is skipped with a
+/// diagnostic when parsing leniently and fails the parse otherwise.
+#[derive(Debug, Default)]
+pub(crate) struct Unrecognized;
+
+impl<'de> Deserialize<'de> for Unrecognized {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct UnrecognizedVisitor;
+
+        impl<'de> de::Visitor<'de> for UnrecognizedVisitor {
+            type Value = Unrecognized;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a recognized key")
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Err(E::custom("unrecognized key"))
+            }
+        }
+
+        deserializer.deserialize_ignored_any(UnrecognizedVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        de::PathTracker,
+        models::{GameDifficulty, GameplayOptions, Leader, LeaderKind, TaxManpowerModifier},
+    };
+    use jomini::TextDeserializer;
+
+    #[derive(Debug, Deserialize)]
+    struct Save {
+        leader: Leader,
+        options: GameplayOptions,
+    }
+
+    const DATA: &[u8] =
+        b"leader={ name=\"Ricardo\" type=wizard }\noptions={ 9 0 0 0 0 0 0 0 0 0 0 0 0 0 1 }";
+
+    #[test]
+    fn test_lenient_defaults() {
+        let track = PathTracker::new().with_lenient(true);
+        let deser = TextDeserializer::from_windows1252_slice(DATA).unwrap();
+        let save = Save::deserialize(track.deserializer(&deser)).unwrap();
+        assert_eq!(save.leader.name, "Ricardo");
+        assert_eq!(save.leader.kind, LeaderKind::General);
+        assert_eq!(save.options.difficulty, GameDifficulty::Normal);
+        assert_eq!(
+            save.options.tax_manpower_modifier,
+            TaxManpowerModifier::Random
+        );
+
+        let diagnostics = track.take_diagnostics();
+        let paths = diagnostics
+            .iter()
+            .map(|x| x.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["leader.type", "options.0"]);
+        assert!(diagnostics[0].message.contains("wizard"));
+    }
+
+    #[test]
+    fn test_strict_fails() {
+        let track = PathTracker::new();
+        let deser = TextDeserializer::from_windows1252_slice(DATA).unwrap();
+        assert!(Save::deserialize(track.deserializer(&deser)).is_err());
+        assert!(track.take_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_lenient_values_without_tracking() {
+        let data =
+            b"leader={ name=\"Ricardo\" type=admiral }\noptions={ 2 0 0 0 0 0 0 0 0 0 0 0 0 0 1 }";
+        let save: Save = jomini::text::de::from_windows1252_slice(data).unwrap();
+        assert_eq!(save.leader.kind, LeaderKind::Admiral);
+        assert_eq!(save.options.difficulty, GameDifficulty::Hard);
+
+        let data = b"leader={ name=\"Ricardo\" type=wizard }";
+        let leader: Result<Leader, _> = jomini::text::de::from_windows1252_slice(data);
+        assert!(leader.is_err());
+    }
+}
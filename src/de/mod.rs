@@ -1,11 +1,14 @@
 mod alternating_key_values;
 mod country_history;
+mod cow_str;
 mod gameplay_settings;
 mod leader_kind;
 mod ledger_vec;
+mod lenient;
 mod list_overflow_byte;
 mod map_capacity;
 mod map_pair;
+mod path;
 mod province_event_value;
 mod province_history;
 mod token_bool;
@@ -15,10 +18,13 @@ mod war_history;
 mod yes_map;
 
 pub(crate) use alternating_key_values::*;
+pub(crate) use cow_str::*;
 pub(crate) use ledger_vec::*;
+pub(crate) use lenient::*;
 pub(crate) use list_overflow_byte::*;
 pub(crate) use map_capacity::*;
 pub(crate) use map_pair::*;
+pub(crate) use path::*;
 pub(crate) use token_bool::*;
 pub use vec_pair::*;
 pub(crate) use yes_map::*;
@@ -0,0 +1,808 @@
+use super::LENIENT;
+use crate::{Diagnostic, Eu4Date};
+use jomini::common::PdsDate;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::{
+    cell::RefCell,
+    fmt::{self, Write},
+};
+
+/// Records the keys and sequence indices leading up to the value being
+/// deserialized so that a failure can report where in the document it
+/// occurred (eg: `countries.ENG.history.1500.1.1.monarch.birth_date`)
+#[derive(Debug, Default)]
+pub(crate) struct PathTracker {
+    state: RefCell<PathState>,
+}
+
+// Buffers are reused between values so that tracking doesn't allocate for
+// every key in the document
+#[derive(Debug, Default)]
+struct PathState {
+    path: String,
+    lengths: Vec<usize>,
+    key: Option<String>,
+    failed: Option<String>,
+    lenient: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl PathTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow scalars of the wrong type to fall back to the zero value of
+    /// their type, and values wrapped in [`Lenient`](super::Lenient) to fall
+    /// back to their default when they fail to deserialize
+    pub fn with_lenient(self, lenient: bool) -> Self {
+        self.state.borrow_mut().lenient = lenient;
+        self
+    }
+
+    /// Wraps a deserializer so that every nested map and sequence it visits
+    /// is tracked
+    pub fn deserializer<'a, D>(&'a self, de: D) -> PathDeserializer<'a, D> {
+        PathDeserializer {
+            de,
+            track: self,
+            key: false,
+            diagnose: false,
+            recover: false,
+        }
+    }
+
+    /// Takes the path to the value where deserialization failed
+    pub fn take(&self) -> Option<String> {
+        self.state.borrow_mut().failed.take()
+    }
+
+    /// Takes the failures that were recovered from in lenient mode
+    pub fn take_diagnostics(&self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.state.borrow_mut().diagnostics)
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.state.borrow().lenient
+    }
+
+    fn diagnose(&self, message: impl fmt::Display) {
+        let mut state = self.state.borrow_mut();
+        let diagnostic = Diagnostic {
+            path: state.path.clone(),
+            message: message.to_string(),
+        };
+        state.diagnostics.push(diagnostic);
+    }
+
+    fn record_key(&self, key: impl fmt::Display) {
+        let mut state = self.state.borrow_mut();
+        let buf = state.key.get_or_insert_with(String::new);
+        buf.clear();
+        let _ = write!(buf, "{}", key);
+    }
+
+    fn push(&self, segment: impl fmt::Display) {
+        let mut state = self.state.borrow_mut();
+        let PathState { path, lengths, .. } = &mut *state;
+        lengths.push(path.len());
+        if !path.is_empty() {
+            path.push('.');
+        }
+        let _ = write!(path, "{}", segment);
+    }
+
+    fn push_key(&self) {
+        let mut state = self.state.borrow_mut();
+        let PathState {
+            path, lengths, key, ..
+        } = &mut *state;
+        lengths.push(path.len());
+        if !path.is_empty() {
+            path.push('.');
+        }
+        match key {
+            Some(key) if !key.is_empty() => path.push_str(key),
+            _ => path.push('?'),
+        }
+        if let Some(key) = key {
+            key.clear();
+        }
+    }
+
+    fn pop<T, E>(&self, result: Result<T, E>) -> Result<T, E> {
+        let mut state = self.state.borrow_mut();
+        match result {
+            // An error may have been recovered from by an intermediate
+            // deserializer, so a later success invalidates the recorded path
+            Ok(_) => state.failed = None,
+
+            // The innermost value to fail is the first to report
+            Err(_) if state.failed.is_none() => state.failed = Some(state.path.clone()),
+            Err(_) => {}
+        }
+
+        let len = state.lengths.pop().unwrap_or(0);
+        state.path.truncate(len);
+        result
+    }
+}
+
+pub(crate) struct PathDeserializer<'a, D> {
+    de: D,
+    track: &'a PathTracker,
+    key: bool,
+
+    // Record errors as diagnostics as the value is lenient
+    diagnose: bool,
+
+    // Replace scalars of the wrong type with the zero value of the type
+    // asked for, as the value is a field or element of a lenient parse
+    recover: bool,
+}
+
+struct PathVisitor<'a, V> {
+    visitor: V,
+    track: &'a PathTracker,
+    key: bool,
+    recover: bool,
+}
+
+struct PathSeed<'a, S> {
+    seed: S,
+    track: &'a PathTracker,
+    key: bool,
+}
+
+struct PathMap<'a, A> {
+    map: A,
+    track: &'a PathTracker,
+}
+
+struct PathSeq<'a, A> {
+    seq: A,
+    track: &'a PathTracker,
+    index: usize,
+}
+
+impl<'a, V> PathVisitor<'a, V> {
+    fn wrap<D>(&self, de: D) -> PathDeserializer<'a, D> {
+        PathDeserializer {
+            de,
+            track: self.track,
+            key: false,
+            diagnose: false,
+            recover: self.recover,
+        }
+    }
+
+    fn record(&self, key: impl fmt::Display) {
+        if self.key {
+            self.track.record_key(key);
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                let visitor = PathVisitor {
+                    visitor,
+                    track: self.track,
+                    key: self.key,
+                    recover: self.recover,
+                };
+                let result = self.de.$method($($arg,)* visitor);
+                if let (true, Err(e)) = (self.diagnose, &result) {
+                    self.track.diagnose(e);
+                }
+                result
+            }
+        )*
+    };
+}
+
+macro_rules! recover_deserialize {
+    ($($method:ident => $hint:expr),* $(,)?) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                if !self.recover {
+                    let visitor = PathVisitor {
+                        visitor,
+                        track: self.track,
+                        key: self.key,
+                        recover: false,
+                    };
+                    let result = self.de.$method(visitor);
+                    if let (true, Err(e)) = (self.diagnose, &result) {
+                        self.track.diagnose(e);
+                    }
+                    return result;
+                }
+
+                let track = self.track;
+                let value = self.de.$method(CaptureVisitor)?;
+                value.replay($hint, visitor, |e| track.diagnose(e))
+            }
+        )*
+    };
+}
+
+impl<'de, D> Deserializer<'de> for PathDeserializer<'_, D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    recover_deserialize! {
+        deserialize_bool => Hint::Bool,
+        deserialize_i8 => Hint::Int(i8::MIN.into(), i8::MAX.into()),
+        deserialize_i16 => Hint::Int(i16::MIN.into(), i16::MAX.into()),
+        deserialize_i32 => Hint::Int(i32::MIN.into(), i32::MAX.into()),
+        deserialize_i64 => Hint::Int(i64::MIN.into(), i64::MAX.into()),
+        deserialize_u8 => Hint::Int(0, u8::MAX.into()),
+        deserialize_u16 => Hint::Int(0, u16::MAX.into()),
+        deserialize_u32 => Hint::Int(0, u32::MAX.into()),
+        deserialize_u64 => Hint::Int(0, u64::MAX.into()),
+        deserialize_f32 => Hint::Float,
+        deserialize_f64 => Hint::Float,
+        deserialize_str => Hint::Str,
+        deserialize_string => Hint::Str,
+    }
+
+    forward_deserialize! {
+        deserialize_any(),
+        deserialize_i128(),
+        deserialize_u128(),
+        deserialize_char(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        // Lenient values are handed a deserializer that records errors, and
+        // learn that they may recover from them by being visited as an option
+        if name == LENIENT && self.track.is_lenient() {
+            return visitor.visit_some(PathDeserializer {
+                de: self.de,
+                track: self.track,
+                key: false,
+                diagnose: true,
+                recover: false,
+            });
+        }
+
+        let visitor = PathVisitor {
+            visitor,
+            track: self.track,
+            key: self.key,
+            recover: self.recover,
+        };
+        self.de.deserialize_newtype_struct(name, visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.de.is_human_readable()
+    }
+}
+
+macro_rules! forward_visit {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                self.record(&v);
+                self.visitor.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V> Visitor<'de> for PathVisitor<'_, V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_str(&str),
+        visit_borrowed_str(&'de str),
+        visit_string(String),
+    }
+
+    fn visit_i32<E>(self, v: i32) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        // Binary saves encode dates as integers, so display the keys that
+        // look like dates as dates
+        match Eu4Date::from_binary_heuristic(v) {
+            Some(date) => self.record(date.game_fmt()),
+            None => self.record(v),
+        }
+        self.visitor.visit_i32(v)
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.record(String::from_utf8_lossy(v));
+        self.visitor.visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.record(String::from_utf8_lossy(v));
+        self.visitor.visit_borrowed_bytes(v)
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.record(String::from_utf8_lossy(&v));
+        self.visitor.visit_byte_buf(v)
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_none()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let de = self.wrap(deserializer);
+        self.visitor.visit_some(de)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let de = self.wrap(deserializer);
+        self.visitor.visit_newtype_struct(de)
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.visitor.visit_seq(PathSeq {
+            seq,
+            track: self.track,
+            index: 0,
+        })
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.visitor.visit_map(PathMap {
+            map,
+            track: self.track,
+        })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        self.visitor.visit_enum(data)
+    }
+}
+
+impl<'de, S> DeserializeSeed<'de> for PathSeed<'_, S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.seed.deserialize(PathDeserializer {
+            de: deserializer,
+            track: self.track,
+            key: self.key,
+            diagnose: false,
+            recover: !self.key && self.track.is_lenient(),
+        })
+    }
+}
+
+impl<'de, A> MapAccess<'de> for PathMap<'_, A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        self.map.next_key_seed(PathSeed {
+            seed,
+            track: self.track,
+            key: true,
+        })
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.track.push_key();
+        let result = self.map.next_value_seed(PathSeed {
+            seed,
+            track: self.track,
+            key: false,
+        });
+        self.track.pop(result)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.map.size_hint()
+    }
+}
+
+impl<'de, A> SeqAccess<'de> for PathSeq<'_, A>
+where
+    A: SeqAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.track.push(self.index);
+        self.index += 1;
+        let result = self.seq.next_element_seed(PathSeed {
+            seed,
+            track: self.track,
+            key: false,
+        });
+        self.track.pop(result)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.seq.size_hint()
+    }
+}
+
+/// The kind of scalar a lenient value asked to deserialize
+#[derive(Debug, Clone, Copy)]
+enum Hint {
+    Bool,
+    Int(i128, i128),
+    Float,
+    Str,
+}
+
+/// A value read by a lenient field before it is handed to the field's
+/// visitor, kept in the form that the deserializer produced it so that a
+/// value of the right type is visited exactly as it would be when strict
+enum Captured<'de> {
+    Bool(bool),
+    I32(i32),
+    I64(i64),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+    Str(String),
+    BorrowedStr(&'de str),
+    String(String),
+    Bytes(Vec<u8>),
+    BorrowedBytes(&'de [u8]),
+    Unit,
+    Map,
+    Seq,
+}
+
+impl<'de> Captured<'de> {
+    fn int(&self) -> Option<i128> {
+        match *self {
+            Captured::I32(x) => Some(x.into()),
+            Captured::I64(x) => Some(x.into()),
+            Captured::U16(x) => Some(x.into()),
+            Captured::U32(x) => Some(x.into()),
+            Captured::U64(x) => Some(x.into()),
+            _ => None,
+        }
+    }
+
+    fn fits(&self, hint: Hint) -> bool {
+        match hint {
+            Hint::Bool => matches!(self, Captured::Bool(_)),
+            Hint::Int(min, max) => self.int().is_some_and(|x| (min..=max).contains(&x)),
+            Hint::Float => {
+                self.int().is_some() || matches!(self, Captured::F32(_) | Captured::F64(_))
+            }
+            Hint::Str => matches!(
+                self,
+                Captured::Str(_) | Captured::BorrowedStr(_) | Captured::String(_)
+            ),
+        }
+    }
+
+    fn unexpected(&self) -> de::Unexpected<'_> {
+        match self {
+            Captured::Bool(x) => de::Unexpected::Bool(*x),
+            Captured::F32(x) => de::Unexpected::Float((*x).into()),
+            Captured::F64(x) => de::Unexpected::Float(*x),
+            Captured::Str(x) | Captured::String(x) => de::Unexpected::Str(x),
+            Captured::BorrowedStr(x) => de::Unexpected::Str(x),
+            Captured::Bytes(x) => de::Unexpected::Bytes(x),
+            Captured::BorrowedBytes(x) => de::Unexpected::Bytes(x),
+            Captured::Unit => de::Unexpected::Unit,
+            Captured::Map => de::Unexpected::Map,
+            Captured::Seq => de::Unexpected::Seq,
+            x => match x.int() {
+                Some(x) if x < 0 => de::Unexpected::Signed(x as i64),
+                Some(x) => de::Unexpected::Unsigned(x as u64),
+                None => de::Unexpected::Other("value"),
+            },
+        }
+    }
+
+    /// Visits the value if it has the type that was asked for, otherwise
+    /// reports the mismatch and visits the zero value of the type
+    fn replay<V, E>(self, hint: Hint, visitor: V, diagnose: impl FnOnce(E)) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+        E: de::Error,
+    {
+        if !self.fits(hint) {
+            diagnose(E::invalid_type(self.unexpected(), &visitor));
+            return match hint {
+                Hint::Bool => visitor.visit_bool(false),
+                Hint::Int(min, _) if min < 0 => visitor.visit_i64(0),
+                Hint::Int(..) => visitor.visit_u64(0),
+                Hint::Float => visitor.visit_f64(0.0),
+                Hint::Str => visitor.visit_str(""),
+            };
+        }
+
+        match self {
+            Captured::Bool(x) => visitor.visit_bool(x),
+            Captured::I32(x) => visitor.visit_i32(x),
+            Captured::I64(x) => visitor.visit_i64(x),
+            Captured::U16(x) => visitor.visit_u16(x),
+            Captured::U32(x) => visitor.visit_u32(x),
+            Captured::U64(x) => visitor.visit_u64(x),
+            Captured::F32(x) => visitor.visit_f32(x),
+            Captured::F64(x) => visitor.visit_f64(x),
+            Captured::Str(x) => visitor.visit_str(&x),
+            Captured::BorrowedStr(x) => visitor.visit_borrowed_str(x),
+            Captured::String(x) => visitor.visit_string(x),
+            Captured::Bytes(x) => visitor.visit_bytes(&x),
+            Captured::BorrowedBytes(x) => visitor.visit_borrowed_bytes(x),
+            Captured::Unit => visitor.visit_unit(),
+            Captured::Map | Captured::Seq => unreachable!("containers never fit a scalar"),
+        }
+    }
+}
+
+struct CaptureVisitor;
+
+macro_rules! capture_visit {
+    ($($method:ident($ty:ty) => $variant:ident),* $(,)?) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Ok(Captured::$variant(v.into()))
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for CaptureVisitor {
+    type Value = Captured<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    capture_visit! {
+        visit_bool(bool) => Bool,
+        visit_i8(i8) => I32,
+        visit_i16(i16) => I32,
+        visit_i32(i32) => I32,
+        visit_i64(i64) => I64,
+        visit_u8(u8) => U16,
+        visit_u16(u16) => U16,
+        visit_u32(u32) => U32,
+        visit_u64(u64) => U64,
+        visit_f32(f32) => F32,
+        visit_f64(f64) => F64,
+        visit_str(&str) => Str,
+        visit_borrowed_str(&'de str) => BorrowedStr,
+        visit_string(String) => String,
+        visit_bytes(&[u8]) => Bytes,
+        visit_borrowed_bytes(&'de [u8]) => BorrowedBytes,
+        visit_byte_buf(Vec<u8>) => Bytes,
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Captured::Unit)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+        Ok(Captured::Seq)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while map
+            .next_entry::<de::IgnoredAny, de::IgnoredAny>()?
+            .is_some()
+        {}
+        Ok(Captured::Map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jomini::TextDeserializer;
+    use serde::Deserialize;
+    use std::collections::HashMap;
+
+    #[derive(Debug, Deserialize)]
+    struct Monarch {
+        #[allow(dead_code)]
+        birth_date: Eu4Date,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Country {
+        #[allow(dead_code)]
+        history: HashMap<String, Monarch>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Save {
+        #[allow(dead_code)]
+        countries: HashMap<String, Country>,
+        #[allow(dead_code)]
+        ids: Vec<i32>,
+    }
+
+    fn failing_path(data: &str) -> Option<String> {
+        let track = PathTracker::new();
+        let deser = TextDeserializer::from_windows1252_slice(data.as_bytes()).unwrap();
+        let result = Save::deserialize(track.deserializer(&deser));
+        assert!(result.is_err());
+        track.take()
+    }
+
+    #[test]
+    fn test_path_of_nested_failure() {
+        let data = r#"
+            countries={
+                FRA={ history={ a={ birth_date=1400.1.1 } } }
+                ENG={ history={ b={ birth_date=1400.1.1 } c={ birth_date=abc } } }
+            }
+            ids={ 1 2 }
+        "#;
+        let path = failing_path(data);
+        assert_eq!(path.as_deref(), Some("countries.ENG.history.c.birth_date"));
+    }
+
+    #[test]
+    fn test_path_of_sequence_failure() {
+        let data = "countries={} ids={ 1 2 abc }";
+        let path = failing_path(data);
+        assert_eq!(path.as_deref(), Some("ids.2"));
+    }
+
+    #[test]
+    fn test_path_of_missing_field() {
+        let data = "countries={ ENG={ history={ a={ } } } } ids={}";
+        let path = failing_path(data);
+        assert_eq!(path.as_deref(), Some("countries.ENG.history.a"));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Options {
+        count: i32,
+        enabled: bool,
+        name: String,
+        ratio: Option<f32>,
+        ids: Vec<u8>,
+    }
+
+    #[test]
+    fn test_lenient_scalars() {
+        let data = "count={ 1 } enabled=maybe name=\"a\" ratio=abc ids={ 1 -2 300 4 }";
+        let track = PathTracker::new().with_lenient(true);
+        let deser = TextDeserializer::from_windows1252_slice(data.as_bytes()).unwrap();
+        let options = Options::deserialize(track.deserializer(&deser)).unwrap();
+        assert_eq!(options.count, 0);
+        assert!(!options.enabled);
+        assert_eq!(options.name, "a");
+        assert_eq!(options.ratio, Some(0.0));
+        assert_eq!(options.ids, vec![1, 0, 0, 4]);
+
+        let diagnostics = track.take_diagnostics();
+        let paths = diagnostics
+            .iter()
+            .map(|x| x.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["count", "enabled", "ratio", "ids.1", "ids.2"]);
+        assert!(diagnostics[3].message.contains("-2"));
+    }
+}
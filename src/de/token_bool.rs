@@ -1,4 +1,4 @@
-use serde::{de, Deserializer};
+use serde::{de, Deserialize, Deserializer};
 use std::fmt;
 
 pub(crate) fn deserialize_token_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
@@ -24,3 +24,17 @@ where
 
     deserializer.deserialize_any(TokenBoolVisitor)
 }
+
+/// A bool deserialized from a token, for structs that can't use
+/// `deserialize_with` (ie: those that borrow)
+#[derive(Debug, Default)]
+pub(crate) struct TokenBool(pub bool);
+
+impl<'de> Deserialize<'de> for TokenBool {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_token_bool(deserializer).map(TokenBool)
+    }
+}
@@ -59,3 +59,27 @@ where
         marker: PhantomData,
     })
 }
+
+/// Key value pairs for structs that can't use `deserialize_with` (ie: those
+/// that borrow)
+#[derive(Debug)]
+pub(crate) struct VecPair<K, V>(pub Vec<(K, V)>);
+
+impl<K, V> Default for VecPair<K, V> {
+    fn default() -> Self {
+        VecPair(Vec::new())
+    }
+}
+
+impl<'de, K, V> Deserialize<'de> for VecPair<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_vec_pair(deserializer).map(VecPair)
+    }
+}
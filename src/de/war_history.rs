@@ -1,11 +1,9 @@
+use super::{Lenient, Unrecognized};
 use crate::{
     models::{WarEvent, WarHistory},
     Eu4Date,
 };
-use serde::{
-    de::{self, Error},
-    Deserialize, Deserializer,
-};
+use serde::{de, Deserialize, Deserializer};
 use std::fmt;
 
 impl<'de> Deserialize<'de> for WarHistory {
@@ -56,7 +54,7 @@ impl<'de> Deserialize<'de> for WarHistory {
                             events: &mut events,
                         })?,
                         Whf::Other => {
-                            return Err(A::Error::custom("unrecognized war history field"))
+                            map.next_value::<Lenient<Unrecognized>>()?;
                         }
                     }
                 }
@@ -202,7 +200,10 @@ impl<'de> de::DeserializeSeed<'de> for ExtendVec<'_> {
                         Wef::RemoveAttacker => WarEvent::RemoveAttacker(map.next_value()?),
                         Wef::RemoveDefender => WarEvent::RemoveDefender(map.next_value()?),
                         Wef::Battle => WarEvent::Battle(map.next_value()?),
-                        Wef::Other => return Err(de::Error::custom("unknown battle key")),
+                        Wef::Other => {
+                            map.next_value::<Lenient<Unrecognized>>()?;
+                            continue;
+                        }
                     };
 
                     self.events.push((self.date, val));
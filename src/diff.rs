@@ -0,0 +1,425 @@
+use crate::{
+    models::{Country, Diplomacy, Eu4Save, GameState, Hegemon, Province},
+    CountryTag, Eu4Date, Eu4Error, Eu4ErrorKind, ProvinceId,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// A value that differs between the old and new save
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: PartialEq> Change<T> {
+    fn between(old: T, new: T) -> Option<Self> {
+        (old != new).then_some(Change { old, new })
+    }
+}
+
+impl Change<f32> {
+    /// The amount the value increased by
+    pub fn delta(&self) -> f32 {
+        self.new - self.old
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProvinceDiff {
+    pub id: ProvinceId,
+    pub owner: Option<Change<Option<CountryTag>>>,
+    pub controller: Option<Change<Option<CountryTag>>>,
+    pub culture: Option<Change<Option<String>>>,
+    pub religion: Option<Change<Option<String>>>,
+
+    /// The sum of the base tax, production, and manpower
+    pub development: Option<Change<f32>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CountryDiff {
+    pub tag: CountryTag,
+    pub treasury: Option<Change<f32>>,
+    pub manpower: Option<Change<f32>>,
+    pub adm_tech: Option<Change<u8>>,
+    pub dip_tech: Option<Change<u8>>,
+    pub mil_tech: Option<Change<u8>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum RelationshipKind {
+    /// A subject relationship with the type of subject (eg: "vassal")
+    Dependency(String),
+    Alliance,
+    RoyalMarriage,
+    Warning,
+    Subsidy,
+    Guarantee,
+    TransferTradePower,
+    WarReparations,
+    SteerTrade,
+    Condottieri,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Relationship {
+    pub kind: RelationshipKind,
+    pub first: CountryTag,
+    pub second: CountryTag,
+}
+
+/// The structured changes between two saves of the same campaign
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SaveDiff {
+    pub old_date: Eu4Date,
+    pub new_date: Eu4Date,
+
+    /// Provinces where at least one of the tracked attributes changed
+    pub provinces: Vec<ProvinceDiff>,
+
+    /// Countries that own provinces in the new save but didn't in the old
+    pub countries_appeared: Vec<CountryTag>,
+
+    /// Countries that owned provinces in the old save but don't in the new
+    pub countries_disappeared: Vec<CountryTag>,
+
+    /// Countries alive in both saves where at least one of the tracked
+    /// attributes changed
+    pub countries: Vec<CountryDiff>,
+
+    /// Names of active wars that weren't active in the old save
+    pub wars_started: Vec<String>,
+
+    /// Names of wars active in the old save that are no longer active
+    pub wars_ended: Vec<String>,
+
+    /// Diplomatic relationships that didn't exist in the old save
+    pub new_relationships: Vec<Relationship>,
+
+    pub emperor: Option<Change<Option<CountryTag>>>,
+    pub papal_controller: Option<Change<Option<CountryTag>>>,
+    pub military_hegemon: Option<Change<Option<CountryTag>>>,
+    pub naval_hegemon: Option<Change<Option<CountryTag>>>,
+    pub economic_hegemon: Option<Change<Option<CountryTag>>>,
+}
+
+impl SaveDiff {
+    /// Compares an older save with a newer save. Both saves must come from
+    /// the same campaign.
+    pub fn compare(old: &Eu4Save, new: &Eu4Save) -> Result<Self, Eu4Error> {
+        if old.meta.campaign_id != new.meta.campaign_id {
+            return Err(Eu4Error::new(Eu4ErrorKind::CampaignMismatch(
+                old.meta.campaign_id.clone(),
+                new.meta.campaign_id.clone(),
+            )));
+        }
+
+        let (countries_appeared, countries_disappeared) =
+            diff_alive_countries(&old.game.countries, &new.game.countries);
+        let (wars_started, wars_ended) = diff_wars(&old.game, &new.game);
+
+        Ok(SaveDiff {
+            old_date: old.meta.date,
+            new_date: new.meta.date,
+            provinces: diff_provinces(&old.game.provinces, &new.game.provinces),
+            countries_appeared,
+            countries_disappeared,
+            countries: diff_countries(&old.game.countries, &new.game.countries),
+            wars_started,
+            wars_ended,
+            new_relationships: diff_relationships(&old.game.diplomacy, &new.game.diplomacy),
+            emperor: Change::between(emperor(&old.game), emperor(&new.game)),
+            papal_controller: Change::between(
+                papal_controller(&old.game),
+                papal_controller(&new.game),
+            ),
+            military_hegemon: diff_hegemon(&old.game.military_hegemon, &new.game.military_hegemon),
+            naval_hegemon: diff_hegemon(&old.game.naval_hegemon, &new.game.naval_hegemon),
+            economic_hegemon: diff_hegemon(&old.game.economic_hegemon, &new.game.economic_hegemon),
+        })
+    }
+}
+
+fn development(province: &Province) -> f32 {
+    province.base_tax + province.base_production + province.base_manpower
+}
+
+fn diff_provinces(
+    old: &HashMap<ProvinceId, Province>,
+    new: &HashMap<ProvinceId, Province>,
+) -> Vec<ProvinceDiff> {
+    let mut result = new
+        .iter()
+        .filter_map(|(id, new)| {
+            let old = old.get(id)?;
+            let diff = ProvinceDiff {
+                id: *id,
+                owner: Change::between(old.owner, new.owner),
+                controller: Change::between(old.controller, new.controller),
+                culture: Change::between(old.culture.clone(), new.culture.clone()),
+                religion: Change::between(old.religion.clone(), new.religion.clone()),
+                development: Change::between(development(old), development(new)),
+            };
+
+            let changed = diff.owner.is_some()
+                || diff.controller.is_some()
+                || diff.culture.is_some()
+                || diff.religion.is_some()
+                || diff.development.is_some();
+            changed.then_some(diff)
+        })
+        .collect::<Vec<_>>();
+
+    result.sort_unstable_by_key(|x| x.id);
+    result
+}
+
+fn alive_countries(countries: &[(CountryTag, Country)]) -> HashSet<CountryTag> {
+    countries
+        .iter()
+        .filter(|(_, country)| country.num_of_cities > 0)
+        .map(|(tag, _)| *tag)
+        .collect()
+}
+
+fn diff_alive_countries(
+    old: &[(CountryTag, Country)],
+    new: &[(CountryTag, Country)],
+) -> (Vec<CountryTag>, Vec<CountryTag>) {
+    let old_alive = alive_countries(old);
+    let new_alive = alive_countries(new);
+    let mut appeared = new_alive
+        .difference(&old_alive)
+        .copied()
+        .collect::<Vec<_>>();
+    let mut disappeared = old_alive
+        .difference(&new_alive)
+        .copied()
+        .collect::<Vec<_>>();
+    appeared.sort_unstable();
+    disappeared.sort_unstable();
+    (appeared, disappeared)
+}
+
+fn diff_countries(
+    old: &[(CountryTag, Country)],
+    new: &[(CountryTag, Country)],
+) -> Vec<CountryDiff> {
+    let old = old
+        .iter()
+        .filter(|(_, country)| country.num_of_cities > 0)
+        .map(|(tag, country)| (*tag, country))
+        .collect::<HashMap<_, _>>();
+
+    let mut result = new
+        .iter()
+        .filter(|(_, country)| country.num_of_cities > 0)
+        .filter_map(|(tag, new)| {
+            let old = old.get(tag)?;
+            let diff = CountryDiff {
+                tag: *tag,
+                treasury: Change::between(old.treasury, new.treasury),
+                manpower: Change::between(old.manpower, new.manpower),
+                adm_tech: Change::between(old.technology.adm_tech, new.technology.adm_tech),
+                dip_tech: Change::between(old.technology.dip_tech, new.technology.dip_tech),
+                mil_tech: Change::between(old.technology.mil_tech, new.technology.mil_tech),
+            };
+
+            let changed = diff.treasury.is_some()
+                || diff.manpower.is_some()
+                || diff.adm_tech.is_some()
+                || diff.dip_tech.is_some()
+                || diff.mil_tech.is_some();
+            changed.then_some(diff)
+        })
+        .collect::<Vec<_>>();
+
+    result.sort_unstable_by_key(|x| x.tag);
+    result
+}
+
+fn diff_wars(old: &GameState, new: &GameState) -> (Vec<String>, Vec<String>) {
+    let old_wars = old
+        .active_wars
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<HashSet<_>>();
+    let new_wars = new
+        .active_wars
+        .iter()
+        .map(|x| x.name.as_str())
+        .collect::<HashSet<_>>();
+
+    let started = new
+        .active_wars
+        .iter()
+        .filter(|x| !old_wars.contains(x.name.as_str()))
+        .map(|x| x.name.clone())
+        .collect();
+
+    let ended = old
+        .active_wars
+        .iter()
+        .filter(|x| !new_wars.contains(x.name.as_str()))
+        .map(|x| x.name.clone())
+        .collect();
+
+    (started, ended)
+}
+
+fn relationships(diplomacy: &Diplomacy) -> Vec<Relationship> {
+    fn pairs<'a, T: 'a>(
+        items: &'a [T],
+        kind: RelationshipKind,
+        tags: impl Fn(&T) -> (CountryTag, CountryTag) + 'a,
+    ) -> impl Iterator<Item = Relationship> + 'a {
+        items.iter().map(move |x| {
+            let (first, second) = tags(x);
+            Relationship {
+                kind: kind.clone(),
+                first,
+                second,
+            }
+        })
+    }
+
+    use RelationshipKind as Kind;
+    let dependencies = diplomacy.dependencies.iter().map(|x| Relationship {
+        kind: Kind::Dependency(x.subject_type.clone()),
+        first: x.first,
+        second: x.second,
+    });
+
+    dependencies
+        .chain(pairs(&diplomacy.alliances, Kind::Alliance, |x| {
+            (x.first, x.second)
+        }))
+        .chain(pairs(
+            &diplomacy.royal_marriages,
+            Kind::RoyalMarriage,
+            |x| (x.first, x.second),
+        ))
+        .chain(pairs(&diplomacy.warnings, Kind::Warning, |x| {
+            (x.first, x.second)
+        }))
+        .chain(pairs(&diplomacy.subsidies, Kind::Subsidy, |x| {
+            (x.first, x.second)
+        }))
+        .chain(pairs(&diplomacy.guarantees, Kind::Guarantee, |x| {
+            (x.first, x.second)
+        }))
+        .chain(pairs(
+            &diplomacy.transfer_trade_powers,
+            Kind::TransferTradePower,
+            |x| (x.first, x.second),
+        ))
+        .chain(pairs(
+            &diplomacy.war_reparations,
+            Kind::WarReparations,
+            |x| (x.first, x.second),
+        ))
+        .chain(pairs(&diplomacy.steer_trades, Kind::SteerTrade, |x| {
+            (x.first, x.second)
+        }))
+        .chain(pairs(&diplomacy.condottieris, Kind::Condottieri, |x| {
+            (x.first, x.second)
+        }))
+        .collect()
+}
+
+fn diff_relationships(old: &Diplomacy, new: &Diplomacy) -> Vec<Relationship> {
+    let old = relationships(old);
+    relationships(new)
+        .into_iter()
+        .filter(|x| !old.contains(x))
+        .collect()
+}
+
+fn emperor(game: &GameState) -> Option<CountryTag> {
+    game.empire.as_ref().and_then(|x| x.emperor)
+}
+
+fn papal_controller(game: &GameState) -> Option<CountryTag> {
+    game.religion_instance_data
+        .values()
+        .find_map(|x| x.papacy.as_ref())
+        .map(|x| x.controller)
+}
+
+fn diff_hegemon(
+    old: &Option<Hegemon>,
+    new: &Option<Hegemon>,
+) -> Option<Change<Option<CountryTag>>> {
+    Change::between(
+        old.as_ref().map(|x| x.country),
+        new.as_ref().map(|x| x.country),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_provinces() {
+        let old = Province {
+            owner: Some("SWE".parse().unwrap()),
+            controller: Some("SWE".parse().unwrap()),
+            culture: Some(String::from("swedish")),
+            base_tax: 3.0,
+            ..Default::default()
+        };
+
+        let new = Province {
+            owner: Some("DAN".parse().unwrap()),
+            base_tax: 4.0,
+            ..old.clone()
+        };
+
+        let id = ProvinceId::from(1);
+        let old = HashMap::from([(id, old.clone()), (ProvinceId::from(2), old)]);
+        let new = HashMap::from([(id, new), (ProvinceId::from(2), old[&id].clone())]);
+        let diff = diff_provinces(&old, &new);
+        assert_eq!(
+            diff,
+            vec![ProvinceDiff {
+                id,
+                owner: Some(Change {
+                    old: Some("SWE".parse().unwrap()),
+                    new: Some("DAN".parse().unwrap()),
+                }),
+                controller: None,
+                culture: None,
+                religion: None,
+                development: Some(Change { old: 3.0, new: 4.0 }),
+            }]
+        );
+        assert_eq!(diff[0].development.as_ref().map(Change::delta), Some(1.0));
+    }
+
+    #[test]
+    fn test_diff_relationships() {
+        let old = b"alliance={ first=ENG second=POR }";
+        let new = b"alliance={ first=ENG second=POR }\ndependency={ first=ENG second=WLS subject_type=\"vassal\" }\nguarantee={ first=FRA second=SCO }";
+        let old: Diplomacy = jomini::text::de::from_windows1252_slice(old).unwrap();
+        let new: Diplomacy = jomini::text::de::from_windows1252_slice(new).unwrap();
+
+        let tag = |x: &str| x.parse::<CountryTag>().unwrap();
+        assert_eq!(
+            diff_relationships(&old, &new),
+            vec![
+                Relationship {
+                    kind: RelationshipKind::Dependency(String::from("vassal")),
+                    first: tag("ENG"),
+                    second: tag("WLS"),
+                },
+                Relationship {
+                    kind: RelationshipKind::Guarantee,
+                    first: tag("FRA"),
+                    second: tag("SCO"),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,546 @@
+use crate::{file::Eu4SliceFile, Eu4Error, Eu4ErrorKind};
+use jomini::{
+    binary::{Lexer, Token, TokenResolver},
+    TextTape, TextToken, Windows1252Encoding,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+/// How many entries ahead to look for a matching key when the fields of an
+/// object fall out of step between the two documents
+const RESYNC_WINDOW: usize = 8;
+
+/// A suggested name for a binary token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenCandidate {
+    /// The binary token
+    pub token: u16,
+
+    /// The name most often aligned with the token
+    pub name: String,
+
+    /// Number of times the token aligned with the name
+    pub votes: u32,
+
+    /// Number of times the token aligned with any name
+    pub total: u32,
+}
+
+/// Formats the candidate as a line that
+/// [`SegmentedResolver::parse`](crate::SegmentedResolver::parse) reads
+impl fmt::Display for TokenCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:04x} {}", self.token, self.name)
+    }
+}
+
+/// Infers the names of binary tokens the resolver does not know by aligning
+/// a binary save with a text save of the same game state (eg: the same save
+/// written with and without ironman).
+///
+/// The two documents are walked structurally in lockstep, and wherever an
+/// unresolved token sits in the binary document, the name in the same
+/// position of the text document is a vote for the token's name.
+///
+/// ```rust
+/// use eu4save::{discover::TokenDiscovery, SegmentedResolver};
+/// use jomini::binary::Token;
+///
+/// let mut binary = Vec::new();
+/// for token in [Token::Id(0x2d82), Token::Equal, Token::Id(0x2d83)] {
+///     token.write(&mut binary)?;
+/// }
+///
+/// let mut discovery = TokenDiscovery::new(SegmentedResolver::empty());
+/// discovery.align(&binary, b"religion=catholic")?;
+/// let lines = discovery
+///     .candidates()
+///     .iter()
+///     .map(|x| x.to_string())
+///     .collect::<Vec<_>>();
+/// assert_eq!(lines, vec!["0x2d82 religion", "0x2d83 catholic"]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct TokenDiscovery<R> {
+    resolver: R,
+    votes: HashMap<u16, HashMap<String, u32>>,
+}
+
+impl<R> TokenDiscovery<R>
+where
+    R: TokenResolver,
+{
+    pub fn new(resolver: R) -> Self {
+        TokenDiscovery {
+            resolver,
+            votes: HashMap::new(),
+        }
+    }
+
+    /// Aligns a binary save with a text save of the same game state. The
+    /// saves may be zipped or not.
+    pub fn align_files(
+        &mut self,
+        binary: &Eu4SliceFile,
+        text: &Eu4SliceFile,
+    ) -> Result<(), Eu4Error> {
+        if !binary.encoding().is_binary() || text.encoding().is_binary() {
+            return Err(Eu4Error::new(Eu4ErrorKind::DiscoveryEncoding {
+                binary: binary.encoding(),
+                text: text.encoding(),
+            }));
+        }
+
+        self.align(&binary.body()?, &text.body()?)
+    }
+
+    /// Aligns the body of a binary document with the body of the text
+    /// document of the same data. Headers are skipped if present.
+    pub fn align(&mut self, binary: &[u8], text: &[u8]) -> Result<(), Eu4Error> {
+        let binary = binary.strip_prefix(b"EU4bin").unwrap_or(binary);
+        let text = text.strip_prefix(b"EU4txt").unwrap_or(text);
+        let binary = binary_nodes(binary)?;
+        let tape = TextTape::from_slice(text)?;
+        let text = text_nodes(tape.tokens());
+
+        let resolver = &self.resolver;
+        let known = binary
+            .iter()
+            .filter_map(|node| match node {
+                Node::Token(id) => resolver.resolve(*id).map(str::as_bytes),
+                _ => None,
+            })
+            .collect();
+
+        let mut aligner = Aligner {
+            binary: &binary,
+            text: &text,
+            resolver,
+            known,
+            votes: &mut self.votes,
+        };
+        aligner.object(0, binary.len(), 0, text.len());
+        Ok(())
+    }
+
+    /// The most voted name of each unresolved token, ordered by token
+    pub fn candidates(&self) -> Vec<TokenCandidate> {
+        let mut result = self
+            .votes
+            .iter()
+            .filter_map(|(&token, names)| {
+                let total = names.values().sum();
+                names
+                    .iter()
+                    .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+                    .map(|(name, &votes)| TokenCandidate {
+                        token,
+                        name: name.clone(),
+                        votes,
+                        total,
+                    })
+            })
+            .collect::<Vec<_>>();
+        result.sort_by_key(|x| x.token);
+        result
+    }
+}
+
+/// A flattened document where the equal operators are elided, so objects
+/// alternate between keys and values
+#[derive(Debug, Clone, Copy)]
+enum Node<'a> {
+    /// Index of the matching end node
+    Container {
+        end: usize,
+        object: bool,
+    },
+    End,
+    Text(&'a [u8]),
+    Token(u16),
+    Other,
+}
+
+fn binary_nodes(data: &[u8]) -> Result<Vec<Node<'_>>, Eu4Error> {
+    let mut lexer = Lexer::new(data);
+    let mut nodes = Vec::new();
+
+    // The index of each open container and whether an equal was seen
+    // directly within it
+    let mut stack: Vec<(usize, bool)> = Vec::new();
+    while let Some(token) = lexer.next_token().map_err(jomini::Error::from)? {
+        let node = match token {
+            Token::Open => {
+                stack.push((nodes.len(), false));
+                Node::Container {
+                    end: 0,
+                    object: false,
+                }
+            }
+            Token::Close => {
+                let Some((start, object)) = stack.pop() else {
+                    continue;
+                };
+                nodes[start] = Node::Container {
+                    end: nodes.len(),
+                    object,
+                };
+                Node::End
+            }
+            Token::Equal => {
+                if let Some(last) = stack.last_mut() {
+                    last.1 = true;
+                }
+                continue;
+            }
+            Token::Id(id) => Node::Token(id),
+            Token::Quoted(x) | Token::Unquoted(x) => Node::Text(x.as_bytes()),
+            _ => Node::Other,
+        };
+        nodes.push(node);
+    }
+
+    // Gracefully handle containers left open at the end of the document
+    while let Some((start, object)) = stack.pop() {
+        nodes[start] = Node::Container {
+            end: nodes.len(),
+            object,
+        };
+        nodes.push(Node::End);
+    }
+
+    Ok(nodes)
+}
+
+fn text_nodes<'a>(tokens: &[TextToken<'a>]) -> Vec<Node<'a>> {
+    let mut nodes = Vec::with_capacity(tokens.len());
+    let mut starts = Vec::new();
+    let mut ind = 0;
+    while let Some(token) = tokens.get(ind) {
+        ind += 1;
+        let node = match token {
+            TextToken::Object { .. } | TextToken::Array { .. } => {
+                starts.push(nodes.len());
+                Node::Container {
+                    end: 0,
+                    object: matches!(token, TextToken::Object { .. }),
+                }
+            }
+            TextToken::End(_) => {
+                let Some(start) = starts.pop() else {
+                    continue;
+                };
+                if let Node::Container { object, .. } = nodes[start] {
+                    nodes[start] = Node::Container {
+                        end: nodes.len(),
+                        object,
+                    };
+                }
+                Node::End
+            }
+            TextToken::Unquoted(x) | TextToken::Quoted(x) => Node::Text(x.as_bytes()),
+
+            // Headers like `rgb { 1 2 3 }` are a single value in binary
+            TextToken::Header(_) => {
+                if let Some(TextToken::Array { end, .. }) = tokens.get(ind) {
+                    ind = end + 1;
+                }
+                Node::Other
+            }
+            TextToken::Operator(_) | TextToken::MixedContainer => continue,
+            _ => Node::Other,
+        };
+        nodes.push(node);
+    }
+
+    nodes
+}
+
+struct Aligner<'a, 'b, R> {
+    binary: &'a [Node<'a>],
+    text: &'a [Node<'b>],
+    resolver: &'a R,
+
+    // Names of the tokens that resolved in the binary document, which can't
+    // be the name of an unknown token
+    known: HashSet<&'a [u8]>,
+    votes: &'a mut HashMap<u16, HashMap<String, u32>>,
+}
+
+/// How well two keys or scalar values line up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fit {
+    /// The binary token is known and is the text
+    Exact,
+
+    /// The binary token is unknown and the text could be its name
+    Candidate,
+    Mismatch,
+}
+
+impl<R: TokenResolver> Aligner<'_, '_, R> {
+    /// Returns the index after the value starting at the index
+    fn skip(nodes: &[Node], ind: usize) -> usize {
+        match nodes[ind] {
+            Node::Container { end, .. } => end + 1,
+            _ => ind + 1,
+        }
+    }
+
+    /// Collects the indices of each (key, value) pair in an object
+    fn entries(nodes: &[Node], mut ind: usize, end: usize) -> Vec<(usize, usize)> {
+        let mut result = Vec::new();
+        while ind < end {
+            let key = ind;
+            let value = Self::skip(nodes, key);
+            if value >= end {
+                break;
+            }
+            ind = Self::skip(nodes, value);
+            result.push((key, value));
+        }
+        result
+    }
+
+    fn values(nodes: &[Node], mut ind: usize, end: usize) -> Vec<usize> {
+        let mut result = Vec::new();
+        while ind < end {
+            result.push(ind);
+            ind = Self::skip(nodes, ind);
+        }
+        result
+    }
+
+    fn fit(&self, binary: Node, text: Node) -> Fit {
+        let Node::Text(text) = text else {
+            let same_shape = matches!(
+                (binary, text),
+                (Node::Other, Node::Other) | (Node::Container { .. }, Node::Container { .. })
+            );
+            return if same_shape {
+                Fit::Exact
+            } else {
+                Fit::Mismatch
+            };
+        };
+
+        match binary {
+            Node::Token(id) => match self.resolver.resolve(id) {
+                Some(name) if name.as_bytes() == text => Fit::Exact,
+                Some(_) => Fit::Mismatch,
+                None if is_identifier(text) && !self.known.contains(text) => Fit::Candidate,
+                None => Fit::Mismatch,
+            },
+            Node::Text(x) if x == text => Fit::Exact,
+            Node::Other if !is_identifier(text) => Fit::Exact,
+            _ => Fit::Mismatch,
+        }
+    }
+
+    /// Checks that the keys and the shape of the values line up
+    fn entry_fit(&self, binary: (usize, usize), text: (usize, usize)) -> Fit {
+        let container = |node| matches!(node, Node::Container { .. });
+        if container(self.binary[binary.1]) != container(self.text[text.1]) {
+            return Fit::Mismatch;
+        }
+
+        self.fit(self.binary[binary.0], self.text[text.0])
+    }
+
+    fn vote(&mut self, binary: usize, text: usize) {
+        if self.fit(self.binary[binary], self.text[text]) != Fit::Candidate {
+            return;
+        }
+
+        if let (Node::Token(id), Node::Text(name)) = (self.binary[binary], self.text[text]) {
+            let name = Windows1252Encoding::decode(name).into_owned();
+            *self.votes.entry(id).or_default().entry(name).or_default() += 1;
+        }
+    }
+
+    fn value(&mut self, binary: usize, text: usize) {
+        match (self.binary[binary], self.text[text]) {
+            (
+                Node::Container { end: bend, object },
+                Node::Container {
+                    end: tend,
+                    object: tobject,
+                },
+            ) if object == tobject => {
+                if object {
+                    self.object(binary + 1, bend, text + 1, tend);
+                } else {
+                    self.array(binary + 1, bend, text + 1, tend);
+                }
+            }
+            (Node::Container { .. }, _) | (_, Node::Container { .. }) => {}
+            _ => self.vote(binary, text),
+        }
+    }
+
+    fn array(&mut self, bstart: usize, bend: usize, tstart: usize, tend: usize) {
+        let binary = Self::values(self.binary, bstart, bend);
+        let text = Self::values(self.text, tstart, tend);
+
+        // Without keys to anchor on, only arrays of the same length are
+        // trusted to line up
+        if binary.len() != text.len() {
+            return;
+        }
+
+        for (b, t) in binary.into_iter().zip(text) {
+            self.value(b, t);
+        }
+    }
+
+    fn object(&mut self, bstart: usize, bend: usize, tstart: usize, tend: usize) {
+        let binary = Self::entries(self.binary, bstart, bend);
+        let text = Self::entries(self.text, tstart, tend);
+
+        let (mut i, mut j) = (0, 0);
+        while i < binary.len() && j < text.len() {
+            if self.entry_fit(binary[i], text[j]) != Fit::Mismatch {
+                self.vote(binary[i].0, text[j].0);
+                self.value(binary[i].1, text[j].1);
+                i += 1;
+                j += 1;
+                continue;
+            }
+
+            // A field is missing from one of the documents, so skip ahead to
+            // the closest pair of entries that line up
+            let resync = (1..=RESYNC_WINDOW).find_map(|d| {
+                let fits = |b: usize, t: usize| {
+                    b < binary.len()
+                        && t < text.len()
+                        && self.entry_fit(binary[b], text[t]) != Fit::Mismatch
+                };
+
+                if fits(i + d, j) {
+                    Some((i + d, j))
+                } else if fits(i, j + d) {
+                    Some((i, j + d))
+                } else {
+                    None
+                }
+            });
+
+            (i, j) = resync.unwrap_or((i + 1, j + 1));
+        }
+    }
+}
+
+/// If the text could be the name of a token
+fn is_identifier(data: &[u8]) -> bool {
+    match data.split_first() {
+        Some((first, rest)) => {
+            (first.is_ascii_alphabetic() || *first == b'_')
+                && rest
+                    .iter()
+                    .all(|x| x.is_ascii_alphanumeric() || matches!(x, b'_' | b'.' | b'-' | b':'))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unmelt::unmelt, Encoding, SegmentedResolver};
+
+    fn resolver() -> SegmentedResolver<'static> {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "date";
+        values[0x21] = "provinces";
+        values[0x22] = "owner";
+        values[0x23] = "cores";
+        values[0x24] = "color";
+        SegmentedResolver::from_parts(values, 0x30, 0x30)
+    }
+
+    fn full_resolver() -> SegmentedResolver<'static> {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "date";
+        values[0x21] = "provinces";
+        values[0x22] = "owner";
+        values[0x23] = "cores";
+        values[0x24] = "color";
+        values[0x25] = "religion";
+        values[0x26] = "catholic";
+        values[0x27] = "hre";
+        values[0x28] = "history";
+        SegmentedResolver::from_parts(values, 0x30, 0x30)
+    }
+
+    #[test]
+    fn test_discover_tokens() {
+        let text = b"date=1444.11.11\nprovinces={\n\t-1={\n\t\towner=\"ENG\"\n\t\treligion=catholic\n\t\tcores={\n\t\t\tENG\n\t\t}\n\t\tcolor=rgb {\n\t\t\t10 20 30\n\t\t}\n\t\thre=yes\n\t}\n}\nhistory={ }";
+        let full = full_resolver();
+        let mut binary = Vec::new();
+        unmelt(text, &mut binary, full.encoder()).unwrap();
+
+        let mut discovery = TokenDiscovery::new(resolver());
+        discovery.align(&binary, text).unwrap();
+        let lines = discovery
+            .candidates()
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "0x0025 religion",
+                "0x0026 catholic",
+                "0x0027 hre",
+                "0x0028 history"
+            ]
+        );
+
+        let builder = SegmentedResolver::parse(lines.join("\n").as_bytes()).unwrap();
+        assert_eq!(builder.resolver().resolve(0x27), Some("hre"));
+    }
+
+    #[test]
+    fn test_discover_files_encoding() {
+        let text = b"EU4txt\ndate=1444.11.11";
+        let file = crate::Eu4File::from_slice(&text[..]).unwrap();
+        let mut discovery = TokenDiscovery::new(resolver());
+        let err = discovery.align_files(&file, &file).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Eu4ErrorKind::DiscoveryEncoding {
+                binary: Encoding::Text,
+                text: Encoding::Text
+            }
+        ));
+    }
+
+    #[test]
+    fn test_discover_resyncs_missing_fields() {
+        let full = full_resolver();
+        let mut binary = Vec::new();
+        let data =
+            b"provinces={ -1={ owner=ENG religion=catholic } -2={ religion=catholic hre=yes } }";
+        unmelt(data, &mut binary, full.encoder()).unwrap();
+
+        // The text has fields that the binary is missing
+        let text = b"provinces={ -1={ owner=ENG religion=catholic } -2={ owner=ENG cores={ ENG } religion=catholic hre=yes } }";
+        let mut discovery = TokenDiscovery::new(resolver());
+        discovery.align(&binary, text).unwrap();
+        let names = discovery
+            .candidates()
+            .into_iter()
+            .map(|x| (x.token, x.name, x.votes))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                (0x25, String::from("religion"), 2),
+                (0x26, String::from("catholic"), 2),
+                (0x27, String::from("hre"), 1),
+            ]
+        );
+    }
+}
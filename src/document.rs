@@ -0,0 +1,1005 @@
+//! An untyped and mutable tree of an EU4 text document
+//!
+//! Unlike the `models`, a document retains every field of the save, so it can
+//! be edited and written back out as EU4txt that the game can load.
+//!
+//! ```rust
+//! use eu4save::{document::{Eu4Document, Value}, CountryTag};
+//! let data = b"EU4txt\ncountries={\n\tENG={\n\t\ttreasury=10.000\n\t\tcustom=yes\n\t}\n}";
+//! let mut doc = Eu4Document::from_slice(&data[..])?;
+//! let eng = doc.country_mut(&"ENG".parse()?).unwrap();
+//! eng.set("treasury", Value::from(250.5));
+//!
+//! let mut out = Vec::new();
+//! doc.write(&mut out)?;
+//! let expected = "EU4txt\ncountries={\n\tENG={\n\t\ttreasury=250.500\n\t\tcustom=yes\n\t}\n}";
+//! assert_eq!(std::str::from_utf8(&out)?, expected);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! The document is lossless. Whitespace and the layout of the original text
+//! is retained, so the output is byte for byte identical to the input until a
+//! field is modified, and even then only the modified fields are reformatted.
+//!
+//! Values can also be addressed by a dot delimited path. A segment matches
+//! the longest key available, so keys that contain dots (like dates) can be
+//! referenced without any escaping. A segment may end with `[n]` to select
+//! the nth field of a key that occurs multiple times, and within an array, a
+//! segment is the index of the element.
+//!
+//! ```rust
+//! use eu4save::document::{Eu4Document, Value};
+//! let data = b"EU4txt\nprovinces={\n\t-1={\n\t\towner=\"SWE\"\n\t\thistory={\n\t\t\t1500.1.1={\n\t\t\t\towner=\"DAN\"\n\t\t\t}\n\t\t}\n\t}\n}";
+//! let mut doc = Eu4Document::from_slice(&data[..])?;
+//! let owner = doc.get_path("provinces.-1.history.1500.1.1.owner");
+//! assert_eq!(owner.and_then(Value::as_str).as_deref(), Some("DAN"));
+//!
+//! doc.set_path("provinces.-1.owner", Value::from("NOR"))?;
+//! let mut out = Vec::new();
+//! doc.write(&mut out)?;
+//! let expected = std::str::from_utf8(data)?.replacen("SWE", "NOR", 1);
+//! assert_eq!(std::str::from_utf8(&out)?, expected);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use crate::{
+    file::Eu4ParsedText, flavor::encode_windows1252, CountryTag, Eu4Date, Eu4Error, Eu4ErrorKind,
+    PdsDate, ProvinceId,
+};
+use jomini::{Encoding, Scalar, TextToken, Windows1252Encoding};
+use std::{borrow::Cow, io::Write};
+
+/// A mutable EU4 text document
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Eu4Document {
+    source: Vec<u8>,
+    root: Object,
+}
+
+impl Eu4Document {
+    /// Parse EU4 text data that has the "EU4txt" header
+    pub fn from_slice(data: &[u8]) -> Result<Self, Eu4Error> {
+        let text = Eu4ParsedText::from_slice(data)?;
+        Self::from_parsed(&text)
+    }
+
+    /// Create a document from already parsed text
+    pub fn from_parsed(text: &Eu4ParsedText) -> Result<Self, Eu4Error> {
+        let source = text.data();
+        let tokens = text.tape().tokens();
+        let parser = Parser { source, tokens };
+        let (mut root, _) = parser.parse_object(0, tokens.len(), 0)?;
+        root.span = Some(Span::new(0, source.len()));
+        Ok(Eu4Document {
+            source: source.to_vec(),
+            root,
+        })
+    }
+
+    /// The top level fields of the document
+    pub fn root(&self) -> &Object {
+        &self.root
+    }
+
+    /// The mutable top level fields of the document
+    pub fn root_mut(&mut self) -> &mut Object {
+        self.root.span = None;
+        &mut self.root
+    }
+
+    /// Return the country object for the given tag
+    pub fn country(&self, tag: &CountryTag) -> Option<&Object> {
+        self.root
+            .get("countries")
+            .and_then(Value::as_object)
+            .and_then(|x| x.get(tag.as_str()))
+            .and_then(Value::as_object)
+    }
+
+    /// Return the mutable country object for the given tag
+    pub fn country_mut(&mut self, tag: &CountryTag) -> Option<&mut Object> {
+        self.country(tag)?;
+        self.root_mut()
+            .get_mut("countries")
+            .and_then(Value::as_object_mut)
+            .and_then(|x| x.get_mut(tag.as_str()))
+            .and_then(Value::as_object_mut)
+    }
+
+    /// Return the province object for the given id
+    pub fn province(&self, id: &ProvinceId) -> Option<&Object> {
+        self.root
+            .get("provinces")
+            .and_then(Value::as_object)
+            .and_then(|x| x.get(&province_key(id)))
+            .and_then(Value::as_object)
+    }
+
+    /// Return the mutable province object for the given id
+    pub fn province_mut(&mut self, id: &ProvinceId) -> Option<&mut Object> {
+        self.province(id)?;
+        self.root_mut()
+            .get_mut("provinces")
+            .and_then(Value::as_object_mut)
+            .and_then(|x| x.get_mut(&province_key(id)))
+            .and_then(Value::as_object_mut)
+    }
+
+    /// Return the first value at the path
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        self.root.path(path)
+    }
+
+    /// Return all the values at the path, which will be more than one when
+    /// the path crosses a key that occurs multiple times
+    pub fn get_path_all(&self, path: &str) -> Vec<&Value> {
+        self.root.path_all(path)
+    }
+
+    /// Return the first mutable value at the path
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        self.root.path(path)?;
+        self.root_mut().path_mut(path)
+    }
+
+    /// Overwrite the value at the path and return the previous value. If the
+    /// value doesn't exist, it is appended to its parent object, which must
+    /// already exist.
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<Option<Value>, Eu4Error> {
+        if let Some(existing) = self.get_path_mut(path) {
+            return Ok(Some(std::mem::replace(existing, value)));
+        }
+
+        // The new key is either the last segment or, when it is a date, the
+        // last three segments
+        let segments = path.split('.').collect::<Vec<_>>();
+        for split in (0..segments.len()).rev() {
+            let key = segments[split..].join(".");
+            if split + 1 < segments.len() && Eu4Date::parse(key.as_bytes()).is_err() {
+                continue;
+            }
+
+            let parent = if split == 0 {
+                Some(self.root_mut())
+            } else {
+                self.get_path_mut(&segments[..split].join("."))
+                    .and_then(Value::as_object_mut)
+            };
+
+            if let Some(parent) = parent {
+                return Ok(parent.set(&key, value));
+            }
+        }
+
+        Err(Eu4Error::new(Eu4ErrorKind::PathNotFound(String::from(
+            path,
+        ))))
+    }
+
+    /// Write the document as EU4txt (header included)
+    pub fn write<W: Write>(&self, mut output: W) -> Result<(), Eu4Error> {
+        output.write_all(b"EU4txt")?;
+        let mut writer = DocumentWriter {
+            source: &self.source,
+            output,
+        };
+
+        match self.root.span {
+            Some(span) => writer.write_span(span)?,
+            None => writer.write_fields(&self.root, 0)?,
+        }
+
+        Ok(())
+    }
+}
+
+fn province_key(id: &ProvinceId) -> String {
+    format!("-{}", id.as_u16())
+}
+
+/// A byte range of the source document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+}
+
+/// A key value pair within an object
+#[derive(Debug, Clone)]
+pub struct Field {
+    key: Vec<u8>,
+    quoted: bool,
+    value: Value,
+
+    // The source of the entire field, cleared when the field is modified
+    span: Option<Span>,
+
+    // Whitespace that preceded the key
+    leading: Option<Span>,
+
+    // The operator between the key and value (with surrounding whitespace)
+    separator: Option<Span>,
+}
+
+impl Field {
+    /// Create a field with an unquoted key
+    pub fn new(key: &str, value: Value) -> Self {
+        Field {
+            key: encode_windows1252(key),
+            quoted: false,
+            value,
+            span: None,
+            leading: None,
+            separator: None,
+        }
+    }
+
+    /// The decoded key
+    pub fn key(&self) -> Cow<'_, str> {
+        Windows1252Encoding::new().decode(&self.key)
+    }
+
+    /// If the key is surrounded in quotes
+    pub fn is_quoted(&self) -> bool {
+        self.quoted
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut Value {
+        self.span = None;
+        &mut self.value
+    }
+}
+
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key && self.quoted == other.quoted && self.value == other.value
+    }
+}
+
+/// An ordered sequence of fields where keys may be repeated
+#[derive(Debug, Clone, Default)]
+pub struct Object {
+    fields: Vec<Field>,
+
+    // The source of the entire object, cleared when the object is modified
+    span: Option<Span>,
+
+    // Whitespace that preceded the closing brace
+    trailing: Option<Span>,
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn fields_mut(&mut self) -> &mut Vec<Field> {
+        self.span = None;
+        for field in self.fields.iter_mut() {
+            field.span = None;
+        }
+        &mut self.fields
+    }
+
+    /// Return the value of the first field with the given key
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.position(key, 0).map(|i| &self.fields[i].value)
+    }
+
+    /// Return the mutable value of the first field with the given key
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        let i = self.position(key, 0)?;
+        Some(self.field_mut(i))
+    }
+
+    /// Return the values of all fields with the given key
+    pub fn get_all<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a Value> + 'a {
+        let key = encode_windows1252(key);
+        self.fields
+            .iter()
+            .filter(move |x| x.key == key)
+            .map(|x| &x.value)
+    }
+
+    /// Overwrite the value of the first field with the given key, or append
+    /// the field if it doesn't exist. The previous value is returned.
+    pub fn set(&mut self, key: &str, value: Value) -> Option<Value> {
+        match self.get_mut(key) {
+            Some(x) => Some(std::mem::replace(x, value)),
+            None => {
+                self.push(key, value);
+                None
+            }
+        }
+    }
+
+    /// Append a field, even if the key already exists
+    pub fn push(&mut self, key: &str, value: Value) {
+        self.span = None;
+        self.fields.push(Field::new(key, value));
+    }
+
+    /// Remove all fields with the given key and return how many were removed
+    pub fn remove(&mut self, key: &str) -> usize {
+        self.span = None;
+        let key = encode_windows1252(key);
+        let len = self.fields.len();
+        self.fields.retain(|x| x.key != key);
+        len - self.fields.len()
+    }
+
+    /// Return the first value at the path relative to this object
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        self.path_all(path).into_iter().next()
+    }
+
+    /// Return all the values at the path relative to this object
+    pub fn path_all(&self, path: &str) -> Vec<&Value> {
+        let segments = path.split('.').collect::<Vec<_>>();
+        let mut result = Vec::new();
+        self.collect_path(&segments, &mut result);
+        result
+    }
+
+    /// Return the first mutable value at the path relative to this object
+    pub fn path_mut(&mut self, path: &str) -> Option<&mut Value> {
+        // Only mark the fields along the path as modified when it exists
+        self.path(path)?;
+        let segments = path.split('.').collect::<Vec<_>>();
+        self.path_segments_mut(&segments)
+    }
+
+    fn position(&self, key: &str, nth: usize) -> Option<usize> {
+        let key = encode_windows1252(key);
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| x.key == key)
+            .nth(nth)
+            .map(|(i, _)| i)
+    }
+
+    fn field_mut(&mut self, index: usize) -> &mut Value {
+        self.span = None;
+        self.fields[index].value_mut()
+    }
+
+    fn collect_path<'a>(&'a self, segments: &[&str], result: &mut Vec<&'a Value>) {
+        for split in (1..=segments.len()).rev() {
+            let (key, nth) = path_key(&segments[..split]);
+            let key = encode_windows1252(&key);
+            let mut values = self.fields.iter().filter(|x| x.key == key);
+            let first = values.next();
+            if first.is_none() {
+                continue;
+            }
+
+            let rest = &segments[split..];
+            let matched = std::iter::once(first.unwrap()).chain(values);
+            for (i, field) in matched.enumerate() {
+                if nth.is_none_or(|n| n == i) {
+                    field.value.collect_path(rest, result);
+                }
+            }
+            return;
+        }
+    }
+
+    fn path_segments_mut(&mut self, segments: &[&str]) -> Option<&mut Value> {
+        for split in (1..=segments.len()).rev() {
+            let (key, nth) = path_key(&segments[..split]);
+            if let Some(i) = self.position(&key, nth.unwrap_or(0)) {
+                return self.field_mut(i).path_segments_mut(&segments[split..]);
+            }
+        }
+
+        None
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.fields == other.fields
+    }
+}
+
+/// Joins path segments into a key and extracts the trailing index selector
+fn path_key(segments: &[&str]) -> (String, Option<usize>) {
+    let key = segments.join(".");
+    if let Some((base, index)) = key
+        .strip_suffix(']')
+        .and_then(|x| x.rsplit_once('['))
+        .and_then(|(base, index)| Some((base, index.parse::<usize>().ok()?)))
+    {
+        return (String::from(base), Some(index));
+    }
+
+    (key, None)
+}
+
+/// A value in the document
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// Unquoted scalar (eg: numbers, dates, `yes`, and identifiers)
+    Unquoted(Vec<u8>),
+
+    /// Quoted scalar with the escape sequences intact
+    Quoted(Vec<u8>),
+
+    Object(Object),
+
+    Array(Vec<Value>),
+
+    /// A header followed by an array (eg: `rgb { 10 20 30 }`)
+    Header(Vec<u8>, Vec<Value>),
+}
+
+impl Value {
+    /// Create an unquoted value
+    pub fn unquoted(data: &str) -> Self {
+        Value::Unquoted(encode_windows1252(data))
+    }
+
+    /// Create a quoted value, escaping quotes and backslashes
+    pub fn quoted(data: &str) -> Self {
+        let mut result = Vec::with_capacity(data.len());
+        for byte in encode_windows1252(data) {
+            if matches!(byte, b'"' | b'\\') {
+                result.push(b'\\');
+            }
+            result.push(byte);
+        }
+        Value::Quoted(result)
+    }
+
+    /// Return the decoded text of a scalar. Escape sequences of quoted values
+    /// are left intact.
+    pub fn as_str(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Value::Unquoted(x) | Value::Quoted(x) => Some(Windows1252Encoding::new().decode(x)),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_scalar().and_then(|x| x.to_i64().ok())
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_scalar().and_then(|x| x.to_f64().ok())
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        self.as_scalar().and_then(|x| x.to_bool().ok())
+    }
+
+    pub fn as_date(&self) -> Option<Eu4Date> {
+        self.as_scalar()
+            .and_then(|x| Eu4Date::parse(x.as_bytes()).ok())
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Value::Object(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut Object> {
+        match self {
+            Value::Object(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match self {
+            Value::Array(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    fn as_scalar(&self) -> Option<Scalar<'_>> {
+        match self {
+            Value::Unquoted(x) | Value::Quoted(x) => Some(Scalar::new(x)),
+            _ => None,
+        }
+    }
+
+    fn collect_path<'a>(&'a self, segments: &[&str], result: &mut Vec<&'a Value>) {
+        let Some((head, rest)) = segments.split_first() else {
+            result.push(self);
+            return;
+        };
+
+        match self {
+            Value::Object(obj) => obj.collect_path(segments, result),
+            Value::Array(values) => {
+                if let Some(value) = head.parse::<usize>().ok().and_then(|i| values.get(i)) {
+                    value.collect_path(rest, result);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn path_segments_mut(&mut self, segments: &[&str]) -> Option<&mut Value> {
+        let Some((head, rest)) = segments.split_first() else {
+            return Some(self);
+        };
+
+        match self {
+            Value::Object(obj) => obj.path_segments_mut(segments),
+            Value::Array(values) => {
+                let i = head.parse::<usize>().ok()?;
+                values.get_mut(i)?.path_segments_mut(rest)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::unquoted(if value { "yes" } else { "no" })
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::Unquoted(value.to_string().into_bytes())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Unquoted(value.to_string().into_bytes())
+    }
+}
+
+/// Floats are written with the 3 digits of precision that the game uses
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Unquoted(format!("{:.3}", value).into_bytes())
+    }
+}
+
+/// Floats are written with the 3 digits of precision that the game uses
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Unquoted(format!("{:.3}", value).into_bytes())
+    }
+}
+
+impl From<Eu4Date> for Value {
+    fn from(value: Eu4Date) -> Self {
+        Value::Unquoted(value.game_fmt().to_string().into_bytes())
+    }
+}
+
+impl From<CountryTag> for Value {
+    fn from(value: CountryTag) -> Self {
+        Value::quoted(value.as_str())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::quoted(value)
+    }
+}
+
+impl From<Object> for Value {
+    fn from(value: Object) -> Self {
+        Value::Object(value)
+    }
+}
+
+struct Parser<'a, 'b> {
+    source: &'a [u8],
+    tokens: &'b [TextToken<'a>],
+}
+
+impl Parser<'_, '_> {
+    /// Parses the fields of an object, with `pos` as the byte position
+    /// where the fields start. Returns the object and the byte position
+    /// where the last field ends.
+    fn parse_object(
+        &self,
+        mut ind: usize,
+        end: usize,
+        mut pos: usize,
+    ) -> Result<(Object, usize), Eu4Error> {
+        let mut fields = Vec::new();
+        while ind < end {
+            let (key, quoted) = match &self.tokens[ind] {
+                TextToken::Unquoted(x) => (*x, false),
+                TextToken::Quoted(x) => (*x, true),
+                _ => return Err(unsupported()),
+            };
+            let key_span = self.scalar_span(key, quoted)?;
+            ind += 1;
+
+            if let Some(TextToken::Operator(_)) = self.tokens.get(ind) {
+                ind += 1;
+            }
+
+            let (value, value_span, next) = self.parse_value(ind, key_span.end)?;
+            fields.push(Field {
+                key: key.as_bytes().to_vec(),
+                quoted,
+                value,
+                span: Some(Span::new(key_span.start, value_span.end)),
+                leading: Some(Span::new(pos, key_span.start)),
+                separator: Some(Span::new(key_span.end, value_span.start)),
+            });
+            pos = value_span.end;
+            ind = next;
+        }
+
+        let trailing = Some(Span::new(pos, self.source.len()));
+        let obj = Object {
+            fields,
+            span: None,
+            trailing,
+        };
+        Ok((obj, pos))
+    }
+
+    /// Parses the value at the token index, with `pos` as the byte position
+    /// to start searching for the value. Returns the value, its byte span,
+    /// and the next token index.
+    fn parse_value(&self, ind: usize, pos: usize) -> Result<(Value, Span, usize), Eu4Error> {
+        match self.tokens.get(ind) {
+            Some(TextToken::Unquoted(x)) => {
+                let span = self.scalar_span(*x, false)?;
+                Ok((Value::Unquoted(x.as_bytes().to_vec()), span, ind + 1))
+            }
+            Some(TextToken::Quoted(x)) => {
+                let span = self.scalar_span(*x, true)?;
+                Ok((Value::Quoted(x.as_bytes().to_vec()), span, ind + 1))
+            }
+            Some(TextToken::Object { end, mixed: false }) => {
+                let open = self.find_open(pos)?;
+                let (mut obj, last) = self.parse_object(ind + 1, *end, open + 1)?;
+                let close = self.find_close(last)?;
+                let span = Span::new(open, close + 1);
+                obj.trailing = Some(Span::new(last, close));
+                obj.span = Some(span);
+                Ok((Value::Object(obj), span, end + 1))
+            }
+            Some(TextToken::Array { end, mixed: false }) => {
+                let open = self.find_open(pos)?;
+                let (values, last) = self.parse_array(ind + 1, *end, open + 1)?;
+                let close = self.find_close(last)?;
+                Ok((Value::Array(values), Span::new(open, close + 1), end + 1))
+            }
+            Some(TextToken::Header(header)) => match self.tokens.get(ind + 1) {
+                Some(TextToken::Array { end, mixed: false }) => {
+                    let header_span = self.scalar_span(*header, false)?;
+                    let open = self.find_open(header_span.end)?;
+                    let (values, last) = self.parse_array(ind + 2, *end, open + 1)?;
+                    let close = self.find_close(last)?;
+                    let value = Value::Header(header.as_bytes().to_vec(), values);
+                    Ok((value, Span::new(header_span.start, close + 1), end + 1))
+                }
+                _ => Err(unsupported()),
+            },
+            _ => Err(unsupported()),
+        }
+    }
+
+    fn parse_array(
+        &self,
+        mut ind: usize,
+        end: usize,
+        mut pos: usize,
+    ) -> Result<(Vec<Value>, usize), Eu4Error> {
+        let mut values = Vec::new();
+        while ind < end {
+            let (value, span, next) = self.parse_value(ind, pos)?;
+            values.push(value);
+            pos = span.end;
+            ind = next;
+        }
+
+        Ok((values, pos))
+    }
+
+    fn scalar_span(&self, scalar: Scalar, quoted: bool) -> Result<Span, Eu4Error> {
+        let data = scalar.as_bytes();
+        let start = (data.as_ptr() as usize)
+            .checked_sub(self.source.as_ptr() as usize)
+            .filter(|&x| x + data.len() <= self.source.len())
+            .ok_or_else(unsupported)?;
+
+        if quoted {
+            Ok(Span::new(start - 1, start + data.len() + 1))
+        } else {
+            Ok(Span::new(start, start + data.len()))
+        }
+    }
+
+    fn find_open(&self, pos: usize) -> Result<usize, Eu4Error> {
+        self.source[pos..]
+            .iter()
+            .position(|&x| x == b'{')
+            .map(|x| x + pos)
+            .ok_or_else(unsupported)
+    }
+
+    /// Finds the brace that closes the container, starting from the end of
+    /// the container's last value. Empty objects that the parser skipped
+    /// over are accounted for.
+    fn find_close(&self, pos: usize) -> Result<usize, Eu4Error> {
+        let mut depth = 0;
+        let mut in_quote = false;
+        let mut in_comment = false;
+        let mut escaped = false;
+        for (i, &byte) in self.source[pos..].iter().enumerate() {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\n' if in_comment => in_comment = false,
+                _ if in_comment => {}
+                b'\\' if in_quote => escaped = true,
+                b'"' => in_quote = !in_quote,
+                _ if in_quote => {}
+                b'#' => in_comment = true,
+                b'{' => depth += 1,
+                b'}' if depth == 0 => return Ok(pos + i),
+                b'}' => depth -= 1,
+                _ => {}
+            }
+        }
+
+        Err(unsupported())
+    }
+}
+
+fn unsupported() -> Eu4Error {
+    Eu4Error::new(Eu4ErrorKind::InvalidSyntax(String::from(
+        "document contains syntax not found in EU4 saves",
+    )))
+}
+
+struct DocumentWriter<'a, W> {
+    source: &'a [u8],
+    output: W,
+}
+
+impl<W: Write> DocumentWriter<'_, W> {
+    fn write_span(&mut self, span: Span) -> Result<(), Eu4Error> {
+        self.output.write_all(&self.source[span.start..span.end])?;
+        Ok(())
+    }
+
+    fn write_newline(&mut self, depth: usize) -> Result<(), Eu4Error> {
+        self.output.write_all(b"\n")?;
+        for _ in 0..depth {
+            self.output.write_all(b"\t")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the fields of an object found at the given depth. Unmodified
+    /// fields are copied from the source.
+    fn write_fields(&mut self, obj: &Object, depth: usize) -> Result<(), Eu4Error> {
+        for field in &obj.fields {
+            match field.leading {
+                Some(span) => self.write_span(span)?,
+                None => self.write_newline(depth)?,
+            }
+
+            if let Some(span) = field.span {
+                self.write_span(span)?;
+                continue;
+            }
+
+            self.write_scalar(&field.key, field.quoted)?;
+            match field.separator {
+                Some(span) => self.write_span(span)?,
+                None => self.output.write_all(b"=")?,
+            }
+            self.write_value(&field.value, depth)?;
+        }
+
+        match obj.trailing {
+            Some(span) => self.write_span(span)?,
+            None if obj.fields.is_empty() && depth > 0 => self.output.write_all(b" ")?,
+            None if depth > 0 => self.write_newline(depth - 1)?,
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn write_scalar(&mut self, data: &[u8], quoted: bool) -> Result<(), Eu4Error> {
+        if quoted {
+            self.output.write_all(b"\"")?;
+            self.output.write_all(data)?;
+            self.output.write_all(b"\"")?;
+        } else {
+            self.output.write_all(data)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a value of a field or array element found at the given depth
+    fn write_value(&mut self, value: &Value, depth: usize) -> Result<(), Eu4Error> {
+        match value {
+            Value::Unquoted(x) => self.write_scalar(x, false)?,
+            Value::Quoted(x) => self.write_scalar(x, true)?,
+            Value::Object(obj) => match obj.span {
+                Some(span) => self.write_span(span)?,
+                None => {
+                    self.output.write_all(b"{")?;
+                    self.write_fields(obj, depth + 1)?;
+                    self.output.write_all(b"}")?;
+                }
+            },
+            Value::Array(values) => self.write_array(values, depth)?,
+            Value::Header(header, values) => {
+                self.output.write_all(header)?;
+                self.output.write_all(b" ")?;
+                self.write_array(values, depth)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_array(&mut self, values: &[Value], depth: usize) -> Result<(), Eu4Error> {
+        if values.is_empty() {
+            self.output.write_all(b"{ }")?;
+            return Ok(());
+        }
+
+        self.output.write_all(b"{")?;
+        let mut previous_scalar = false;
+        for value in values {
+            let is_scalar = matches!(value, Value::Unquoted(_) | Value::Quoted(_));
+            if previous_scalar && is_scalar {
+                self.output.write_all(b" ")?;
+            } else {
+                self.write_newline(depth + 1)?;
+            }
+            self.write_value(value, depth + 1)?;
+            previous_scalar = is_scalar;
+        }
+        self.write_newline(depth)?;
+        self.output.write_all(b"}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_edit_province() {
+        let data = b"EU4txt\ndate=1444.11.11\nprovinces={\n\t-1={\n\t\towner=\"SWE\"\n\t\tcolor=rgb {\n\t\t\t10 20 30\n\t\t}\n\t\tname=\"Stock\\\"holm\"\n\t}\n}";
+        let mut doc = Eu4Document::from_slice(&data[..]).unwrap();
+
+        let stockholm = doc.province(&ProvinceId::from(1)).unwrap();
+        assert_eq!(
+            stockholm.get("owner").and_then(Value::as_str).unwrap(),
+            "SWE"
+        );
+        assert_eq!(
+            doc.root().get("date").and_then(Value::as_date),
+            Some(Eu4Date::from_ymd(1444, 11, 11))
+        );
+
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        assert_eq!(out.as_slice(), &data[..]);
+
+        let stockholm = doc.province_mut(&ProvinceId::from(1)).unwrap();
+        stockholm.set("owner", Value::from("DAN".parse::<CountryTag>().unwrap()));
+        stockholm.push("hre", Value::from(true));
+
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        let expected = "EU4txt\ndate=1444.11.11\nprovinces={\n\t-1={\n\t\towner=\"DAN\"\n\t\tcolor=rgb {\n\t\t\t10 20 30\n\t\t}\n\t\tname=\"Stock\\\"holm\"\n\t\thre=yes\n\t}\n}";
+        assert_eq!(std::str::from_utf8(&out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_document_duplicate_keys() {
+        let data = b"EU4txt\nactive_war={ name=\"a\" }\nactive_war={ name=\"b\" }";
+        let mut doc = Eu4Document::from_slice(&data[..]).unwrap();
+        assert_eq!(doc.root().get_all("active_war").count(), 2);
+
+        let names = doc.get_path_all("active_war.name");
+        let names = names.iter().filter_map(|x| x.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["a", "b"]);
+
+        let second = doc.get_path("active_war[1].name");
+        assert_eq!(second.and_then(Value::as_str).as_deref(), Some("b"));
+
+        doc.set_path("active_war[1].name", Value::from("c"))
+            .unwrap();
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        let expected = "EU4txt\nactive_war={ name=\"a\" }\nactive_war={ name=\"c\" }";
+        assert_eq!(std::str::from_utf8(&out).unwrap(), expected);
+
+        assert_eq!(doc.root_mut().remove("active_war"), 2);
+        assert!(doc.root().is_empty());
+    }
+
+    #[test]
+    fn test_document_lossless() {
+        let data = b"EU4txt\n\n  a = 1  b={ 1 2 3 }\r\nc = { d = \"e\" {} } \nf=rgb{1 2 3}\n";
+        let doc = Eu4Document::from_slice(&data[..]).unwrap();
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        assert_eq!(out.as_slice(), &data[..]);
+    }
+
+    #[test]
+    fn test_document_set_path() {
+        let data = b"EU4txt\ncountries={\n\tENG={\n\t\tcores={ 1 2 }\n\t\thistory={\n\t\t\t1400.1.1={ }\n\t\t}\n\t}\n}";
+        let mut doc = Eu4Document::from_slice(&data[..]).unwrap();
+        assert_eq!(
+            doc.get_path("countries.ENG.cores.1")
+                .and_then(Value::as_i64),
+            Some(2)
+        );
+
+        doc.set_path("countries.ENG.treasury", Value::from(10.0))
+            .unwrap();
+        doc.set_path("countries.ENG.history.1444.11.11", Object::new().into())
+            .unwrap();
+        assert!(doc
+            .set_path("countries.FRA.treasury", Value::from(1))
+            .is_err());
+        assert!(doc.get_path("countries.ENG.history.1444.11.11").is_some());
+        doc.get_path_mut("countries.ENG.cores")
+            .and_then(Value::as_array_mut)
+            .unwrap()
+            .push(Value::from(3));
+
+        let mut out = Vec::new();
+        doc.write(&mut out).unwrap();
+        let expected = "EU4txt\ncountries={\n\tENG={\n\t\tcores={\n\t\t\t1 2 3\n\t\t}\n\t\thistory={\n\t\t\t1400.1.1={ }\n\t\t\t1444.11.11={ }\n\t\t}\n\t\ttreasury=10.000\n\t}\n}";
+        assert_eq!(std::str::from_utf8(&out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_document_quoted_escape() {
+        assert_eq!(
+            Value::quoted("a \"b\" ö"),
+            Value::Quoted(b"a \\\"b\\\" \xf6".to_vec())
+        );
+    }
+}
@@ -1,20 +1,77 @@
-use crate::file::Eu4FileEntryName;
+use crate::{file::Eu4FileEntryName, Encoding};
 use jomini::binary;
 use std::{fmt, io};
 
 /// An EU4 Error
-#[derive(thiserror::Error, Debug)]
-#[error(transparent)]
-pub struct Eu4Error(#[from] Box<Eu4ErrorKind>);
+#[derive(Debug)]
+pub struct Eu4Error(Box<Eu4ErrorImpl>);
+
+#[derive(Debug)]
+struct Eu4ErrorImpl {
+    kind: Eu4ErrorKind,
+    path: Option<String>,
+    offset: Option<u64>,
+}
 
 impl Eu4Error {
     pub(crate) fn new(kind: Eu4ErrorKind) -> Eu4Error {
-        Eu4Error(Box::new(kind))
+        Eu4Error(Box::new(Eu4ErrorImpl {
+            kind,
+            path: None,
+            offset: None,
+        }))
+    }
+
+    /// Attaches where in the document the error occurred
+    pub(crate) fn with_location(mut self, path: Option<String>, offset: Option<u64>) -> Eu4Error {
+        self.0.path = path.filter(|x| !x.is_empty()).or(self.0.path.take());
+        self.0.offset = self.0.offset.or(offset);
+        self
     }
 
     /// Return the specific type of error
     pub fn kind(&self) -> &Eu4ErrorKind {
-        &self.0
+        &self.0.kind
+    }
+
+    /// The path of keys (and sequence indices) leading to the value that
+    /// failed to deserialize, like `countries.ENG.history.1500.1.1.monarch`.
+    /// Only available for deserialization errors of input that can be read
+    /// again, like the `parse_save` entry points, or when parsing leniently.
+    pub fn path(&self) -> Option<&str> {
+        self.0.path.as_deref()
+    }
+
+    /// How many bytes of the save data had been read when the error was
+    /// raised. This is the read position rather than the position of the
+    /// failing value: input is read in buffered chunks, so the value may lie
+    /// up to a buffer's length before the offset. Parse errors report the
+    /// position of the token that failed to parse.
+    pub fn offset(&self) -> Option<u64> {
+        self.0.offset
+    }
+}
+
+impl fmt::Display for Eu4Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.kind.fmt(f)?;
+        match (self.path(), self.offset()) {
+            (Some(path), Some(offset)) => write!(f, " (at {path}, after reading {offset} bytes)"),
+            (Some(path), None) => write!(f, " (at {path})"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Eu4Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        std::error::Error::source(&self.0.kind)
+    }
+}
+
+impl From<Box<Eu4ErrorKind>> for Eu4Error {
+    fn from(err: Box<Eu4ErrorKind>) -> Self {
+        Eu4Error::new(*err)
     }
 }
 
@@ -63,11 +120,36 @@ pub enum Eu4ErrorKind {
     #[error("expected {0} file to exist within zip")]
     MissingFile(Eu4FileEntryName),
 
+    #[error("{0} file has already been written to the zip")]
+    DuplicateFile(Eu4FileEntryName),
+
     #[error("io error: {0}")]
     Io(#[from] io::Error),
 
     #[error("invalid syntax: {0}")]
     InvalidSyntax(String),
+
+    #[error("no object found along document path: {0}")]
+    PathNotFound(String),
+
+    #[error("saves are from different campaigns: {0} and {1}")]
+    CampaignMismatch(String, String),
+
+    #[error("operation was cancelled")]
+    Cancelled,
+
+    #[error("{0} file does not share the encoding of the files already written to the zip")]
+    MixedEntryEncoding(Eu4FileEntryName),
+
+    #[error("no token tables registered")]
+    NoTokenTables,
+
+    #[error(
+        "token discovery requires a binary save and a text save, found {} and {}",
+        .binary.as_str(),
+        .text.as_str()
+    )]
+    DiscoveryEncoding { binary: Encoding, text: Encoding },
 }
 
 impl From<jomini::Error> for Eu4Error {
@@ -85,7 +167,8 @@ impl From<jomini::Error> for Eu4Error {
                 Eu4Error::new(Eu4ErrorKind::Deserialize(x))
             }
         } else {
-            Eu4Error::new(Eu4ErrorKind::Parse(value))
+            let offset = value.offset().map(|x| x as u64);
+            Eu4Error::new(Eu4ErrorKind::Parse(value)).with_location(None, offset)
         }
     }
 }
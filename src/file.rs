@@ -1,23 +1,38 @@
 //! Parsing and deserializing EU4 save files
 use crate::{
+    de::PathTracker,
     flavor::Eu4Flavor,
+    json::JsonWriter,
     melt,
-    models::{Eu4Save, GameState, Meta},
+    meta::{binary_checksum, text_checksum, MetaSeed, CHECKSUM_TAIL_LEN},
+    models::{borrowed, Country, Eu4Save, GameState, Meta, Province},
+    progress::{ProgressHooks, TrackedReader, Tracker},
     resolver::SegmentedResolver,
-    Encoding, Eu4Error, Eu4ErrorKind, MeltOptions, MeltedDocument,
+    sections::{
+        GameSections, GameStateVisitor, GameStateVisitorSeed, PartialGameStateSeed, PartialSave,
+        SectionEntrySeed,
+    },
+    unmelt::{self, TokenEncoder},
+    CountryTag, Diagnostic, Encoding, Eu4Error, Eu4ErrorKind, JsonMeltOptions, MeltOptions,
+    MeltedDocument, ParseOptions, ProvinceId,
 };
 use jomini::{
     binary::TokenResolver, text::ObjectReader, TextDeserializer, TextTape, Windows1252Encoding,
 };
 use rawzip::{CompressionMethod, FileReader, ReaderAt};
-use serde::de::DeserializeOwned;
+use serde::de::{DeserializeOwned, DeserializeSeed};
 use std::{
-    collections::HashSet,
     fmt::Display,
     fs::File,
     io::{Cursor, Read, Seek, Write},
 };
 
+#[cfg(feature = "parallel")]
+use crate::parallel;
+
+#[cfg(feature = "async")]
+pub use crate::async_file::Eu4AsyncFile;
+
 #[cfg(feature = "zstd_c")]
 use std::io::BufReader;
 
@@ -84,7 +99,30 @@ impl<'a> Eu4Text<'a> {
     }
 
     pub fn deserializer(&self) -> Eu4Modeller<'a, SegmentedResolver<'static>> {
-        Eu4Modeller::from_reader(Box::new(self.0), EMPTY_RESOLVER).with_encoding(Encoding::Text)
+        let data = self.0;
+        Eu4Modeller::from_reader(data, EMPTY_RESOLVER)
+            .with_encoding(Encoding::Text)
+            .with_reopen(move || Ok(Box::new(data)))
+    }
+
+    /// Parses the save into models that borrow their strings from the text
+    pub fn parse_save_borrowed(&self) -> Result<borrowed::Eu4Save<'a>, Eu4Error> {
+        parse_borrowed_text(self.0, self.0)
+    }
+
+    /// Convert the text document into the binary format, with the encoder
+    /// responsible for mapping field names to their binary token.
+    pub fn unmelt<Encoder, Writer>(
+        &self,
+        encoder: Encoder,
+        mut output: Writer,
+    ) -> Result<(), Eu4Error>
+    where
+        Encoder: TokenEncoder,
+        Writer: Write,
+    {
+        output.write_all(BIN_HEADER)?;
+        unmelt::unmelt(self.0, output, encoder)
     }
 }
 
@@ -110,17 +148,82 @@ where
     }
 
     pub fn melt<Resolver, Writer>(
+        self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        self.melt_with_progress(options, resolver, output, ProgressHooks::new())
+    }
+
+    /// Melts while reporting progress to and checking for cancellation from
+    /// the hooks
+    pub fn melt_with_progress<Resolver, Writer>(
+        self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+        hooks: ProgressHooks,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        let tracker = Tracker::new(hooks);
+        let result = self.melt_tracked(options, resolver, output, &tracker);
+        tracker.finish(result)
+    }
+
+    fn melt_tracked<Resolver, Writer>(
         self,
         options: MeltOptions,
         resolver: Resolver,
         mut output: Writer,
+        tracker: &Tracker,
     ) -> Result<MeltedDocument, Eu4Error>
     where
         Resolver: TokenResolver,
         Writer: Write,
     {
         output.write_all(b"EU4txt\n")?;
-        melt::melt(self.0, output, resolver, options.check_header(false))
+        let reader = TrackedReader::new(self.0, tracker);
+        melt::melt(
+            reader,
+            output,
+            resolver,
+            options.check_header(false),
+            tracker,
+        )
+    }
+
+    /// Melts the binary tokens straight to JSON without an intermediate
+    /// plaintext document
+    pub fn melt_json<Resolver, Writer>(
+        self,
+        options: MeltOptions,
+        json: JsonMeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        let tracker = Tracker::new(ProgressHooks::new());
+        let mut wtr = JsonWriter::new(output, json);
+        let doc = melt::melt_tokens(
+            self.0,
+            &mut wtr,
+            resolver,
+            options.check_header(false),
+            &tracker,
+        )?;
+        wtr.finish()?;
+        Ok(doc)
     }
 }
 
@@ -168,21 +271,214 @@ impl<'a> Eu4SliceFile<'a> {
         }
     }
 
+    /// The data of the save without headers, with the entries of a zip
+    /// concatenated into a single document
+    pub(crate) fn body(&self) -> Result<std::borrow::Cow<'a, [u8]>, Eu4Error> {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) => Ok(std::borrow::Cow::Borrowed(data.0)),
+            Eu4SliceFileKind::Binary(data) => Ok(std::borrow::Cow::Borrowed(data.0)),
+            Eu4SliceFileKind::Zip(archive) => {
+                let mut result = Vec::new();
+                for name in [Eu4FileEntryName::Meta, Eu4FileEntryName::Gamestate] {
+                    let mut entry = Vec::new();
+                    archive.get(name)?.read_to_end(&mut entry)?;
+                    let (_, body) = file_header(&entry).ok_or(Eu4ErrorKind::ZipHeader)?;
+                    result.extend_from_slice(body);
+                }
+                Ok(std::borrow::Cow::Owned(result))
+            }
+        }
+    }
+
     pub fn parse_save<Resolver>(&self, resolver: Resolver) -> Result<Eu4Save, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        let (save, _) = self.parse_save_with(resolver, ParseOptions::new())?;
+        Ok(save)
+    }
+
+    /// Parses the save according to the options, returning the fields that
+    /// were replaced with their default when parsing leniently
+    pub fn parse_save_with<Resolver>(
+        &self,
+        resolver: Resolver,
+        options: ParseOptions,
+    ) -> Result<(Eu4Save, Vec<Diagnostic>), Eu4Error>
     where
         Resolver: TokenResolver,
+    {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) => data
+                .deserializer()
+                .with_options(options)
+                .deserialize_with_diagnostics(),
+            Eu4SliceFileKind::Binary(data) => {
+                let data = data.0;
+                Eu4Modeller::from_reader(data, resolver)
+                    .with_encoding(Encoding::Binary)
+                    .with_reopen(move || Ok(Box::new(data)))
+                    .with_options(options)
+                    .deserialize_with_diagnostics()
+            }
+            Eu4SliceFileKind::Zip(archive) => archive.parse_save_with(&resolver, options),
+        }
+    }
+
+    /// Parses the save into [borrowed models](borrowed) whose strings borrow
+    /// from the save data. The entries of a zip are inflated into the buffer
+    /// for the models to borrow from, otherwise the buffer is left untouched.
+    pub fn parse_save_borrowed<'b, Resolver>(
+        &'b self,
+        resolver: &'b Resolver,
+        buf: &'b mut Vec<u8>,
+    ) -> Result<borrowed::Eu4Save<'b>, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) => data.parse_save_borrowed(),
+            Eu4SliceFileKind::Binary(data) => parse_borrowed_binary(data.0, data.0, resolver),
+            Eu4SliceFileKind::Zip(archive) => {
+                buf.clear();
+                let mut bodies = Vec::with_capacity(2);
+                for name in [Eu4FileEntryName::Meta, Eu4FileEntryName::Gamestate] {
+                    let start = buf.len();
+                    archive.get(name)?.read_to_end(buf)?;
+                    file_header(&buf[start..]).ok_or(Eu4ErrorKind::ZipHeader)?;
+                    bodies.push(start + BIN_HEADER.len()..buf.len());
+                }
+
+                let data: &'b [u8] = buf;
+                let meta = &data[bodies[0].clone()];
+                let gamestate = &data[bodies[1].clone()];
+                if archive.is_text {
+                    parse_borrowed_text(meta, gamestate)
+                } else {
+                    parse_borrowed_binary(meta, gamestate, resolver)
+                }
+            }
+        }
+    }
+
+    /// Deserializes the save with the countries and provinces split across
+    /// threads. Zip entries are decompressed concurrently.
+    #[cfg(feature = "parallel")]
+    pub fn parse_save_parallel<Resolver>(&self, resolver: Resolver) -> Result<Eu4Save, Eu4Error>
+    where
+        Resolver: TokenResolver + Sync,
     {
         match &self.kind {
             Eu4SliceFileKind::Text(data) => {
-                Eu4Modeller::from_reader(Box::new(data.0), EMPTY_RESOLVER)
-                    .with_encoding(Encoding::Text)
-                    .deserialize()
+                let doc = parallel::deserialize(data.0, Encoding::Text, &EMPTY_RESOLVER)?;
+                Ok(doc.into_save())
+            }
+            Eu4SliceFileKind::Binary(data) => {
+                let doc = parallel::deserialize(data.0, Encoding::Binary, &resolver)?;
+                Ok(doc.into_save())
+            }
+            Eu4SliceFileKind::Zip(archive) => archive.parse_save_parallel(resolver),
+        }
+    }
+
+    /// Deserializes only the metadata. For plaintext and binary saves,
+    /// parsing stops where the metadata ends instead of reading through the
+    /// gamestate.
+    pub fn parse_meta<Resolver>(&self, resolver: Resolver) -> Result<Meta, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) => {
+                let tail = &data.0[data.0.len().saturating_sub(CHECKSUM_TAIL_LEN)..];
+                let seed = MetaSeed {
+                    checksum: text_checksum(tail),
+                };
+                seed.deserialize(&mut data.deserializer())
             }
-            Eu4SliceFileKind::Binary(data) => data.deserializer(resolver).deserialize(),
+            Eu4SliceFileKind::Binary(data) => {
+                let tail = &data.0[data.0.len().saturating_sub(CHECKSUM_TAIL_LEN)..];
+                let seed = MetaSeed {
+                    checksum: binary_checksum(tail, &resolver),
+                };
+                seed.deserialize(&mut data.deserializer(resolver))
+            }
+            Eu4SliceFileKind::Zip(archive) => archive.deserialize_entry(archive.meta, resolver),
+        }
+    }
+
+    /// Deserializes the metadata and only the selected sections of the
+    /// gamestate. Unselected sections are skipped without being deserialized.
+    pub fn parse_sections<Resolver>(
+        &self,
+        resolver: Resolver,
+        sections: GameSections,
+    ) -> Result<PartialSave, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        // Reading the metadata stops where it ends instead of passing over
+        // the gamestate a second time
+        let meta = self.parse_meta(&resolver)?;
+        let game = self.deserialize_gamestate(resolver, PartialGameStateSeed(sections))?;
+        Ok(PartialSave { meta, game })
+    }
+
+    /// Deserializes only the country stored under the given tag, skipping
+    /// over the rest of the gamestate. Returns `None` if the country doesn't
+    /// exist.
+    pub fn parse_country<Resolver>(
+        &self,
+        tag: &CountryTag,
+        resolver: Resolver,
+    ) -> Result<Option<Country>, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        self.deserialize_gamestate(resolver, SectionEntrySeed::new("countries", *tag))
+    }
+
+    /// Deserializes only the province with the given id, skipping over the
+    /// rest of the gamestate. Returns `None` if the province doesn't exist.
+    pub fn parse_province<Resolver>(
+        &self,
+        id: &ProvinceId,
+        resolver: Resolver,
+    ) -> Result<Option<Province>, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        self.deserialize_gamestate(resolver, SectionEntrySeed::new("provinces", *id))
+    }
+
+    /// Streams the countries, provinces, and wars of the gamestate into the
+    /// visitor as each one is decoded
+    pub fn visit_gamestate<Resolver, V>(
+        &self,
+        resolver: Resolver,
+        visitor: &mut V,
+    ) -> Result<(), Eu4Error>
+    where
+        Resolver: TokenResolver,
+        V: GameStateVisitor,
+    {
+        self.deserialize_gamestate(resolver, GameStateVisitorSeed(visitor))
+    }
+
+    fn deserialize_gamestate<Seed, T, Resolver>(
+        &self,
+        resolver: Resolver,
+        seed: Seed,
+    ) -> Result<T, Eu4Error>
+    where
+        Seed: for<'de> DeserializeSeed<'de, Value = T>,
+        Resolver: TokenResolver,
+    {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) => seed.deserialize(&mut data.deserializer()),
+            Eu4SliceFileKind::Binary(data) => seed.deserialize(&mut data.deserializer(resolver)),
             Eu4SliceFileKind::Zip(archive) => {
-                let meta: Meta = archive.deserialize_entry(archive.meta, &resolver)?;
-                let game: GameState = archive.deserialize_entry(archive.gamestate, &resolver)?;
-                Ok(Eu4Save { meta, game })
+                archive.deserialize_entry_seed(archive.gamestate, resolver, seed)
             }
         }
     }
@@ -200,23 +496,97 @@ impl<'a> Eu4SliceFile<'a> {
     }
 
     pub fn melt<Resolver, Writer>(
+        &self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        self.melt_with_progress(options, resolver, output, ProgressHooks::new())
+    }
+
+    /// Melts while reporting progress to and checking for cancellation from
+    /// the hooks
+    pub fn melt_with_progress<Resolver, Writer>(
         &self,
         options: MeltOptions,
         resolver: Resolver,
         mut output: Writer,
+        hooks: ProgressHooks,
     ) -> Result<MeltedDocument, Eu4Error>
     where
         Resolver: TokenResolver,
         Writer: Write,
     {
-        match &self.kind {
+        let tracker = Tracker::new(hooks);
+        let result = match &self.kind {
+            Eu4SliceFileKind::Text(data) if options.is_canonical() => {
+                output.write_all(b"EU4txt\n")?;
+                melt::melt_text_canonical(data.0, output, options, &tracker)
+            }
             Eu4SliceFileKind::Text(data) => {
                 output.write_all(b"EU4txt\n")?;
+                let mut reader = TrackedReader::new(data.0, &tracker);
+                std::io::copy(&mut reader, &mut output)
+                    .map(|_| MeltedDocument::new())
+                    .map_err(Eu4Error::from)
+            }
+            Eu4SliceFileKind::Binary(data) => {
+                data.melt_tracked(options, resolver, output, &tracker)
+            }
+            Eu4SliceFileKind::Zip(zip) => zip.melt_tracked(options, resolver, output, &tracker),
+        };
+        tracker.finish(result)
+    }
+
+    /// Converts the save to JSON. Binary data is melted straight to JSON
+    /// while plaintext is parsed and then converted.
+    pub fn melt_json<Resolver, Writer>(
+        &self,
+        options: MeltOptions,
+        json: JsonMeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) if options.is_canonical() => {
+                let tracker = Tracker::new(ProgressHooks::new());
+                let mut text = Vec::with_capacity(data.0.len());
+                melt::melt_text_canonical(data.0, &mut text, options, &tracker)?;
+                text_json(&text, json, output)
+            }
+            Eu4SliceFileKind::Text(data) => text_json(data.0, json, output),
+            Eu4SliceFileKind::Binary(data) => data.melt_json(options, json, resolver, output),
+            Eu4SliceFileKind::Zip(zip) => zip.melt_json(options, json, resolver, output),
+        }
+    }
+
+    /// Convert a text save (or the entries of a text zip) into the binary
+    /// format. Binary data is copied to the output as is.
+    pub fn unmelt<Encoder, Writer>(
+        &self,
+        encoder: Encoder,
+        mut output: Writer,
+    ) -> Result<(), Eu4Error>
+    where
+        Encoder: TokenEncoder,
+        Writer: Write,
+    {
+        match &self.kind {
+            Eu4SliceFileKind::Text(data) => data.unmelt(encoder, output),
+            Eu4SliceFileKind::Binary(data) => {
+                output.write_all(BIN_HEADER)?;
                 output.write_all(data.0)?;
-                Ok(MeltedDocument::new())
+                Ok(())
             }
-            Eu4SliceFileKind::Binary(data) => data.melt(options, resolver, output),
-            Eu4SliceFileKind::Zip(zip) => zip.melt(options, resolver, output),
+            Eu4SliceFileKind::Zip(zip) => zip.unmelt(encoder, output),
         }
     }
 }
@@ -329,22 +699,68 @@ where
     }
 
     pub fn melt<Resolver, Writer>(
+        &mut self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        self.melt_with_progress(options, resolver, output, ProgressHooks::new())
+    }
+
+    /// Melts while reporting progress to and checking for cancellation from
+    /// the hooks
+    pub fn melt_with_progress<Resolver, Writer>(
         &mut self,
         options: MeltOptions,
         resolver: Resolver,
         mut output: Writer,
+        hooks: ProgressHooks,
     ) -> Result<MeltedDocument, Eu4Error>
     where
         Resolver: TokenResolver,
         Writer: Write,
     {
+        let tracker = Tracker::new(hooks);
         output.write_all(b"EU4txt\n")?;
-        melt(
-            &mut self.reader,
+        let reader = TrackedReader::new(&mut self.reader, &tracker);
+        let result = melt(
+            reader,
             output,
             resolver,
             options.skip_checksum(false),
-        )
+            &tracker,
+        );
+        tracker.finish(result)
+    }
+
+    /// Melts the binary entry straight to JSON without an intermediate
+    /// plaintext document
+    pub fn melt_json<Resolver, Writer>(
+        &mut self,
+        options: MeltOptions,
+        json: JsonMeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        let tracker = Tracker::new(ProgressHooks::new());
+        let mut wtr = JsonWriter::new(output, json);
+        let doc = melt::melt_tokens(
+            &mut self.reader,
+            &mut wtr,
+            resolver,
+            options.skip_checksum(false),
+            &tracker,
+        )?;
+        wtr.finish()?;
+        Ok(doc)
     }
 }
 
@@ -456,6 +872,40 @@ where
         }
     }
 
+    /// Converts the meta, gamestate, and ai entries into a single binary
+    /// document. Binary entries are copied to the output as is.
+    pub fn unmelt<Encoder, Writer>(
+        &self,
+        encoder: Encoder,
+        mut output: Writer,
+    ) -> Result<(), Eu4Error>
+    where
+        Encoder: TokenEncoder,
+        Writer: Write,
+    {
+        output.write_all(BIN_HEADER)?;
+        let mut data = Vec::new();
+        for name in [
+            Eu4FileEntryName::Meta,
+            Eu4FileEntryName::Gamestate,
+            Eu4FileEntryName::Ai,
+        ] {
+            data.clear();
+            self.get(name)?.read_to_end(&mut data)?;
+            let body = file_header(&data)
+                .map(|(_, body)| body)
+                .ok_or_else(|| Eu4Error::new(Eu4ErrorKind::ZipHeader))?;
+
+            if self.is_text {
+                unmelt::unmelt(body, &mut output, &encoder)?;
+            } else {
+                output.write_all(body)?;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn deserialize_entry<T, Resolver>(
         &self,
         entry: rawzip::ZipArchiveEntryWayfinder,
@@ -464,68 +914,425 @@ where
     where
         T: DeserializeOwned,
         Resolver: TokenResolver,
+    {
+        self.entry_deserializer(entry, resolver)?.deserialize()
+    }
+
+    /// Deserializes the meta and gamestate entries, collecting the
+    /// diagnostics of both
+    fn parse_save_with<Resolver>(
+        &self,
+        resolver: Resolver,
+        options: ParseOptions,
+    ) -> Result<(Eu4Save, Vec<Diagnostic>), Eu4Error>
+    where
+        Resolver: TokenResolver + Clone,
+    {
+        let (meta, mut diagnostics) = self
+            .entry_deserializer(self.meta, resolver.clone())?
+            .with_options(options)
+            .deserialize_with_diagnostics::<Meta>()?;
+        let (game, game_diagnostics) = self
+            .entry_deserializer(self.gamestate, resolver)?
+            .with_options(options)
+            .deserialize_with_diagnostics::<GameState>()?;
+        diagnostics.extend(game_diagnostics);
+        Ok((Eu4Save { meta, game }, diagnostics))
+    }
+
+    fn entry_deserializer<Resolver>(
+        &self,
+        entry: rawzip::ZipArchiveEntryWayfinder,
+        resolver: Resolver,
+    ) -> Result<Eu4Modeller<'_, Resolver>, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        let reader = self.entry_reader(entry)?;
+        let modeller = Eu4Modeller::from_reader(reader, resolver)
+            .with_reopen(move || Ok(Box::new(self.entry_reader(entry)?)));
+        Ok(modeller)
+    }
+
+    fn entry_reader(
+        &self,
+        entry: rawzip::ZipArchiveEntryWayfinder,
+    ) -> Result<impl Read + '_, Eu4Error> {
+        let zip_entry = self.archive.get_entry(entry).map_err(Eu4ErrorKind::Zip)?;
+        let compressed = zip_entry.reader();
+        let expected = compressed.claim_verifier();
+        let reader = CompressedFileReader::from_compressed(compressed, self.compression)?;
+        Ok(ZipEntryVerifier::new(reader, expected))
+    }
+
+    pub(crate) fn deserialize_entry_seed<Seed, T, Resolver>(
+        &self,
+        entry: rawzip::ZipArchiveEntryWayfinder,
+        resolver: Resolver,
+        seed: Seed,
+    ) -> Result<T, Eu4Error>
+    where
+        Seed: for<'de> DeserializeSeed<'de, Value = T>,
+        Resolver: TokenResolver,
     {
         let zip_entry = self.archive.get_entry(entry).map_err(Eu4ErrorKind::Zip)?;
         let compressed = zip_entry.reader();
         let expected = compressed.claim_verifier();
         let reader = CompressedFileReader::from_compressed(compressed, self.compression)?;
         let reader = ZipEntryVerifier::new(reader, expected);
-        let data: T = Eu4Modeller::from_reader(reader, resolver).deserialize()?;
-        Ok(data)
+        seed.deserialize(&mut Eu4Modeller::from_reader(reader, resolver))
+    }
+
+    /// Decompresses and deserializes the meta entry on a separate thread
+    /// while the gamestate is deserialized in parallel
+    #[cfg(feature = "parallel")]
+    pub fn parse_save_parallel<Resolver>(&self, resolver: Resolver) -> Result<Eu4Save, Eu4Error>
+    where
+        R: Sync,
+        Resolver: TokenResolver + Sync,
+    {
+        std::thread::scope(|scope| {
+            let meta = scope.spawn(|| self.deserialize_entry::<Meta, _>(self.meta, &resolver));
+
+            let mut data = Vec::with_capacity(self.gamestate.uncompressed_size_hint() as usize);
+            self.get(Eu4FileEntryName::Gamestate)?
+                .read_to_end(&mut data)?;
+            let (header, body) =
+                file_header(&data).ok_or_else(|| Eu4Error::new(Eu4ErrorKind::ZipHeader))?;
+            let encoding = match header {
+                FileHeader::Text => Encoding::Text,
+                FileHeader::Binary => Encoding::Binary,
+            };
+
+            let game = parallel::deserialize::<GameState, _>(body, encoding, &resolver)?;
+            let meta = meta.join().expect("meta thread to not panic")?;
+            Ok(Eu4Save {
+                meta,
+                game: game.into_game(),
+            })
+        })
     }
 
     pub fn melt<Resolver, Writer>(
+        &self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        self.melt_with_progress(options, resolver, output, ProgressHooks::new())
+    }
+
+    /// Melts while reporting progress to and checking for cancellation from
+    /// the hooks
+    pub fn melt_with_progress<Resolver, Writer>(
+        &self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+        hooks: ProgressHooks,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        let tracker = Tracker::new(hooks);
+        let result = self.melt_tracked(options, resolver, output, &tracker);
+        tracker.finish(result)
+    }
+
+    fn melt_tracked<Resolver, Writer>(
         &self,
         options: MeltOptions,
         resolver: Resolver,
         mut output: Writer,
+        tracker: &Tracker,
     ) -> Result<MeltedDocument, Eu4Error>
     where
         Resolver: TokenResolver,
         Writer: Write,
     {
-        if self.is_text {
-            let mut meta = self.get(Eu4FileEntryName::Meta)?;
+        let entry = |name| {
+            self.get(name)
+                .map(|entry| TrackedReader::new(entry, tracker))
+        };
+
+        if self.is_text && options.is_canonical() {
+            let mut data = Vec::new();
+            let mut header = [0u8; TXT_HEADER.len() + 1];
+            for name in [
+                Eu4FileEntryName::Meta,
+                Eu4FileEntryName::Gamestate,
+                Eu4FileEntryName::Ai,
+            ] {
+                let mut entry = entry(name)?;
+                entry.read_exact(&mut header)?;
+                entry.read_to_end(&mut data)?;
+            }
+
+            output.write_all(b"EU4txt\n")?;
+            melt::melt_text_canonical(&data, output, options, tracker)
+        } else if self.is_text {
+            let mut meta = entry(Eu4FileEntryName::Meta)?;
             std::io::copy(&mut meta, &mut output)?;
 
             let mut header = [0u8; TXT_HEADER.len() + 1];
-            let mut gamestate = self.get(Eu4FileEntryName::Gamestate)?;
+            let mut gamestate = entry(Eu4FileEntryName::Gamestate)?;
             gamestate.read_exact(&mut header)?;
             std::io::copy(&mut gamestate, &mut output)?;
 
-            let mut ai = self.get(Eu4FileEntryName::Ai)?;
+            let mut ai = entry(Eu4FileEntryName::Ai)?;
             ai.read_exact(&mut header)?;
             std::io::copy(&mut ai, &mut output)?;
 
             Ok(MeltedDocument::new())
         } else {
             output.write_all(b"EU4txt\n")?;
-            let meta = self.get(Eu4FileEntryName::Meta)?;
-            let meta_result = melt(meta, &mut output, &resolver, options.skip_checksum(true))?;
+            let meta = entry(Eu4FileEntryName::Meta)?;
+            let meta_result = melt(
+                meta,
+                &mut output,
+                &resolver,
+                options.skip_checksum(true),
+                tracker,
+            )?;
 
-            let gamestate = self.get(Eu4FileEntryName::Gamestate)?;
+            let gamestate = entry(Eu4FileEntryName::Gamestate)?;
             let gamestate_result = melt(
                 gamestate,
                 &mut output,
                 &resolver,
                 options.skip_checksum(true),
+                tracker,
             )?;
 
-            let ai = self.get(Eu4FileEntryName::Ai)?;
-            let ai_result = melt(ai, &mut output, &resolver, options.skip_checksum(false))?;
+            let ai = entry(Eu4FileEntryName::Ai)?;
+            let ai_result = melt(
+                ai,
+                &mut output,
+                &resolver,
+                options.skip_checksum(false),
+                tracker,
+            )?;
 
-            let union = meta_result
-                .unknown_tokens
-                .iter()
-                .chain(gamestate_result.unknown_tokens.iter())
-                .chain(ai_result.unknown_tokens.iter())
-                .copied()
-                .collect::<HashSet<u16>>();
+            let mut result = meta_result;
+            result.merge(gamestate_result);
+            result.merge(ai_result);
+            Ok(result)
+        }
+    }
 
-            Ok(MeltedDocument {
-                unknown_tokens: union,
-            })
+    /// Converts the meta, gamestate, and ai entries into a single JSON
+    /// document
+    pub fn melt_json<Resolver, Writer>(
+        &self,
+        options: MeltOptions,
+        json: JsonMeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        if self.is_text {
+            let mut data = Vec::with_capacity(
+                (self.meta.uncompressed_size_hint()
+                    + self.gamestate.uncompressed_size_hint()
+                    + self.ai.uncompressed_size_hint()) as usize,
+            );
+
+            let mut header = [0u8; TXT_HEADER.len() + 1];
+            for name in [
+                Eu4FileEntryName::Meta,
+                Eu4FileEntryName::Gamestate,
+                Eu4FileEntryName::Ai,
+            ] {
+                let mut entry = self.get(name)?;
+                entry.read_exact(&mut header)?;
+                entry.read_to_end(&mut data)?;
+            }
+
+            if options.is_canonical() {
+                let tracker = Tracker::new(ProgressHooks::new());
+                let mut text = Vec::with_capacity(data.len());
+                melt::melt_text_canonical(&data, &mut text, options, &tracker)?;
+                return text_json(&text, json, output);
+            }
+
+            return text_json(&data, json, output);
         }
+
+        let tracker = Tracker::new(ProgressHooks::new());
+        let mut wtr = JsonWriter::new(output, json);
+        let mut result = MeltedDocument::new();
+        for (name, skip_checksum) in [
+            (Eu4FileEntryName::Meta, true),
+            (Eu4FileEntryName::Gamestate, true),
+            (Eu4FileEntryName::Ai, false),
+        ] {
+            let doc = melt::melt_tokens(
+                self.get(name)?,
+                &mut wtr,
+                &resolver,
+                options.skip_checksum(skip_checksum),
+                &tracker,
+            )?;
+            result.merge(doc);
+        }
+
+        wtr.finish()?;
+        Ok(result)
+    }
+}
+
+/// Converts headerless plaintext to JSON
+fn text_json<Writer>(
+    data: &[u8],
+    json: JsonMeltOptions,
+    output: Writer,
+) -> Result<MeltedDocument, Eu4Error>
+where
+    Writer: Write,
+{
+    let tape = TextTape::from_slice(data)?;
+    tape.windows1252_reader()
+        .json()
+        .with_options(json.text_options())
+        .to_writer(output)?;
+    Ok(MeltedDocument::new())
+}
+
+/// Writes the meta, gamestate, and ai entries into a zip archive that the game
+/// can load.
+///
+/// Each entry must start with either the EU4txt or EU4bin header, and all
+/// entries must share the same encoding. If no ai entry is written, an empty
+/// one is written when the archive is finished.
+///
+/// ```rust
+/// use eu4save::file::{Eu4FileEntryName, Eu4ZipWriter};
+/// let mut out = Vec::new();
+/// let mut writer = Eu4ZipWriter::new(&mut out);
+/// writer.write_entry(Eu4FileEntryName::Meta, &b"EU4txt\ndate=1444.11.11\n"[..])?;
+/// writer.write_entry(Eu4FileEntryName::Gamestate, &b"EU4txt\ncurrent_age=age_of_discovery\n"[..])?;
+/// writer.finish()?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct Eu4ZipWriter<W> {
+    archive: rawzip::ZipArchiveWriter<W>,
+    compression: CompressionMethod,
+    written: Vec<Eu4FileEntryName>,
+    is_text: Option<bool>,
+}
+
+impl<W> Eu4ZipWriter<W>
+where
+    W: Write,
+{
+    /// Create a writer that deflates entries
+    pub fn new(writer: W) -> Self {
+        Eu4ZipWriter {
+            archive: rawzip::ZipArchiveWriter::new(writer),
+            compression: CompressionMethod::DEFLATE,
+            written: Vec::new(),
+            is_text: None,
+        }
+    }
+
+    /// Set the compression of subsequent entries. Only deflate and store are
+    /// supported.
+    pub fn with_compression(self, compression: CompressionMethod) -> Result<Self, Eu4Error> {
+        if !matches!(
+            compression,
+            CompressionMethod::DEFLATE | CompressionMethod::STORE
+        ) {
+            return Err(Eu4ErrorKind::UnknownCompression.into());
+        }
+
+        Ok(Eu4ZipWriter {
+            compression,
+            ..self
+        })
+    }
+
+    /// Write an entry from a reader of its (uncompressed) data, header included
+    pub fn write_entry<Reader>(
+        &mut self,
+        name: Eu4FileEntryName,
+        mut reader: Reader,
+    ) -> Result<(), Eu4Error>
+    where
+        Reader: Read,
+    {
+        if self.written.contains(&name) {
+            return Err(Eu4ErrorKind::DuplicateFile(name).into());
+        }
+
+        let mut header = [0u8; TXT_HEADER.len()];
+        reader.read_exact(&mut header)?;
+        let is_text = match file_header(&header) {
+            Some((FileHeader::Text, _)) => true,
+            Some((FileHeader::Binary, _)) => false,
+            None => return Err(Eu4ErrorKind::ZipHeader.into()),
+        };
+
+        if *self.is_text.get_or_insert(is_text) != is_text {
+            return Err(Eu4ErrorKind::MixedEntryEncoding(name).into());
+        }
+
+        let (mut entry, config) = self
+            .archive
+            .new_file(name.to_string().as_str())
+            .compression_method(self.compression)
+            .start()
+            .map_err(Eu4ErrorKind::Zip)?;
+
+        let descriptor = if self.compression == CompressionMethod::DEFLATE {
+            let encoder =
+                flate2::write::DeflateEncoder::new(&mut entry, flate2::Compression::default());
+            let mut writer = config.wrap(encoder);
+            writer.write_all(&header)?;
+            std::io::copy(&mut reader, &mut writer)?;
+            let (encoder, descriptor) = writer.finish().map_err(Eu4ErrorKind::Zip)?;
+            encoder.finish()?;
+            descriptor
+        } else {
+            let mut writer = config.wrap(&mut entry);
+            writer.write_all(&header)?;
+            std::io::copy(&mut reader, &mut writer)?;
+            let (_, descriptor) = writer.finish().map_err(Eu4ErrorKind::Zip)?;
+            descriptor
+        };
+
+        entry.finish(descriptor).map_err(Eu4ErrorKind::Zip)?;
+        self.written.push(name);
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer. The meta
+    /// and gamestate entries must have been written.
+    pub fn finish(mut self) -> Result<W, Eu4Error> {
+        for name in [Eu4FileEntryName::Meta, Eu4FileEntryName::Gamestate] {
+            if !self.written.contains(&name) {
+                return Err(Eu4ErrorKind::MissingFile(name).into());
+            }
+        }
+
+        if !self.written.contains(&Eu4FileEntryName::Ai) {
+            let header: &[u8] = if self.is_text == Some(false) {
+                BIN_HEADER
+            } else {
+                b"EU4txt\n"
+            };
+            self.write_entry(Eu4FileEntryName::Ai, header)?;
+        }
+
+        let result = self.archive.finish().map_err(Eu4ErrorKind::Zip)?;
+        Ok(result)
     }
 }
 
@@ -566,42 +1373,179 @@ where
     }
 
     pub fn parse_save<Resolver>(&self, resolver: Resolver) -> Result<Eu4Save, Eu4Error>
+    where
+        Resolver: TokenResolver + Clone,
+    {
+        let (save, _) = self.parse_save_with(resolver, ParseOptions::new())?;
+        Ok(save)
+    }
+
+    /// Parses the save according to the options, returning the fields that
+    /// were replaced with their default when parsing leniently
+    pub fn parse_save_with<Resolver>(
+        &self,
+        resolver: Resolver,
+        options: ParseOptions,
+    ) -> Result<(Eu4Save, Vec<Diagnostic>), Eu4Error>
     where
         Resolver: TokenResolver + Clone,
     {
         match &self.kind {
-            Eu4FsFileKind::Text(file) => Ok(Eu4Modeller::from_reader(file, resolver)
+            Eu4FsFileKind::Text(file) => Eu4Modeller::from_reader(seek_body(file)?, resolver)
                 .with_encoding(Encoding::Text)
-                .deserialize()?),
-            Eu4FsFileKind::Binary(file) => file.as_ref().deserializer(resolver).deserialize(),
-            Eu4FsFileKind::Zip(archive) => {
-                let meta: Meta = archive.deserialize_entry(archive.meta, resolver.clone())?;
-                let game: GameState = archive.deserialize_entry(archive.gamestate, resolver)?;
+                .with_reopen(move || reopen_body(file))
+                .with_options(options)
+                .deserialize_with_diagnostics(),
+            Eu4FsFileKind::Binary(file) => Eu4Binary(seek_body(file.get_ref())?)
+                .deserializer(resolver)
+                .with_reopen(move || reopen_body(file.get_ref()))
+                .with_options(options)
+                .deserialize_with_diagnostics(),
+            Eu4FsFileKind::Zip(archive) => archive.parse_save_with(resolver, options),
+        }
+    }
+
+    /// Parses the save while reporting the bytes read to and checking for
+    /// cancellation from the hooks
+    pub fn parse_save_with_progress<Resolver>(
+        &self,
+        resolver: Resolver,
+        hooks: ProgressHooks,
+    ) -> Result<Eu4Save, Eu4Error>
+    where
+        Resolver: TokenResolver + Clone,
+    {
+        let tracker = Tracker::new(hooks);
+        let deserialize = |reader, encoding, resolver| {
+            Eu4Modeller::from_reader(TrackedReader::new(reader, &tracker), resolver)
+                .with_encoding(encoding)
+                .deserialize()
+        };
+
+        let result = match &self.kind {
+            Eu4FsFileKind::Text(file) => seek_body(file)
+                .and_then(|file| deserialize(Box::new(file), Encoding::Text, resolver)),
+            Eu4FsFileKind::Binary(file) => seek_body(file.get_ref())
+                .and_then(|file| deserialize(Box::new(file), Encoding::Binary, resolver)),
+            Eu4FsFileKind::Zip(archive) => (|| {
+                let meta = archive.get(Eu4FileEntryName::Meta)?;
+                let meta: Meta =
+                    Eu4Modeller::from_reader(TrackedReader::new(meta, &tracker), resolver.clone())
+                        .deserialize()?;
+
+                let game = archive.get(Eu4FileEntryName::Gamestate)?;
+                let game: GameState =
+                    Eu4Modeller::from_reader(TrackedReader::new(game, &tracker), resolver)
+                        .deserialize()?;
                 Ok(Eu4Save { meta, game })
+            })(),
+        };
+        tracker.finish(result)
+    }
+
+    /// Deserializes only the metadata. For plaintext and binary saves,
+    /// parsing stops where the metadata ends instead of reading through the
+    /// gamestate.
+    pub fn parse_meta<Resolver>(&self, resolver: Resolver) -> Result<Meta, Eu4Error>
+    where
+        Resolver: TokenResolver,
+    {
+        match &self.kind {
+            Eu4FsFileKind::Text(file) => {
+                let seed = MetaSeed {
+                    checksum: text_checksum(&read_tail(file, TXT_HEADER.len())?),
+                };
+                let mut modeller = Eu4Modeller::from_reader(seek_body(file)?, resolver)
+                    .with_encoding(Encoding::Text);
+                seed.deserialize(&mut modeller)
+            }
+            Eu4FsFileKind::Binary(file) => {
+                let tail = read_tail(file.get_ref(), BIN_HEADER.len())?;
+                let seed = MetaSeed {
+                    checksum: binary_checksum(&tail, &resolver),
+                };
+                seed.deserialize(&mut Eu4Binary(seek_body(file.get_ref())?).deserializer(resolver))
             }
+            Eu4FsFileKind::Zip(archive) => archive.deserialize_entry(archive.meta, resolver),
         }
     }
 
+    /// Deserializes the metadata and only the selected sections of the
+    /// gamestate. Unselected sections are skipped without being deserialized.
+    pub fn parse_sections<Resolver>(
+        &self,
+        resolver: Resolver,
+        sections: GameSections,
+    ) -> Result<PartialSave, Eu4Error>
+    where
+        Resolver: TokenResolver + Clone,
+    {
+        // Reading the metadata stops where it ends instead of passing over
+        // the gamestate a second time
+        let meta = self.parse_meta(resolver.clone())?;
+        let seed = PartialGameStateSeed(sections);
+        let game = match &self.kind {
+            Eu4FsFileKind::Text(file) => {
+                let mut modeller = Eu4Modeller::from_reader(seek_body(file)?, resolver)
+                    .with_encoding(Encoding::Text);
+                seed.deserialize(&mut modeller)?
+            }
+            Eu4FsFileKind::Binary(file) => {
+                seed.deserialize(&mut Eu4Binary(seek_body(file.get_ref())?).deserializer(resolver))?
+            }
+            Eu4FsFileKind::Zip(archive) => {
+                archive.deserialize_entry_seed(archive.gamestate, resolver, seed)?
+            }
+        };
+
+        Ok(PartialSave { meta, game })
+    }
+
     pub fn melt<Resolver, Writer>(
+        &self,
+        options: MeltOptions,
+        resolver: Resolver,
+        output: Writer,
+    ) -> Result<MeltedDocument, Eu4Error>
+    where
+        Resolver: TokenResolver,
+        Writer: Write,
+    {
+        self.melt_with_progress(options, resolver, output, ProgressHooks::new())
+    }
+
+    /// Melts while reporting progress to and checking for cancellation from
+    /// the hooks
+    pub fn melt_with_progress<Resolver, Writer>(
         &self,
         options: MeltOptions,
         resolver: Resolver,
         mut output: Writer,
+        hooks: ProgressHooks,
     ) -> Result<MeltedDocument, Eu4Error>
     where
         Resolver: TokenResolver,
         Writer: Write,
     {
-        match &self.kind {
-            Eu4FsFileKind::Text(file) => {
-                let mut file = *&file;
+        let tracker = Tracker::new(hooks);
+        let result = match &self.kind {
+            Eu4FsFileKind::Text(file) if options.is_canonical() => (|| {
+                let mut data = Vec::new();
+                TrackedReader::new(seek_body(file)?, &tracker).read_to_end(&mut data)?;
+                output.write_all(b"EU4txt\n")?;
+                melt::melt_text_canonical(&data, &mut output, options, &tracker)
+            })(),
+            Eu4FsFileKind::Text(file) => (|| {
+                let mut file: &File = file;
                 file.seek(std::io::SeekFrom::Start(0))?;
-                std::io::copy(&mut file, &mut output)?;
+                std::io::copy(&mut TrackedReader::new(file, &tracker), &mut output)?;
                 Ok(MeltedDocument::new())
-            }
-            Eu4FsFileKind::Binary(file) => file.as_ref().melt(options, resolver, output),
-            Eu4FsFileKind::Zip(zip) => zip.melt(options, resolver, output),
-        }
+            })(),
+            Eu4FsFileKind::Binary(file) => seek_body(file.get_ref())
+                .and_then(|file| Eu4Binary(file).melt_tracked(options, resolver, output, &tracker)),
+            Eu4FsFileKind::Zip(zip) => zip.melt_tracked(options, resolver, output, &tracker),
+        };
+        tracker.finish(result)
     }
 }
 
@@ -610,6 +1554,17 @@ enum FileHeader {
     Binary,
 }
 
+/// Seeks the file back to the start of the data that follows its header
+fn seek_body(file: &File) -> Result<&File, Eu4Error> {
+    let mut reader = file;
+    reader.seek(std::io::SeekFrom::Start(TXT_HEADER.len() as u64))?;
+    Ok(file)
+}
+
+fn reopen_body(file: &File) -> Result<Box<dyn Read + '_>, Eu4Error> {
+    Ok(Box::new(seek_body(file)?))
+}
+
 fn file_header(data: &[u8]) -> Option<(FileHeader, &[u8])> {
     if data.len() < TXT_HEADER.len() {
         return None;
@@ -623,10 +1578,72 @@ fn file_header(data: &[u8]) -> Option<(FileHeader, &[u8])> {
     }
 }
 
+/// Deserializes the metadata and gamestate into models that borrow from the
+/// text
+fn parse_borrowed_text<'a>(
+    meta: &'a [u8],
+    gamestate: &'a [u8],
+) -> Result<borrowed::Eu4Save<'a>, Eu4Error> {
+    let tape = TextTape::from_slice(meta)?;
+    let meta: Meta = TextDeserializer::from_windows1252_tape(&tape).deserialize()?;
+    let tape = TextTape::from_slice(gamestate)?;
+    let game: borrowed::GameState<'a> =
+        TextDeserializer::from_windows1252_tape(&tape).deserialize()?;
+    Ok(borrowed::Eu4Save { meta, game })
+}
+
+/// Deserializes the metadata and gamestate into models that borrow from the
+/// binary data
+fn parse_borrowed_binary<'a, Resolver>(
+    meta: &'a [u8],
+    gamestate: &'a [u8],
+    resolver: &'a Resolver,
+) -> Result<borrowed::Eu4Save<'a>, Eu4Error>
+where
+    Resolver: TokenResolver,
+{
+    use jomini::binary::BinaryFlavor;
+    let meta: Meta = Eu4Flavor::new()
+        .deserializer()
+        .deserialize_slice(meta, resolver)?;
+    let game: borrowed::GameState<'a> = Eu4Flavor::new()
+        .deserializer()
+        .deserialize_slice(gamestate, resolver)?;
+    Ok(borrowed::Eu4Save { meta, game })
+}
+
+/// Reads the end of the file where the checksum is written and then seeks
+/// to the start of the data after the header
+fn read_tail(mut file: &File, header_len: usize) -> Result<Vec<u8>, Eu4Error> {
+    let len = file.metadata()?.len();
+    let tail_start = len
+        .saturating_sub(CHECKSUM_TAIL_LEN as u64)
+        .max(header_len as u64);
+    file.seek(std::io::SeekFrom::Start(tail_start))?;
+    let mut tail = Vec::with_capacity(CHECKSUM_TAIL_LEN);
+    file.read_to_end(&mut tail)?;
+    file.seek(std::io::SeekFrom::Start(header_len as u64))?;
+    Ok(tail)
+}
+
+/// Opens the input of a modeller again from its start
+type Reopen<'obj> = Box<dyn Fn() -> Result<Box<dyn Read + 'obj>, Eu4Error> + 'obj>;
+
 pub struct Eu4Modeller<'obj, R: jomini::binary::TokenResolver> {
     reader: Box<dyn Read + 'obj>,
     resolver: R,
     encoding: Option<Encoding>,
+
+    // Track how far into the input deserialization reached and the keys
+    // that lead to the value being deserialized so errors can be located
+    tracker: Tracker<'static>,
+    path: PathTracker,
+
+    // Tracking keys slows down deserialization, so keys are only tracked
+    // when parsing leniently or while repeating a parse that failed. Errors
+    // from input that can't be reopened only report their byte offset
+    track_path: bool,
+    reopen: Option<Reopen<'obj>>,
 }
 
 impl<'obj, R: jomini::binary::TokenResolver> Eu4Modeller<'obj, R> {
@@ -635,6 +1652,23 @@ impl<'obj, R: jomini::binary::TokenResolver> Eu4Modeller<'obj, R> {
             reader: Box::new(reader),
             resolver,
             encoding: None,
+            tracker: Tracker::new(ProgressHooks::new()),
+            path: PathTracker::new(),
+            track_path: false,
+            reopen: None,
+        }
+    }
+
+    /// Allows a failed parse to be repeated from the start of the input with
+    /// key tracking enabled, so that keys aren't tracked while parsing
+    /// succeeds
+    pub(crate) fn with_reopen<F>(self, reopen: F) -> Self
+    where
+        F: Fn() -> Result<Box<dyn Read + 'obj>, Eu4Error> + 'obj,
+    {
+        Eu4Modeller {
+            reopen: Some(Box::new(reopen)),
+            ..self
         }
     }
 
@@ -645,6 +1679,19 @@ impl<'obj, R: jomini::binary::TokenResolver> Eu4Modeller<'obj, R> {
         }
     }
 
+    pub fn with_options(self, options: ParseOptions) -> Self {
+        Eu4Modeller {
+            path: self.path.with_lenient(options.is_lenient()),
+            ..self
+        }
+    }
+
+    /// Takes the fields that were replaced with their default when parsing
+    /// leniently
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        self.path.take_diagnostics()
+    }
+
     pub fn encoding(&self) -> Encoding {
         self.encoding.unwrap_or(Encoding::Text)
     }
@@ -653,7 +1700,57 @@ impl<'obj, R: jomini::binary::TokenResolver> Eu4Modeller<'obj, R> {
     where
         T: DeserializeOwned,
     {
-        T::deserialize(self)
+        let encoding = self.encoding;
+        let result = T::deserialize(&mut *self);
+
+        // Lenient values were already recorded with their path as they were
+        // recovered
+        if self.path.is_lenient() {
+            return result;
+        }
+
+        result.map_err(|err| self.locate::<T>(err, encoding))
+    }
+
+    /// Repeats a failed parse from the start of the input while tracking
+    /// keys to find the path to the value that failed
+    fn locate<T>(&self, err: Eu4Error, encoding: Option<Encoding>) -> Eu4Error
+    where
+        T: DeserializeOwned,
+    {
+        let Some(reader) = self.reopen.as_ref().and_then(|reopen| reopen().ok()) else {
+            return err;
+        };
+
+        let mut retry = Eu4Modeller::from_reader(reader, &self.resolver);
+        retry.encoding = encoding;
+        retry.track_path = true;
+        match T::deserialize(&mut retry) {
+            Ok(_) => err,
+            Err(e) => {
+                let path = e.path().map(String::from);
+                err.with_location(path, None)
+            }
+        }
+    }
+
+    /// Deserializes the data and takes the fields that were replaced with
+    /// their default when parsing leniently
+    pub fn deserialize_with_diagnostics<T>(mut self) -> Result<(T, Vec<Diagnostic>), Eu4Error>
+    where
+        T: DeserializeOwned,
+    {
+        let data = self.deserialize()?;
+        Ok((data, self.take_diagnostics()))
+    }
+
+    /// Streams the countries, provinces, and wars of a gamestate into the
+    /// visitor as each one is decoded
+    pub fn visit<V>(&mut self, visitor: &mut V) -> Result<(), Eu4Error>
+    where
+        V: GameStateVisitor,
+    {
+        GameStateVisitorSeed(visitor).deserialize(self)
     }
 }
 
@@ -685,6 +1782,7 @@ impl<'de, 'a: 'de, R: jomini::binary::TokenResolver> serde::de::Deserializer<'de
             None => {
                 let mut header = [0u8; BIN_HEADER.len()];
                 self.reader.read_exact(&mut header)?;
+                self.tracker.add_bytes(header.len());
                 let encoding = match file_header(&header) {
                     Some((FileHeader::Text, _)) => Encoding::Text,
                     Some((FileHeader::Binary, _)) => Encoding::Binary,
@@ -695,18 +1793,33 @@ impl<'de, 'a: 'de, R: jomini::binary::TokenResolver> serde::de::Deserializer<'de
             }
         };
 
-        if matches!(encoding, Encoding::Binary) {
+        let reader = TrackedReader::new(&mut self.reader, &self.tracker);
+        let track_path = self.track_path || self.path.is_lenient();
+        let result = if matches!(encoding, Encoding::Binary) {
             use jomini::binary::BinaryFlavor;
             let flavor = Eu4Flavor::new();
-            let mut deser = flavor
-                .deserializer()
-                .from_reader(&mut self.reader, &self.resolver);
-            Ok(deser.deserialize_struct(name, fields, visitor)?)
+            let mut deser = flavor.deserializer().from_reader(reader, &self.resolver);
+            if track_path {
+                self.path
+                    .deserializer(&mut deser)
+                    .deserialize_struct(name, fields, visitor)
+            } else {
+                (&mut deser).deserialize_struct(name, fields, visitor)
+            }
         } else {
-            let reader = jomini::text::TokenReader::new(&mut self.reader);
+            let reader = jomini::text::TokenReader::new(reader);
             let mut deser = TextDeserializer::from_windows1252_reader(reader);
-            Ok(deser.deserialize_struct(name, fields, visitor)?)
-        }
+            if track_path {
+                self.path
+                    .deserializer(&mut deser)
+                    .deserialize_struct(name, fields, visitor)
+            } else {
+                (&mut deser).deserialize_struct(name, fields, visitor)
+            }
+        };
+
+        let offset = self.tracker.bytes();
+        result.map_err(|e| Eu4Error::from(e).with_location(self.path.take(), Some(offset)))
     }
 
     serde::forward_to_deserialize_any! {
@@ -717,6 +1830,7 @@ impl<'de, 'a: 'de, R: jomini::binary::TokenResolver> serde::de::Deserializer<'de
 }
 
 enum CompressedReaderKind<R: Read> {
+    Store(R),
     Deflate(flate2::read::DeflateDecoder<R>),
     #[cfg(feature = "zstd_c")]
     ZstdC(zstd::stream::Decoder<'static, BufReader<R>>),
@@ -734,6 +1848,9 @@ impl<R: Read> CompressedFileReader<R> {
         R: Read,
     {
         match compression {
+            CompressionMethod::STORE => Ok(CompressedFileReader {
+                reader: CompressedReaderKind::Store(reader),
+            }),
             CompressionMethod::DEFLATE => {
                 let inflater = flate2::read::DeflateDecoder::new(reader);
                 Ok(CompressedFileReader {
@@ -773,6 +1890,7 @@ where
 {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         match &mut self.reader {
+            CompressedReaderKind::Store(reader) => reader.read(buf),
             CompressedReaderKind::Deflate(reader) => reader.read(buf),
             #[cfg(feature = "zstd_c")]
             CompressedReaderKind::ZstdC(reader) => reader.read(buf),
@@ -802,6 +1920,7 @@ impl Display for Eu4FileEntryName {
 
 /// A parsed EU4 text document
 pub struct Eu4ParsedText<'a> {
+    data: &'a [u8],
     tape: TextTape<'a>,
 }
 
@@ -818,10 +1937,239 @@ impl<'a> Eu4ParsedText<'a> {
     /// Parse headerless EU4 text data
     pub fn from_raw(data: &'a [u8]) -> Result<Self, Eu4Error> {
         let tape = TextTape::from_slice(data).map_err(Eu4ErrorKind::Parse)?;
-        Ok(Eu4ParsedText { tape })
+        Ok(Eu4ParsedText { data, tape })
     }
 
     pub fn reader(&self) -> ObjectReader<'_, '_, Windows1252Encoding> {
         self.tape.windows1252_reader()
     }
+
+    pub(crate) fn tape(&self) -> &TextTape<'a> {
+        &self.tape
+    }
+
+    pub(crate) fn data(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_entry<R: ReaderAt>(zip: &Eu4Zip<R>, name: Eu4FileEntryName) -> Vec<u8> {
+        let mut entry = zip.get(name).unwrap();
+        let mut out = Vec::new();
+        entry.read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_deserialize_error_location() {
+        let data = b"EU4txt\ndate=1444.11.11\ncountries={\n  FRA={ government_rank=abc }\n}\n";
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        let err = file.parse_save(SegmentedResolver::empty()).unwrap_err();
+        assert_eq!(err.path(), Some("countries.FRA.government_rank"));
+        assert!(err.offset().is_some());
+        assert!(err.to_string().contains("countries.FRA.government_rank"));
+    }
+
+    #[test]
+    fn test_deserialize_error_location_binary() {
+        let data = b"EU4txt\ndate=1444.11.11\ncountries={\n  FRA={ government_rank=abc }\n}\n";
+        let resolver = SegmentedResolver::empty();
+        let mut binary = Vec::new();
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        file.unmelt(resolver.encoder(), &mut binary).unwrap();
+
+        let file = Eu4File::from_slice(&binary).unwrap();
+        let err = file.parse_save(&resolver).unwrap_err();
+        assert_eq!(err.path(), Some("countries.FRA.government_rank"));
+        assert!(err.offset().is_some());
+    }
+
+    #[test]
+    fn test_parse_save_lenient() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"SWE\"\n\
+displayed_country_name=\"Sweden\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\nchecksum=\"abc123\"\n\
+current_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\n\
+trade={ }\nreligion_instance_data={ }\nreligions={ catholic={ } }\nprovinces={ -1={ name=\"Stockholm\" \
+base_tax=lots base_manpower=2.5 is_city=yes institutions={ 0 } } }\n\
+active_war={ name=\"War\" history={ 1444.11.11={ add_attacker=SWE declare_ultimatum=yes } } \
+original_attacker=SWE original_defender=DAN }\nincome_statistics={ }\nnation_size_statistics={ }\n\
+score_statistics={ }\ninflation_statistics={ }\ngameplaysettings={ setgameplayoptions={ 9 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\n\
+diplomacy={ }\n";
+
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        let resolver = SegmentedResolver::empty();
+        assert!(file.parse_save(&resolver).is_err());
+
+        let (save, diagnostics) = file
+            .parse_save_with(&resolver, ParseOptions::lenient())
+            .unwrap();
+        let stockholm = &save.game.provinces[&ProvinceId::from(1)];
+        assert_eq!(stockholm.base_tax, 0.0);
+        assert_eq!(stockholm.base_manpower, 2.5);
+        assert!(stockholm.is_city);
+        assert_eq!(save.game.active_wars[0].history.events.len(), 1);
+
+        let paths = diagnostics
+            .iter()
+            .map(|x| x.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![
+                "provinces.-1.base_tax",
+                "active_war.history.1444.11.11.declare_ultimatum",
+                "gameplaysettings.setgameplayoptions.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_zip_writer_roundtrip() {
+        let meta = b"EU4txt\ndate=1444.11.11\n";
+        let gamestate = b"EU4txt\ncurrent_age=age_of_discovery\n";
+        let ai = b"EU4txt\nai={ }\n";
+
+        for compression in [CompressionMethod::DEFLATE, CompressionMethod::STORE] {
+            let mut out = Vec::new();
+            let mut writer = Eu4ZipWriter::new(&mut out)
+                .with_compression(compression)
+                .unwrap();
+            writer
+                .write_entry(Eu4FileEntryName::Gamestate, &gamestate[..])
+                .unwrap();
+            writer
+                .write_entry(Eu4FileEntryName::Meta, &meta[..])
+                .unwrap();
+            writer.write_entry(Eu4FileEntryName::Ai, &ai[..]).unwrap();
+            writer.finish().unwrap();
+
+            let file = Eu4File::from_slice(&out).unwrap();
+            assert_eq!(file.encoding(), Encoding::TextZip);
+            let Eu4SliceFileKind::Zip(zip) = file.kind() else {
+                panic!("expected zip");
+            };
+
+            assert_eq!(read_entry(zip, Eu4FileEntryName::Meta), meta);
+            assert_eq!(read_entry(zip, Eu4FileEntryName::Gamestate), gamestate);
+            assert_eq!(read_entry(zip, Eu4FileEntryName::Ai), ai);
+        }
+    }
+
+    #[test]
+    fn test_zip_writer_strip_ai() {
+        let mut out = Vec::new();
+        let mut writer = Eu4ZipWriter::new(&mut out);
+        writer
+            .write_entry(Eu4FileEntryName::Meta, &b"EU4bin"[..])
+            .unwrap();
+        writer
+            .write_entry(Eu4FileEntryName::Gamestate, &b"EU4bin"[..])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let file = Eu4File::from_slice(&out).unwrap();
+        assert_eq!(file.encoding(), Encoding::BinaryZip);
+        let Eu4SliceFileKind::Zip(zip) = file.kind() else {
+            panic!("expected zip");
+        };
+        assert_eq!(read_entry(zip, Eu4FileEntryName::Ai), b"EU4bin");
+    }
+
+    #[test]
+    fn test_zip_writer_mixed_encoding() {
+        let mut out = Vec::new();
+        let mut writer = Eu4ZipWriter::new(&mut out);
+        writer
+            .write_entry(Eu4FileEntryName::Meta, &b"EU4bin"[..])
+            .unwrap();
+        let err = writer
+            .write_entry(Eu4FileEntryName::Gamestate, &b"EU4txt\n"[..])
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Eu4ErrorKind::MixedEntryEncoding(Eu4FileEntryName::Gamestate)
+        ));
+    }
+
+    #[test]
+    fn test_zip_writer_unsupported_compression() {
+        let mut out = Vec::new();
+        let result = Eu4ZipWriter::new(&mut out).with_compression(CompressionMethod::BZIP2);
+        let err = result.err().unwrap();
+        assert!(matches!(err.kind(), Eu4ErrorKind::UnknownCompression));
+    }
+
+    #[test]
+    fn test_fs_parse_meta_then_save() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"SWE\"\n\
+displayed_country_name=\"Sweden\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\nchecksum=\"abc123\"\n\
+current_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\n\
+trade={ }\nreligion_instance_data={ }\nreligions={ catholic={ } }\nprovinces={ -1={ name=\"Stockholm\" \
+culture=swedish institutions={ 0 } } }\nincome_statistics={ }\nnation_size_statistics={ }\n\
+score_statistics={ }\ninflation_statistics={ }\ngameplaysettings={ setgameplayoptions={ 1 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\n\
+diplomacy={ }\n";
+        let path = std::env::temp_dir().join(format!("eu4save-fs-{}.eu4", std::process::id()));
+        std::fs::write(&path, &data[..]).unwrap();
+        let file = Eu4File::from_file(File::open(&path).unwrap()).unwrap();
+        let resolver = SegmentedResolver::empty();
+        let meta = file.parse_meta(&resolver);
+        let save = file.parse_save(&resolver);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(meta.unwrap().player.as_str(), "SWE");
+        let save = save.unwrap();
+        assert_eq!(save.meta.player.as_str(), "SWE");
+        assert_eq!(save.game.provinces.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_save_borrowed_zip() {
+        let meta = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"SWE\"\n\
+displayed_country_name=\"Sweden\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\nchecksum=\"abc123\"\n";
+        let gamestate = b"EU4txt\ncurrent_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\n\
+trade={ }\nreligion_instance_data={ }\nreligions={ catholic={ } }\nprovinces={ -1={ name=\"Stockholm\" \
+culture=swedish institutions={ 0 } } }\nincome_statistics={ }\nnation_size_statistics={ }\n\
+score_statistics={ }\ninflation_statistics={ }\ngameplaysettings={ setgameplayoptions={ 1 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\n\
+diplomacy={ }\n";
+
+        let mut zip = Vec::new();
+        let mut writer = Eu4ZipWriter::new(&mut zip);
+        writer
+            .write_entry(Eu4FileEntryName::Meta, &meta[..])
+            .unwrap();
+        writer
+            .write_entry(Eu4FileEntryName::Gamestate, &gamestate[..])
+            .unwrap();
+        writer
+            .write_entry(Eu4FileEntryName::Ai, &b"EU4txt\n"[..])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let file = Eu4File::from_slice(&zip).unwrap();
+        let resolver = SegmentedResolver::empty();
+        let mut buf = Vec::new();
+        let save = file.parse_save_borrowed(&resolver, &mut buf).unwrap();
+        assert_eq!(save.meta.player, "SWE");
+        let stockholm = &save.game.provinces[&ProvinceId::from(1)];
+        assert!(matches!(
+            stockholm.culture,
+            Some(std::borrow::Cow::Borrowed("swedish"))
+        ));
+
+        let expected = file.parse_save(&resolver).unwrap();
+        let save = Eu4Save::from(save);
+        let stockholm = &save.game.provinces[&ProvinceId::from(1)];
+        assert_eq!(
+            stockholm.culture,
+            expected.game.provinces[&ProvinceId::from(1)].culture
+        );
+        assert_eq!(save.game.religions[0].0, expected.game.religions[0].0);
+    }
 }
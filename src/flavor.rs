@@ -83,6 +83,20 @@ pub fn decode_eu4_escaped_text(mut input: &[u8]) -> String {
     String::from_utf16_lossy(&wide_chars)
 }
 
+/// Converts a utf-8 string into windows-1252 bytes, replacing characters that
+/// can't be represented with a question mark
+pub(crate) fn encode_windows1252(input: &str) -> Vec<u8> {
+    input
+        .chars()
+        .map(|c| match u32::from(c) {
+            x @ (0x00..=0x7F | 0xA0..=0xFF) => x as u8,
+            x => (0x80..=0x9F)
+                .find(|&b| cp1252_to_ucs2(b) == x)
+                .unwrap_or(b'?'),
+        })
+        .collect()
+}
+
 /// Converts a CP1252 byte to its UCS-2 equivalent
 fn cp1252_to_ucs2(cp: u8) -> u32 {
     match cp {
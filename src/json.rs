@@ -0,0 +1,938 @@
+//! Writes melted binary tokens as JSON
+//!
+//! A container is written as an object once its first entry is seen to be a
+//! field, else as an array, so only the pending key of each open container
+//! and the values of an object that aren't part of a field (its "remainder")
+//! are held back while melting. Grouping duplicate keys needs the entire
+//! document, so it is buffered and rendered at the end.
+use crate::{melt::MeltWriter, Eu4Error, Eu4ErrorKind};
+use jomini::{binary::Rgb, common::PdsDateFormatter, json::TypeNarrowing, Windows1252Encoding};
+use std::{collections::HashMap, io::Write};
+
+/// Bytes of rendered JSON that are buffered before writing them out
+const FLUSH_LEN: usize = 1 << 15;
+
+/// Controls how fields that share a key in an object are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JsonDuplicateKeys {
+    /// Write every field in the order encountered, so an object may contain
+    /// the same key more than once: `{"core":"AAA","core":"BBB"}`
+    #[default]
+    Preserve,
+
+    /// Group the values of a repeated key into an array at the position of
+    /// the first occurrence: `{"core":["AAA","BBB"]}`
+    ///
+    /// A repeated key may appear anywhere in an object, so the whole document
+    /// is buffered in memory and nothing is written until melting finishes.
+    /// Each container is rendered into its parent's buffer when it closes,
+    /// so nested data is copied once per level of depth and peak memory is a
+    /// multiple of the size of the JSON output.
+    Group,
+}
+
+/// Customizes the JSON written when melting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JsonMeltOptions {
+    duplicate_keys: JsonDuplicateKeys,
+    type_narrowing: TypeNarrowing,
+}
+
+impl Default for JsonMeltOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JsonMeltOptions {
+    pub fn new() -> Self {
+        Self {
+            duplicate_keys: JsonDuplicateKeys::Preserve,
+            type_narrowing: TypeNarrowing::All,
+        }
+    }
+
+    pub fn duplicate_keys(self, duplicate_keys: JsonDuplicateKeys) -> Self {
+        JsonMeltOptions {
+            duplicate_keys,
+            ..self
+        }
+    }
+
+    /// Which strings are converted to booleans and numbers when they look
+    /// like one
+    pub fn type_narrowing(self, type_narrowing: TypeNarrowing) -> Self {
+        JsonMeltOptions {
+            type_narrowing,
+            ..self
+        }
+    }
+
+    /// The equivalent options for converting plaintext saves to JSON
+    pub(crate) fn text_options(&self) -> jomini::json::JsonOptions {
+        let duplicate_keys = match self.duplicate_keys {
+            JsonDuplicateKeys::Preserve => jomini::json::DuplicateKeyMode::Preserve,
+            JsonDuplicateKeys::Group => jomini::json::DuplicateKeyMode::Group,
+        };
+
+        jomini::json::JsonOptions::new()
+            .with_duplicate_keys(duplicate_keys)
+            .with_type_narrowing(self.type_narrowing)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Item {
+    /// Decoded text of a scalar and if it should be narrowed
+    Scalar {
+        start: usize,
+        end: usize,
+        narrow: bool,
+    },
+
+    /// Rendered JSON of a closed container
+    Container {
+        start: usize,
+        end: usize,
+    },
+
+    Equal,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Entry {
+    Field { key: usize, value: usize },
+    Value(usize),
+}
+
+/// A container that is buffered until it is closed so that its repeated keys
+/// can be grouped
+#[derive(Debug, Default)]
+struct Frame {
+    buf: Vec<u8>,
+    items: Vec<Item>,
+    object: bool,
+    key_next: bool,
+}
+
+impl Frame {
+    fn clear(&mut self) {
+        self.buf.clear();
+        self.items.clear();
+        self.object = false;
+        self.key_next = false;
+    }
+
+    /// Marks that a value (which may be a key) has been written
+    fn wrote_value(&mut self) {
+        if self.object {
+            self.key_next = !self.key_next;
+        }
+    }
+
+    fn text(&self, item: Item) -> &[u8] {
+        match item {
+            Item::Scalar { start, end, .. } | Item::Container { start, end } => {
+                &self.buf[start..end]
+            }
+            Item::Equal => b"",
+        }
+    }
+
+    fn entries(&self) -> Vec<Entry> {
+        let mut result = Vec::new();
+        let mut i = 0;
+        while i < self.items.len() {
+            match (self.items[i], self.items.get(i + 1), self.items.get(i + 2)) {
+                (Item::Scalar { .. }, Some(Item::Equal), Some(x)) if !matches!(x, Item::Equal) => {
+                    result.push(Entry::Field {
+                        key: i,
+                        value: i + 2,
+                    });
+                    i += 3;
+                }
+                (Item::Equal, _, _) => i += 1,
+                _ => {
+                    result.push(Entry::Value(i));
+                    i += 1;
+                }
+            }
+        }
+        result
+    }
+
+    fn write_key(&self, item: usize, out: &mut Vec<u8>) {
+        write_string(out, self.text(self.items[item]));
+        out.push(b':');
+    }
+
+    fn write_value(&self, item: usize, out: &mut Vec<u8>) {
+        match self.items[item] {
+            x @ Item::Scalar { narrow, .. } => write_scalar(out, self.text(x), narrow),
+            x @ Item::Container { .. } => out.extend_from_slice(self.text(x)),
+            Item::Equal => out.extend_from_slice(b"null"),
+        }
+    }
+
+    fn write_field(&self, key: usize, value: usize, out: &mut Vec<u8>) {
+        self.write_key(key, out);
+        self.write_value(value, out);
+    }
+
+    /// Renders the frame as an object when it is the document or starts with
+    /// a field, else as an array. The values of a repeated key in an object
+    /// are grouped into an array, values in an object that aren't part of a
+    /// field are written to a "remainder" array, and fields in an array are
+    /// written as single field objects.
+    fn render(&self, is_document: bool, out: &mut Vec<u8>) {
+        let entries = self.entries();
+        let is_object = is_document || matches!(entries.first(), Some(Entry::Field { .. }));
+        if !is_object {
+            out.push(b'[');
+            for (i, entry) in entries.iter().enumerate() {
+                if i != 0 {
+                    out.push(b',');
+                }
+
+                match *entry {
+                    Entry::Field { key, value } => {
+                        out.push(b'{');
+                        self.write_field(key, value, out);
+                        out.push(b'}');
+                    }
+                    Entry::Value(x) => self.write_value(x, out),
+                }
+            }
+            out.push(b']');
+            return;
+        }
+
+        out.push(b'{');
+        let mut first = true;
+        let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+        let mut positions: HashMap<&[u8], usize> = HashMap::new();
+        for entry in &entries {
+            if let Entry::Field { key, value } = *entry {
+                let text = self.text(self.items[key]);
+                let position = *positions.entry(text).or_insert_with(|| {
+                    groups.push((key, Vec::new()));
+                    groups.len() - 1
+                });
+                groups[position].1.push(value);
+            }
+        }
+
+        for (key, values) in &groups {
+            if !std::mem::take(&mut first) {
+                out.push(b',');
+            }
+
+            self.write_key(*key, out);
+            if let [value] = values.as_slice() {
+                self.write_value(*value, out);
+            } else {
+                out.push(b'[');
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        out.push(b',');
+                    }
+                    self.write_value(*value, out);
+                }
+                out.push(b']');
+            }
+        }
+
+        let mut remainder = entries.iter().filter_map(|x| match x {
+            Entry::Value(x) => Some(*x),
+            Entry::Field { .. } => None,
+        });
+
+        if let Some(value) = remainder.next() {
+            if !first {
+                out.push(b',');
+            }
+            out.extend_from_slice(b"\"remainder\":[");
+            self.write_value(value, out);
+            for value in remainder {
+                out.push(b',');
+                self.write_value(value, out);
+            }
+            out.push(b']');
+        }
+
+        out.push(b'}');
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Kind {
+    /// Nothing has been written for the container yet
+    #[default]
+    Unknown,
+    Object,
+    Array,
+}
+
+/// A container that is written out as its contents are seen. It becomes an
+/// object when its first entry is a field, else an array.
+#[derive(Debug, Default)]
+struct Stream {
+    kind: Kind,
+
+    /// The stream whose remainder the container is written to, else the
+    /// container is written to the output
+    sink: Option<usize>,
+
+    /// If a field has been written to the object
+    wrote_field: bool,
+
+    /// Decoded text of a scalar that may be the key of a field
+    key: Vec<u8>,
+    has_key: bool,
+    narrow: bool,
+
+    /// If the pending key has been followed by an equal sign
+    equal: bool,
+
+    /// If a field of an array is being written and needs its object closed
+    close_field: bool,
+
+    /// Rendered values of an object that aren't part of a field
+    remainder: Vec<u8>,
+}
+
+impl Stream {
+    fn clear(&mut self) {
+        self.kind = Kind::Unknown;
+        self.sink = None;
+        self.wrote_field = false;
+        self.key.clear();
+        self.has_key = false;
+        self.equal = false;
+        self.close_field = false;
+        self.remainder.clear();
+    }
+}
+
+/// Writes melted tokens as JSON
+pub(crate) struct JsonWriter<W> {
+    writer: W,
+    options: JsonMeltOptions,
+
+    /// Open containers when duplicate keys are preserved, with the document
+    /// at the bottom
+    streams: Vec<Stream>,
+    spare_streams: Vec<Stream>,
+
+    /// Open containers when duplicate keys are grouped
+    document: Frame,
+    stack: Vec<Frame>,
+    spare: Vec<Frame>,
+
+    /// Decoded text of the value being written
+    scratch: Vec<u8>,
+
+    /// Rendered JSON that has yet to be written out
+    output: Vec<u8>,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W, options: JsonMeltOptions) -> Self {
+        let grouping = options.duplicate_keys == JsonDuplicateKeys::Group;
+        let document = Stream {
+            kind: Kind::Object,
+            ..Stream::default()
+        };
+
+        JsonWriter {
+            writer,
+            options,
+            streams: if grouping { Vec::new() } else { vec![document] },
+            spare_streams: Vec::new(),
+            document: Frame {
+                object: true,
+                key_next: true,
+                ..Frame::default()
+            },
+            stack: Vec::new(),
+            spare: Vec::new(),
+            scratch: Vec::new(),
+            output: if grouping { Vec::new() } else { vec![b'{'] },
+        }
+    }
+
+    fn is_grouping(&self) -> bool {
+        self.options.duplicate_keys == JsonDuplicateKeys::Group
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.stack.last_mut().unwrap_or(&mut self.document)
+    }
+
+    fn top(&mut self) -> usize {
+        self.streams.len() - 1
+    }
+
+    fn sink(&mut self, sink: Option<usize>) -> &mut Vec<u8> {
+        match sink {
+            Some(i) => &mut self.streams[i].remainder,
+            None => &mut self.output,
+        }
+    }
+
+    /// Writes the separator of a value that isn't part of a field and
+    /// returns where the value is written
+    fn value_sink(&mut self) -> Option<usize> {
+        let top = self.top();
+        let stream = &mut self.streams[top];
+        if stream.kind == Kind::Object {
+            if !stream.remainder.is_empty() {
+                stream.remainder.push(b',');
+            }
+            return Some(top);
+        }
+
+        let separator = if stream.kind == Kind::Unknown {
+            b'['
+        } else {
+            b','
+        };
+        stream.kind = Kind::Array;
+        let sink = stream.sink;
+        self.sink(sink).push(separator);
+        sink
+    }
+
+    /// Writes the pending scalar as a value as it didn't become a key
+    fn flush_key(&mut self) {
+        let top = self.top();
+        let stream = &mut self.streams[top];
+        if !stream.has_key {
+            return;
+        }
+
+        stream.has_key = false;
+        stream.equal = false;
+        let narrow = stream.narrow;
+        let key = std::mem::take(&mut stream.key);
+        let sink = self.value_sink();
+        write_scalar(self.sink(sink), &key, narrow);
+
+        let stream = &mut self.streams[top];
+        stream.key = key;
+        stream.key.clear();
+    }
+
+    /// Writes what precedes a value (its key or separator) and returns where
+    /// the value is written
+    fn start_value(&mut self) -> Option<usize> {
+        let top = self.top();
+        let stream = &mut self.streams[top];
+        if !stream.equal {
+            self.flush_key();
+            return self.value_sink();
+        }
+
+        let in_array = stream.kind == Kind::Array;
+        let separator = in_array || std::mem::replace(&mut stream.wrote_field, true);
+        stream.has_key = false;
+        stream.equal = false;
+        stream.close_field = in_array;
+        let sink = stream.sink;
+        let key = std::mem::take(&mut stream.key);
+
+        let out = self.sink(sink);
+        if separator {
+            out.push(b',');
+        }
+        if in_array {
+            out.push(b'{');
+        }
+        write_string(out, &key);
+        out.push(b':');
+
+        let stream = &mut self.streams[top];
+        stream.key = key;
+        stream.key.clear();
+        sink
+    }
+
+    /// Closes the single field object when a field in an array was written
+    fn end_value(&mut self) {
+        let top = self.top();
+        let stream = &mut self.streams[top];
+        if std::mem::take(&mut stream.close_field) {
+            let sink = stream.sink;
+            self.sink(sink).push(b'}');
+        }
+    }
+
+    /// Closes the innermost container
+    fn close_stream(&mut self) {
+        self.flush_key();
+        let Some(mut stream) = self.streams.pop() else {
+            return;
+        };
+
+        let out = self.sink(stream.sink);
+        match stream.kind {
+            Kind::Unknown => out.extend_from_slice(b"[]"),
+            Kind::Array => out.push(b']'),
+            Kind::Object => {
+                if !stream.remainder.is_empty() {
+                    if stream.wrote_field {
+                        out.push(b',');
+                    }
+                    out.extend_from_slice(b"\"remainder\":[");
+                    out.extend_from_slice(&stream.remainder);
+                    out.push(b']');
+                }
+                out.push(b'}');
+            }
+        }
+
+        stream.clear();
+        self.spare_streams.push(stream);
+    }
+
+    /// Writes out the rendered JSON once enough has accumulated
+    fn flush(&mut self) -> Result<(), Eu4Error> {
+        if self.output.len() >= FLUSH_LEN {
+            self.writer.write_all(&self.output)?;
+            self.output.clear();
+        }
+
+        Ok(())
+    }
+
+    /// Appends a scalar to the current container with its text written by
+    /// the given function
+    fn push_scalar<F>(&mut self, quoted: bool, write: F) -> Result<(), Eu4Error>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let narrow = match self.options.type_narrowing {
+            TypeNarrowing::All => true,
+            TypeNarrowing::Unquoted => !quoted,
+            TypeNarrowing::None => false,
+        };
+
+        if self.is_grouping() {
+            let frame = self.frame();
+            let start = frame.buf.len();
+            write(&mut frame.buf);
+            let end = frame.buf.len();
+            frame.items.push(Item::Scalar { start, end, narrow });
+            frame.wrote_value();
+            return Ok(());
+        }
+
+        let top = self.top();
+        if self.streams[top].equal {
+            let mut scratch = std::mem::take(&mut self.scratch);
+            scratch.clear();
+            write(&mut scratch);
+            let sink = self.start_value();
+            write_scalar(self.sink(sink), &scratch, narrow);
+            self.scratch = scratch;
+            self.end_value();
+        } else {
+            self.flush_key();
+            let stream = &mut self.streams[top];
+            write(&mut stream.key);
+            stream.has_key = true;
+            stream.narrow = narrow;
+        }
+
+        self.flush()
+    }
+
+    fn write_text(&mut self, quoted: bool, data: &[u8]) -> Result<(), Eu4Error> {
+        self.push_scalar(quoted, |buf| {
+            buf.extend_from_slice(Windows1252Encoding::decode(data).as_bytes())
+        })
+    }
+
+    fn write_display(&mut self, data: impl std::fmt::Display) -> Result<(), Eu4Error> {
+        self.push_scalar(false, |buf| {
+            let _ = write!(buf, "{}", data);
+        })
+    }
+
+    /// Closes the document and writes out what remains
+    pub fn finish(mut self) -> Result<W, Eu4Error> {
+        if !self.stack.is_empty() || self.streams.len() > 1 {
+            return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(
+                "unclosed container at end of document".to_string(),
+            )));
+        }
+
+        if self.is_grouping() {
+            self.document.render(true, &mut self.output);
+        } else {
+            self.close_stream();
+        }
+
+        self.writer.write_all(&self.output)?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> MeltWriter for JsonWriter<W> {
+    fn depth(&self) -> usize {
+        if self.is_grouping() {
+            self.stack.len()
+        } else {
+            self.streams.len().saturating_sub(1)
+        }
+    }
+
+    fn expecting_key(&self) -> bool {
+        if self.is_grouping() {
+            let frame = self.stack.last().unwrap_or(&self.document);
+            frame.object && frame.key_next
+        } else {
+            self.streams
+                .last()
+                .is_some_and(|x| x.kind == Kind::Object && !x.has_key)
+        }
+    }
+
+    fn write_start(&mut self) -> Result<(), Eu4Error> {
+        if self.is_grouping() {
+            let frame = self.spare.pop().unwrap_or_default();
+            self.stack.push(frame);
+            return Ok(());
+        }
+
+        let sink = self.start_value();
+        let mut stream = self.spare_streams.pop().unwrap_or_default();
+        stream.sink = sink;
+        self.streams.push(stream);
+        Ok(())
+    }
+
+    fn write_end(&mut self) -> Result<(), Eu4Error> {
+        if !self.is_grouping() {
+            if self.streams.len() <= 1 {
+                return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(
+                    "unexpected end of container".to_string(),
+                )));
+            }
+
+            self.close_stream();
+            self.end_value();
+            return self.flush();
+        }
+
+        let Some(mut child) = self.stack.pop() else {
+            return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(
+                "unexpected end of container".to_string(),
+            )));
+        };
+
+        let parent = self.frame();
+        let start = parent.buf.len();
+        child.render(false, &mut parent.buf);
+        let end = parent.buf.len();
+        parent.items.push(Item::Container { start, end });
+        parent.wrote_value();
+
+        child.clear();
+        self.spare.push(child);
+        Ok(())
+    }
+
+    fn write_equal(&mut self) -> Result<(), Eu4Error> {
+        if self.is_grouping() {
+            let frame = self.frame();
+            frame.items.push(Item::Equal);
+            frame.object = true;
+            frame.key_next = false;
+            return Ok(());
+        }
+
+        let top = self.top();
+        let stream = &mut self.streams[top];
+        if stream.has_key && !stream.equal {
+            stream.equal = true;
+            if stream.kind == Kind::Unknown {
+                stream.kind = Kind::Object;
+                let sink = stream.sink;
+                self.sink(sink).push(b'{');
+            }
+        }
+        Ok(())
+    }
+
+    fn write_bool(&mut self, data: bool) -> Result<(), Eu4Error> {
+        self.write_text(false, if data { b"yes" } else { b"no" })
+    }
+
+    fn write_u32(&mut self, data: u32) -> Result<(), Eu4Error> {
+        self.write_display(data)
+    }
+
+    fn write_u64(&mut self, data: u64) -> Result<(), Eu4Error> {
+        self.write_display(data)
+    }
+
+    fn write_i32(&mut self, data: i32) -> Result<(), Eu4Error> {
+        self.write_display(data)
+    }
+
+    fn write_i64(&mut self, data: i64) -> Result<(), Eu4Error> {
+        self.write_display(data)
+    }
+
+    fn write_date(&mut self, data: PdsDateFormatter) -> Result<(), Eu4Error> {
+        self.write_display(data)
+    }
+
+    fn write_rgb(&mut self, data: &Rgb) -> Result<(), Eu4Error> {
+        let mut rgb = std::mem::take(&mut self.scratch);
+        rgb.clear();
+        write!(rgb, "{{\"rgb\":[{},{},{}", data.r, data.g, data.b)?;
+        if let Some(a) = data.a {
+            write!(rgb, ",{}", a)?;
+        }
+        rgb.extend_from_slice(b"]}");
+
+        if self.is_grouping() {
+            let frame = self.frame();
+            let start = frame.buf.len();
+            frame.buf.extend_from_slice(&rgb);
+            let end = frame.buf.len();
+            frame.items.push(Item::Container { start, end });
+            frame.wrote_value();
+        } else {
+            let sink = self.start_value();
+            self.sink(sink).extend_from_slice(&rgb);
+            self.end_value();
+        }
+
+        self.scratch = rgb;
+        self.flush()
+    }
+
+    fn write_unquoted(&mut self, data: &[u8]) -> Result<(), Eu4Error> {
+        self.write_text(false, data)
+    }
+
+    fn write_quoted(&mut self, data: &[u8]) -> Result<(), Eu4Error> {
+        self.write_text(true, data)
+    }
+
+    fn write_unknown(&mut self, token: u16) -> Result<(), Eu4Error> {
+        self.push_scalar(false, |buf| {
+            let _ = write!(buf, "__unknown_0x{:x}", token);
+        })
+    }
+}
+
+/// Writes the scalar narrowed to a boolean or number when requested, else
+/// as a string
+fn write_scalar(out: &mut Vec<u8>, data: &[u8], narrow: bool) {
+    if narrow {
+        write_narrowed(out, data)
+    } else {
+        write_string(out, data)
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, data: &[u8]) {
+    out.push(b'"');
+    for &byte in data {
+        match byte {
+            b'"' => out.extend_from_slice(b"\\\""),
+            b'\\' => out.extend_from_slice(b"\\\\"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\t' => out.extend_from_slice(b"\\t"),
+            0x00..=0x1f => {
+                let _ = write!(out, "\\u{:04x}", byte);
+            }
+            _ => out.push(byte),
+        }
+    }
+    out.push(b'"');
+}
+
+/// Returns if the text is a decimal number without an exponent
+fn is_decimal(data: &str) -> bool {
+    let digits = data.strip_prefix(['-', '+']).unwrap_or(data);
+    let mut seen_digit = false;
+    let mut seen_dot = false;
+    for c in digits.bytes() {
+        match c {
+            b'0'..=b'9' => seen_digit = true,
+            b'.' if !seen_dot => seen_dot = true,
+            _ => return false,
+        }
+    }
+    seen_digit
+}
+
+/// Writes the scalar as a boolean or number when it can be interpreted as
+/// one, else as a string
+fn write_narrowed(out: &mut Vec<u8>, data: &[u8]) {
+    match data {
+        b"yes" => return out.extend_from_slice(b"true"),
+        b"no" => return out.extend_from_slice(b"false"),
+        _ => {}
+    }
+
+    let Some(text) = std::str::from_utf8(data).ok().filter(|x| is_decimal(x)) else {
+        return write_string(out, data);
+    };
+
+    if let Ok(x) = text.parse::<i64>() {
+        let _ = write!(out, "{}", x);
+    } else if let Ok(x) = text.parse::<u64>() {
+        let _ = write!(out, "{}", x);
+    } else if let Ok(x) = text.parse::<f64>() {
+        let start = out.len();
+        let _ = write!(out, "{}", x);
+        if !out[start..].contains(&b'.') {
+            out.extend_from_slice(b".0");
+        }
+    } else {
+        write_string(out, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Eu4File, MeltOptions, SegmentedResolver};
+
+    fn resolver() -> SegmentedResolver<'static> {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "date";
+        values[0x21] = "player";
+        values[0x22] = "treasury";
+        values[0x23] = "provinces";
+        values[0x24] = "owner";
+        values[0x25] = "color";
+        values[0x26] = "cores";
+        values[0x27] = "hre";
+        values[0x28] = "is_ironman";
+        values[0x29] = "history";
+        values[0x2a] = "religion";
+        SegmentedResolver::from_parts(values, 0x30, 0x30)
+    }
+
+    const TEXT: &str = "EU4txt\ndate=1444.11.11\nplayer=\"ENG\"\nis_ironman=yes\ntreasury=100.250\nprovinces={\n\t-1={\n\t\towner=\"ENG\"\n\t\tcores={ ENG FRA }\n\t\thre=yes\n\t\treligion=catholic\n\t\treligion=protestant\n\t\tcolor=rgb { 10 20 30 }\n\t\thistory={ }\n\t}\n}";
+
+    fn melt_json(json: JsonMeltOptions) -> (String, String) {
+        let resolver = resolver();
+        let mut binary = Vec::new();
+        let file = Eu4File::from_slice(TEXT.as_bytes()).unwrap();
+        file.unmelt(resolver.encoder(), &mut binary).unwrap();
+
+        let file = Eu4File::from_slice(&binary).unwrap();
+        let mut melted = Vec::new();
+        file.melt(MeltOptions::new(), &resolver, &mut melted)
+            .unwrap();
+        let mut expected = Vec::new();
+        Eu4File::from_slice(&melted)
+            .unwrap()
+            .melt_json(MeltOptions::new(), json, &resolver, &mut expected)
+            .unwrap();
+
+        let mut actual = Vec::new();
+        file.melt_json(MeltOptions::new(), json, &resolver, &mut actual)
+            .unwrap();
+
+        (
+            String::from_utf8(actual).unwrap(),
+            String::from_utf8(expected).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_melt_json_preserve() {
+        let (actual, expected) = melt_json(JsonMeltOptions::new());
+        assert_eq!(actual, expected);
+        assert!(actual.starts_with(r#"{"date":"1444.11.11","player":"ENG","treasury":100.25,"#));
+        assert!(actual.contains(r#""religion":"catholic","religion":"protestant""#));
+        assert!(actual.contains(r#""color":{"rgb":[10,20,30]}"#));
+        assert!(!actual.contains("is_ironman"));
+    }
+
+    #[test]
+    fn test_melt_json_group() {
+        let json = JsonMeltOptions::new().duplicate_keys(JsonDuplicateKeys::Group);
+        let (actual, expected) = melt_json(json);
+        assert_eq!(actual, expected);
+        assert!(actual.contains(r#""religion":["catholic","protestant"]"#));
+    }
+
+    #[test]
+    fn test_melt_json_rgb_alpha() {
+        let mut writer = JsonWriter::new(Vec::new(), JsonMeltOptions::new());
+        writer.write_unquoted(b"color").unwrap();
+        writer.write_equal().unwrap();
+        let rgb = Rgb {
+            r: 10,
+            g: 20,
+            b: 30,
+            a: Some(40),
+        };
+        writer.write_rgb(&rgb).unwrap();
+        let out = writer.finish().unwrap();
+        assert_eq!(out, br#"{"color":{"rgb":[10,20,30,40]}}"#);
+    }
+
+    #[test]
+    fn test_streamed_containers_match_grouped() {
+        let write = |json: JsonMeltOptions| {
+            let mut writer = JsonWriter::new(Vec::new(), json);
+            writer.write_unquoted(b"a").unwrap();
+            writer.write_equal().unwrap();
+            writer.write_start().unwrap();
+            writer.write_i32(1).unwrap();
+            writer.write_unquoted(b"b").unwrap();
+            writer.write_equal().unwrap();
+            writer.write_start().unwrap();
+            writer.write_unquoted(b"c").unwrap();
+            writer.write_equal().unwrap();
+            writer.write_quoted(b"d").unwrap();
+            writer.write_start().unwrap();
+            writer.write_end().unwrap();
+            writer.write_unquoted(b"e").unwrap();
+            writer.write_end().unwrap();
+            writer.write_end().unwrap();
+            writer.write_start().unwrap();
+            writer.write_end().unwrap();
+            writer.write_unquoted(b"f").unwrap();
+            writer.write_equal().unwrap();
+            String::from_utf8(writer.finish().unwrap()).unwrap()
+        };
+
+        let preserved = write(JsonMeltOptions::new());
+        let grouped = write(JsonMeltOptions::new().duplicate_keys(JsonDuplicateKeys::Group));
+        assert_eq!(preserved, grouped);
+        assert_eq!(
+            preserved,
+            r#"{"a":[1,{"b":{"c":"d","remainder":[[],"e"]}}],"remainder":[[],"f"]}"#
+        );
+    }
+
+    #[test]
+    fn test_write_narrowed() {
+        let narrow = |x: &str| {
+            let mut out = Vec::new();
+            write_narrowed(&mut out, x.as_bytes());
+            String::from_utf8(out).unwrap()
+        };
+
+        assert_eq!(narrow("yes"), "true");
+        assert_eq!(narrow("-12"), "-12");
+        assert_eq!(narrow("18446744073709551615"), "18446744073709551615");
+        assert_eq!(narrow("1.000"), "1.0");
+        assert_eq!(narrow("0.500"), "0.5");
+        assert_eq!(narrow("1444.11.11"), "\"1444.11.11\"");
+        assert_eq!(narrow("inf"), "\"inf\"");
+    }
+}
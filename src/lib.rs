@@ -43,6 +43,18 @@ assert_eq!(Some(17.982), trade);
 # Ok::<(), Box<dyn std::error::Error>>(())
 ```
 
+## Async runtimes
+
+With the `async` feature, `Eu4File::from_async_reader` reads a save from a
+tokio `AsyncRead` into memory. Parsing and melting are CPU bound, so the
+returned `Eu4AsyncFile` runs them on tokio's blocking thread pool to keep
+them from stalling the runtime.
+
+```ignore
+let file = Eu4File::from_async_reader(tokio::fs::File::open(path).await?).await?;
+let save = file.parse_save(resolver).await?;
+```
+
 ## Ironman
 
 Ironman saves are supported through a provided `TokenResolver`. Per PDS counsel, the data to construct such a `TokenResolver` is not distributed here.
@@ -51,21 +63,41 @@ You may look to other projects EU4 ironman projects like ironmelt or paperman
 for inspiration.
 */
 
+#[cfg(feature = "async")]
+mod async_file;
+/// Aggregate many saves from the same playthrough
+pub mod campaign;
 mod country_tag;
 pub mod de;
+/// Compare two saves from the same campaign
+pub mod diff;
+/// Infer the names of unknown binary tokens
+pub mod discover;
+pub mod document;
 mod errors;
 mod eu4date;
 mod extraction;
 pub mod file;
 pub mod flavor;
+mod json;
 mod melt;
+mod meta;
 /// Repository of raw structs extracted from a save file
 pub mod models;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod parse;
+mod progress;
 mod province_id;
 /// Ergonomic module for querying info from a save file
 pub mod query;
+mod registry;
 mod resolver;
+mod scan;
+/// Selectively deserialize sections of the gamestate
+pub mod sections;
 mod tag_resolver;
+mod unmelt;
 
 pub use country_tag::*;
 pub use errors::*;
@@ -74,7 +106,12 @@ pub use extraction::*;
 #[doc(inline)]
 pub use file::Eu4File;
 pub use jomini::binary::{BasicTokenResolver, FailedResolveStrategy};
+pub use json::{JsonDuplicateKeys, JsonMeltOptions};
 pub use melt::*;
+pub use parse::*;
+pub use progress::*;
 pub use province_id::*;
-pub use resolver::{SegmentedResolver, SegmentedResolverBuilder};
+pub use registry::{ResolverRegistry, ResolverSelection, VersionFit, VersionRange};
+pub use resolver::{SegmentedResolver, SegmentedResolverBuilder, TokenChange, TokenDiff};
 pub use tag_resolver::*;
+pub use unmelt::TokenEncoder;
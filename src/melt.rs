@@ -1,12 +1,15 @@
-use crate::{flavor::Eu4Flavor, Eu4Date, Eu4Error, Eu4ErrorKind};
+use crate::{
+    flavor::Eu4Flavor, progress::Tracker, scan, Encoding, Eu4Date, Eu4Error, Eu4ErrorKind,
+    SegmentedResolver,
+};
 use jomini::{
-    binary::{self, BinaryFlavor, FailedResolveStrategy, TokenReader, TokenResolver},
-    common::PdsDate,
-    TextWriterBuilder,
+    binary::{self, BinaryFlavor, FailedResolveStrategy, Rgb, TokenReader, TokenResolver},
+    common::{PdsDate, PdsDateFormatter},
+    TextWriter, TextWriterBuilder,
 };
 use std::{
-    collections::HashSet,
-    io::{Read, Write},
+    collections::{btree_map::Entry, BTreeMap, HashSet},
+    io::{self, Read, Write},
 };
 
 #[derive(Debug, Clone, Copy)]
@@ -68,14 +71,60 @@ impl Quoter {
     }
 }
 
+/// How dates are written when melting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MeltDateFormat {
+    /// The format the game uses: `1444.11.11`
+    #[default]
+    Game,
+
+    /// ISO-8601: `1444-11-11`
+    Iso8601,
+}
+
+/// How nested fields are indented when melting
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MeltIndent {
+    /// A tab per level, like the game
+    #[default]
+    Tabs,
+
+    /// The given number of spaces per level
+    Spaces(u8),
+
+    /// No indentation
+    None,
+}
+
+/// How the melted document is laid out
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MeltLayout {
+    /// One field per line with array values written on a single line
+    #[default]
+    Lines,
+
+    /// The entire document on a single line with only the whitespace
+    /// necessary to separate values
+    Compact,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct MeltOptions {
     skip_checksum: bool,
     verbatim: bool,
     on_failed_resolve: FailedResolveStrategy,
     check_header: bool,
+    float_precision: Option<u8>,
+    trim_float_zeros: bool,
+    date_format: MeltDateFormat,
+    indent: MeltIndent,
+    layout: MeltLayout,
+    canonical: bool,
 }
 
+/// Decimal places of floats in canonical output unless otherwise specified
+const CANONICAL_FLOAT_PRECISION: usize = 5;
+
 impl Default for MeltOptions {
     fn default() -> Self {
         Self::new()
@@ -89,6 +138,12 @@ impl MeltOptions {
             verbatim: false,
             on_failed_resolve: FailedResolveStrategy::Ignore,
             check_header: true,
+            float_precision: None,
+            trim_float_zeros: false,
+            date_format: MeltDateFormat::Game,
+            indent: MeltIndent::Tabs,
+            layout: MeltLayout::Lines,
+            canonical: false,
         }
     }
 
@@ -116,11 +171,92 @@ impl MeltOptions {
             ..self
         }
     }
+
+    /// Number of decimal places written for every float. By default, the
+    /// precision matches the game's, which depends on the float's encoding.
+    pub fn float_precision(self, float_precision: u8) -> Self {
+        MeltOptions {
+            float_precision: Some(float_precision),
+            ..self
+        }
+    }
+
+    /// Remove trailing zeros (and a trailing decimal point) from floats, so
+    /// that `1.500` is written as `1.5` and `2.000` as `2`
+    pub fn trim_float_zeros(self, trim_float_zeros: bool) -> Self {
+        MeltOptions {
+            trim_float_zeros,
+            ..self
+        }
+    }
+
+    pub fn date_format(self, date_format: MeltDateFormat) -> Self {
+        MeltOptions {
+            date_format,
+            ..self
+        }
+    }
+
+    pub fn indent(self, indent: MeltIndent) -> Self {
+        MeltOptions { indent, ..self }
+    }
+
+    pub fn layout(self, layout: MeltLayout) -> Self {
+        MeltOptions { layout, ..self }
+    }
+
+    pub(crate) fn is_canonical(&self) -> bool {
+        self.canonical
+    }
+
+    /// Normalize the output so that two saves can be compared with a line
+    /// based diff. The entries of `countries` and `provinces` are sorted by
+    /// key, volatile fields (the checksum, random number seeds, and the unit
+    /// id counter) are dropped, and floats are written with the same
+    /// precision and without trailing zeros. Plaintext saves are normalized
+    /// too instead of being copied as is.
+    ///
+    /// Sorting needs the entire document, so canonical melting reads the
+    /// whole input (each entry of a zip) into memory and then copies it again
+    /// in the sorted order before anything is written.
+    pub fn canonical(self, canonical: bool) -> Self {
+        MeltOptions { canonical, ..self }
+    }
+
+    fn format_date(&self, date: Eu4Date) -> PdsDateFormatter {
+        match self.date_format {
+            MeltDateFormat::Game => date.game_fmt(),
+            MeltDateFormat::Iso8601 => date.iso_8601(),
+        }
+    }
+
+    /// Writes the float to the buffer with the configured precision, falling
+    /// back to the given precision
+    fn format_float(&self, buf: &mut String, data: f64, precision: usize) {
+        use std::fmt::Write as _;
+        let precision = match self.float_precision {
+            Some(x) => usize::from(x),
+            None if self.canonical => CANONICAL_FLOAT_PRECISION,
+            None => precision,
+        };
+
+        buf.clear();
+        let _ = write!(buf, "{:.*}", precision, data);
+        if (self.trim_float_zeros || self.canonical) && buf.contains('.') {
+            let len = buf.trim_end_matches('0').trim_end_matches('.').len();
+            buf.truncate(len);
+        }
+
+        if buf.as_str() == "-0" {
+            buf.remove(0);
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 pub struct MeltedDocument {
     pub(crate) unknown_tokens: HashSet<u16>,
+    pub(crate) unknown_report: UnknownTokenReport,
 }
 
 impl MeltedDocument {
@@ -132,6 +268,356 @@ impl MeltedDocument {
     pub fn unknown_tokens(&self) -> &HashSet<u16> {
         &self.unknown_tokens
     }
+
+    /// Where and how often each unknown token was written. Only collected
+    /// when unknown tokens are melted with [`FailedResolveStrategy::Stringify`]
+    /// as following the keys of the document slows down melting.
+    pub fn unknown_token_report(&self) -> &UnknownTokenReport {
+        &self.unknown_report
+    }
+
+    /// Combines the unknown tokens of separately melted entries
+    pub(crate) fn merge(&mut self, other: MeltedDocument) {
+        self.unknown_tokens.extend(other.unknown_tokens);
+        self.unknown_report.merge(other.unknown_report);
+    }
+}
+
+/// An unknown token that was written out as `__unknown_0xNNNN`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownToken {
+    pub token: u16,
+
+    /// Number of times the token was written as a key
+    pub key_occurrences: usize,
+
+    /// Number of times the token was written as a value
+    pub value_occurrences: usize,
+
+    /// The keys leading to the container of the first occurrence, like
+    /// `countries.ENG.history`. Empty at the top level.
+    pub path: String,
+
+    /// The first value the token was assigned to as a key, or the key it was
+    /// assigned to as a value. Containers are written as `{ ... }`.
+    pub sample: Option<String>,
+}
+
+impl UnknownToken {
+    /// Total number of times the token was written
+    pub fn occurrences(&self) -> usize {
+        self.key_occurrences + self.value_occurrences
+    }
+}
+
+/// The context of every unknown token encountered while melting
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownTokenReport {
+    tokens: BTreeMap<u16, UnknownToken>,
+}
+
+impl UnknownTokenReport {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn get(&self, token: u16) -> Option<&UnknownToken> {
+        self.tokens.get(&token)
+    }
+
+    /// The unknown tokens in ascending order
+    pub fn tokens(&self) -> impl Iterator<Item = &UnknownToken> + '_ {
+        self.tokens.values()
+    }
+
+    fn merge(&mut self, other: UnknownTokenReport) {
+        for (token, entry) in other.tokens {
+            match self.tokens.entry(token) {
+                Entry::Vacant(x) => {
+                    x.insert(entry);
+                }
+                Entry::Occupied(mut x) => {
+                    let x = x.get_mut();
+                    x.key_occurrences += entry.key_occurrences;
+                    x.value_occurrences += entry.value_occurrences;
+                }
+            }
+        }
+    }
+}
+
+/// The last scalar that was read in a key position, kept until an equal
+/// operator reveals that it was a key
+#[derive(Debug, Clone, Copy, Default)]
+enum PreviousScalar {
+    #[default]
+    Other,
+    Id(u16),
+    I32(i32),
+    U32(u32),
+    Text,
+}
+
+/// Follows the keys of the document being melted so that unknown tokens can
+/// be reported with where they were found
+#[derive(Debug, Default)]
+struct UnknownTokenCollector {
+    path: String,
+    lengths: Vec<usize>,
+    key: String,
+    text: String,
+    previous: PreviousScalar,
+    value_pending: bool,
+    last_was_value: bool,
+    candidate: Option<u16>,
+    awaiting_sample: Option<u16>,
+    report: UnknownTokenReport,
+}
+
+impl UnknownTokenCollector {
+    /// Observes every token before it is written
+    fn observe<R: TokenResolver>(&mut self, token: &binary::Token, resolver: &R) {
+        if matches!(token, binary::Token::Equal) {
+            self.key.clear();
+            match self.previous {
+                PreviousScalar::Other => {}
+                PreviousScalar::Id(x) => {
+                    write_scalar(&mut self.key, &binary::Token::Id(x), resolver)
+                }
+                PreviousScalar::I32(x) => {
+                    write_scalar(&mut self.key, &binary::Token::I32(x), resolver)
+                }
+                PreviousScalar::U32(x) => {
+                    write_scalar(&mut self.key, &binary::Token::U32(x), resolver)
+                }
+                PreviousScalar::Text => self.key.push_str(&self.text),
+            }
+
+            if let Some(unknown) = self.candidate.take() {
+                self.add(unknown, true);
+            }
+
+            self.previous = PreviousScalar::Other;
+            self.value_pending = true;
+            return;
+        }
+
+        // An unknown token in a key position that isn't followed by an equal
+        // operator is an element of an array
+        if let Some(unknown) = self.candidate.take() {
+            self.add(unknown, false);
+        }
+
+        if let Some(unknown) = self.awaiting_sample.take() {
+            let mut sample = String::new();
+            match token {
+                binary::Token::Open => sample.push_str("{ ... }"),
+                binary::Token::Close => {}
+                _ => write_scalar(&mut sample, token, resolver),
+            }
+
+            if let Some(entry) = self.report.tokens.get_mut(&unknown) {
+                entry.sample = Some(sample).filter(|x| !x.is_empty());
+            }
+        }
+
+        self.last_was_value = false;
+        match token {
+            binary::Token::Open => {
+                self.lengths.push(self.path.len());
+                if self.value_pending && !self.key.is_empty() {
+                    if !self.path.is_empty() {
+                        self.path.push('.');
+                    }
+                    self.path.push_str(&self.key);
+                }
+                self.key.clear();
+                self.previous = PreviousScalar::Other;
+                self.value_pending = false;
+            }
+            binary::Token::Close => {
+                if let Some(len) = self.lengths.pop() {
+                    self.path.truncate(len);
+                }
+                self.key.clear();
+                self.previous = PreviousScalar::Other;
+                self.value_pending = false;
+            }
+            _ if self.value_pending => {
+                self.value_pending = false;
+                self.last_was_value = true;
+                self.previous = PreviousScalar::Other;
+            }
+            binary::Token::Id(x) => self.previous = PreviousScalar::Id(*x),
+            binary::Token::I32(x) => self.previous = PreviousScalar::I32(*x),
+            binary::Token::U32(x) => self.previous = PreviousScalar::U32(*x),
+            binary::Token::Quoted(_) | binary::Token::Unquoted(_) => {
+                self.text.clear();
+                write_scalar(&mut self.text, token, resolver);
+                self.previous = PreviousScalar::Text;
+            }
+            _ => self.previous = PreviousScalar::Other,
+        }
+    }
+
+    /// Records the unknown token that was just observed
+    fn record(&mut self, token: u16) {
+        if self.last_was_value {
+            self.add(token, false);
+        } else {
+            self.candidate = Some(token);
+        }
+    }
+
+    fn add(&mut self, token: u16, as_key: bool) {
+        let entry = self.report.tokens.entry(token).or_insert_with(|| {
+            if as_key {
+                self.awaiting_sample = Some(token);
+            }
+
+            // The key of an unknown value is only known outside of arrays
+            let sample = (!as_key && self.last_was_value).then(|| self.key.clone());
+            UnknownToken {
+                token,
+                key_occurrences: 0,
+                value_occurrences: 0,
+                path: self.path.clone(),
+                sample,
+            }
+        });
+
+        if as_key {
+            entry.key_occurrences += 1;
+        } else {
+            entry.value_occurrences += 1;
+        }
+    }
+
+    fn finish(mut self) -> UnknownTokenReport {
+        if let Some(unknown) = self.candidate.take() {
+            self.add(unknown, false);
+        }
+        self.report
+    }
+}
+
+/// Appends a short textual form of a scalar token
+fn write_scalar<R: TokenResolver>(buf: &mut String, token: &binary::Token, resolver: &R) {
+    use std::fmt::Write as _;
+    let _ = match token {
+        binary::Token::U32(x) => write!(buf, "{}", x),
+        binary::Token::U64(x) => write!(buf, "{}", x),
+        binary::Token::I64(x) => write!(buf, "{}", x),
+        binary::Token::I32(x) => match Eu4Date::from_binary_heuristic(*x) {
+            Some(date) => write!(buf, "{}", date.game_fmt()),
+            None => write!(buf, "{}", x),
+        },
+        binary::Token::Bool(x) => write!(buf, "{}", if *x { "yes" } else { "no" }),
+        binary::Token::F32(x) => write!(buf, "{}", Eu4Flavor::new().visit_f32(*x)),
+        binary::Token::F64(x) => write!(buf, "{}", Eu4Flavor::new().visit_f64(*x)),
+        binary::Token::Quoted(x) | binary::Token::Unquoted(x) => write!(buf, "{}", x),
+        binary::Token::Id(x) => match resolver.resolve(*x) {
+            Some(id) => write!(buf, "{}", id),
+            None => write!(buf, "__unknown_0x{:x}", x),
+        },
+        binary::Token::Rgb(x) => write!(buf, "rgb {{ {} {} {} }}", x.r, x.g, x.b),
+        _ => Ok(()),
+    };
+}
+
+/// Receives the values decoded from binary tokens while melting
+pub(crate) trait MeltWriter {
+    fn depth(&self) -> usize;
+    fn expecting_key(&self) -> bool;
+    fn write_start(&mut self) -> Result<(), Eu4Error>;
+    fn write_end(&mut self) -> Result<(), Eu4Error>;
+    fn write_equal(&mut self) -> Result<(), Eu4Error>;
+    fn write_bool(&mut self, data: bool) -> Result<(), Eu4Error>;
+    fn write_u32(&mut self, data: u32) -> Result<(), Eu4Error>;
+    fn write_u64(&mut self, data: u64) -> Result<(), Eu4Error>;
+    fn write_i32(&mut self, data: i32) -> Result<(), Eu4Error>;
+    fn write_i64(&mut self, data: i64) -> Result<(), Eu4Error>;
+    fn write_date(&mut self, data: PdsDateFormatter) -> Result<(), Eu4Error>;
+    fn write_rgb(&mut self, data: &Rgb) -> Result<(), Eu4Error>;
+    fn write_unquoted(&mut self, data: &[u8]) -> Result<(), Eu4Error>;
+    fn write_quoted(&mut self, data: &[u8]) -> Result<(), Eu4Error>;
+    fn write_unknown(&mut self, token: u16) -> Result<(), Eu4Error>;
+}
+
+impl<W: Write> MeltWriter for TextWriter<W> {
+    fn depth(&self) -> usize {
+        TextWriter::depth(self)
+    }
+
+    fn expecting_key(&self) -> bool {
+        TextWriter::expecting_key(self)
+    }
+
+    fn write_start(&mut self) -> Result<(), Eu4Error> {
+        Ok(self.write_array_start()?)
+    }
+
+    fn write_end(&mut self) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_end(self)?)
+    }
+
+    fn write_equal(&mut self) -> Result<(), Eu4Error> {
+        Ok(self.write_operator(jomini::text::Operator::Equal)?)
+    }
+
+    fn write_bool(&mut self, data: bool) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_bool(self, data)?)
+    }
+
+    fn write_u32(&mut self, data: u32) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_u32(self, data)?)
+    }
+
+    fn write_u64(&mut self, data: u64) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_u64(self, data)?)
+    }
+
+    fn write_i32(&mut self, data: i32) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_i32(self, data)?)
+    }
+
+    fn write_i64(&mut self, data: i64) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_i64(self, data)?)
+    }
+
+    fn write_date(&mut self, data: PdsDateFormatter) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_date(self, data)?)
+    }
+
+    fn write_rgb(&mut self, data: &Rgb) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_rgb(self, data)?)
+    }
+
+    fn write_unquoted(&mut self, data: &[u8]) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_unquoted(self, data)?)
+    }
+
+    fn write_quoted(&mut self, data: &[u8]) -> Result<(), Eu4Error> {
+        Ok(TextWriter::write_quoted(self, data)?)
+    }
+
+    fn write_unknown(&mut self, token: u16) -> Result<(), Eu4Error> {
+        Ok(write!(self, "__unknown_0x{:x}", token)?)
+    }
+}
+
+fn text_writer_builder(options: &MeltOptions) -> TextWriterBuilder {
+    let mut builder = TextWriterBuilder::new();
+    match options.indent {
+        MeltIndent::Tabs => builder.indent_char(b'\t').indent_factor(1),
+        MeltIndent::Spaces(x) => builder.indent_char(b' ').indent_factor(x),
+        MeltIndent::None => builder.indent_factor(0),
+    };
+    builder
 }
 
 pub(crate) fn melt<Reader, Writer, Resolver>(
@@ -139,42 +625,341 @@ pub(crate) fn melt<Reader, Writer, Resolver>(
     output: Writer,
     resolver: Resolver,
     options: MeltOptions,
+    tracker: &Tracker,
 ) -> Result<MeltedDocument, Eu4Error>
 where
     Reader: Read,
     Writer: Write,
     Resolver: TokenResolver,
 {
+    let mut builder = text_writer_builder(&options);
+    match options.layout {
+        MeltLayout::Lines => {
+            let mut wtr = builder.from_writer(output);
+            melt_tokens(input, &mut wtr, resolver, options, tracker)
+        }
+        MeltLayout::Compact => {
+            let output = CompactWriter::new(output);
+            let mut wtr = builder.indent_factor(0).from_writer(output);
+            melt_tokens(input, &mut wtr, resolver, options, tracker)
+        }
+    }
+}
+
+/// Rewrites the headerless plaintext data in canonical form so that it can
+/// be compared with other canonically melted saves
+pub(crate) fn melt_text_canonical<Writer>(
+    data: &[u8],
+    output: Writer,
+    options: MeltOptions,
+    tracker: &Tracker,
+) -> Result<MeltedDocument, Eu4Error>
+where
+    Writer: Write,
+{
+    let body = canonical_order(data, Encoding::Text, &SegmentedResolver::empty())?;
+    let reader = jomini::text::TokenReader::from_slice(body.as_slice());
+    let mut builder = text_writer_builder(&options);
+    match options.layout {
+        MeltLayout::Lines => {
+            let mut wtr = builder.from_writer(output);
+            write_text_tokens(reader, &mut wtr, options, tracker)?
+        }
+        MeltLayout::Compact => {
+            let output = CompactWriter::new(output);
+            let mut wtr = builder.indent_factor(0).from_writer(output);
+            write_text_tokens(reader, &mut wtr, options, tracker)?
+        }
+    }
+
+    Ok(MeltedDocument::new())
+}
+
+fn write_text_tokens<W: Write>(
+    mut reader: jomini::text::TokenReader,
+    wtr: &mut TextWriter<W>,
+    options: MeltOptions,
+    tracker: &Tracker,
+) -> Result<(), Eu4Error> {
+    use jomini::text::Token;
+    let mut float_buf = String::new();
+    while let Some(token) = reader.next()? {
+        tracker.add_token()?;
+        match token {
+            Token::Open => wtr.write_array_start()?,
+            Token::Close => wtr.write_end()?,
+            Token::Operator(op) => wtr.write_operator(op)?,
+            Token::Quoted(x) => wtr.write_quoted(x.as_bytes())?,
+            Token::Unquoted(x) => {
+                let data = x.as_bytes();
+                let volatile = wtr.expecting_key()
+                    && std::str::from_utf8(data).is_ok_and(|id| is_volatile(id, wtr.depth()));
+                if volatile {
+                    let mut next = reader.read()?;
+                    if matches!(next, Token::Operator(_)) {
+                        next = reader.read()?;
+                    }
+
+                    if matches!(next, Token::Open) {
+                        reader.skip_container()?;
+                    }
+                    continue;
+                }
+
+                match parse_decimal(data) {
+                    Some((value, precision)) => {
+                        options.format_float(&mut float_buf, value, precision);
+                        wtr.write_unquoted(float_buf.as_bytes())?
+                    }
+                    None => wtr.write_unquoted(data)?,
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a plaintext float (digits with a single decimal point) and the
+/// number of decimal places it was written with
+fn parse_decimal(data: &[u8]) -> Option<(f64, usize)> {
+    let digits = data.strip_prefix(b"-").unwrap_or(data);
+    let dot = digits.iter().position(|&x| x == b'.')?;
+    let (whole, fraction) = (&digits[..dot], &digits[dot + 1..]);
+    let is_digits = |x: &[u8]| !x.is_empty() && x.iter().all(u8::is_ascii_digit);
+    if !is_digits(whole) || !is_digits(fraction) {
+        return None;
+    }
+
+    let value = std::str::from_utf8(data).ok()?.parse::<f64>().ok()?;
+    Some((value, fraction.len()))
+}
+
+/// Collapses the line breaks that separate fields into the minimum
+/// whitespace needed to keep values apart. Line breaks inside quoted strings
+/// are left alone.
+struct CompactWriter<W> {
+    writer: W,
+    buf: Vec<u8>,
+    in_quote: bool,
+    escaped: bool,
+    pending_break: bool,
+    last: Option<u8>,
+}
+
+impl<W> CompactWriter<W> {
+    fn new(writer: W) -> Self {
+        CompactWriter {
+            writer,
+            buf: Vec::new(),
+            in_quote: false,
+            escaped: false,
+            pending_break: false,
+            last: None,
+        }
+    }
+}
+
+impl<W: Write> Write for CompactWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.clear();
+        for &byte in data {
+            if self.in_quote {
+                self.buf.push(byte);
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_quote = false;
+                }
+                continue;
+            }
+
+            if byte == b'\n' {
+                self.pending_break = true;
+                continue;
+            }
+
+            if std::mem::take(&mut self.pending_break)
+                && !matches!(self.last, None | Some(b'{') | Some(b' '))
+                && !matches!(byte, b'}' | b' ')
+            {
+                self.buf.push(b' ');
+            }
+
+            self.buf.push(byte);
+            self.in_quote = byte == b'"';
+            self.last = Some(byte);
+        }
+
+        self.writer.write_all(&self.buf)?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Decodes the binary tokens of the input into the writer
+pub(crate) fn melt_tokens<Reader, Writer, Resolver>(
+    input: Reader,
+    wtr: &mut Writer,
+    resolver: Resolver,
+    options: MeltOptions,
+    tracker: &Tracker,
+) -> Result<MeltedDocument, Eu4Error>
+where
+    Reader: Read,
+    Writer: MeltWriter,
+    Resolver: TokenResolver,
+{
+    if options.canonical {
+        let mut data = Vec::new();
+        let mut input = input;
+        input.read_to_end(&mut data)?;
+        let body = match options.check_header {
+            true => data
+                .strip_prefix(b"EU4bin")
+                .ok_or_else(|| Eu4Error::new(Eu4ErrorKind::UnknownHeader))?,
+            false => data.as_slice(),
+        };
+
+        let body = canonical_order(body, Encoding::Binary, &resolver)?;
+        let reader = TokenReader::new(body.as_slice());
+        return write_tokens(reader, wtr, resolver, options, tracker);
+    }
+
     let mut reader = TokenReader::new(input);
     if options.check_header && reader.read_bytes(6)? != b"EU4bin" {
         return Err(Eu4Error::new(Eu4ErrorKind::UnknownHeader));
     }
 
-    let mut wtr = TextWriterBuilder::new()
-        .indent_char(b'\t')
-        .indent_factor(1)
-        .from_writer(output);
+    write_tokens(reader, wtr, resolver, options, tracker)
+}
+
+/// Sort key of an entry in a section so that provinces are ordered by id and
+/// countries by tag
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum EntryKey {
+    Number(u64, i64),
+    Text(String),
+}
+
+impl EntryKey {
+    fn from_entry<R: TokenResolver>(entry: &[u8], encoding: Encoding, resolver: &R) -> Self {
+        if matches!(encoding, Encoding::Text) {
+            let mut reader = jomini::text::TokenReader::from_slice(entry);
+            return match reader.next() {
+                Ok(Some(jomini::text::Token::Quoted(x) | jomini::text::Token::Unquoted(x))) => {
+                    let text = x.to_string();
+                    match text.parse::<i64>() {
+                        Ok(x) => EntryKey::Number(x.unsigned_abs(), x),
+                        Err(_) => EntryKey::Text(text),
+                    }
+                }
+                _ => EntryKey::Text(String::new()),
+            };
+        }
+
+        let mut reader = TokenReader::from_slice(entry);
+        match reader.next() {
+            Ok(Some(binary::Token::I32(x))) => EntryKey::Number(x.unsigned_abs().into(), x.into()),
+            Ok(Some(binary::Token::U32(x))) => EntryKey::Number(x.into(), x.into()),
+            Ok(Some(binary::Token::Quoted(x) | binary::Token::Unquoted(x))) => {
+                EntryKey::Text(x.to_string())
+            }
+            Ok(Some(binary::Token::Id(x))) => {
+                EntryKey::Text(resolver.resolve(x).unwrap_or_default().to_string())
+            }
+            _ => EntryKey::Text(String::new()),
+        }
+    }
+}
+
+/// Rewrites the headerless data with the entries of the countries and
+/// provinces sorted, as their order can differ between saves
+fn canonical_order<R: TokenResolver>(
+    data: &[u8],
+    encoding: Encoding,
+    resolver: &R,
+) -> Result<Vec<u8>, Eu4Error> {
+    let sections = scan::scan(data, encoding, resolver)?;
+    let mut result = Vec::with_capacity(data.len());
+    let mut position = 0;
+    for section in &sections {
+        // Anything in the section that precedes the first entry stays put
+        let first = section.entries.first().copied().unwrap_or(section.end);
+        result.extend_from_slice(&data[position..section.start]);
+        result.extend_from_slice(&data[section.start..first]);
+
+        let mut entries = section
+            .entry_ranges()
+            .map(|range| {
+                let key = EntryKey::from_entry(&data[range.clone()], encoding, resolver);
+                (key, range)
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        for (_, range) in entries {
+            result.extend_from_slice(&data[range]);
+        }
+
+        position = section.end;
+    }
+
+    result.extend_from_slice(&data[position..]);
+    Ok(result)
+}
+
+/// Returns if the field changes between saves without reflecting a change
+/// in the game
+fn is_volatile(id: &str, depth: usize) -> bool {
+    id == "checksum" || id == "random" || id.ends_with("seed") || (depth == 0 && id == "unit")
+}
+
+fn write_tokens<Writer, Resolver>(
+    mut reader: TokenReader,
+    wtr: &mut Writer,
+    resolver: Resolver,
+    options: MeltOptions,
+    tracker: &Tracker,
+) -> Result<MeltedDocument, Eu4Error>
+where
+    Writer: MeltWriter,
+    Resolver: TokenResolver,
+{
     let flavor = Eu4Flavor::new();
     let mut unknown_tokens: HashSet<u16> = HashSet::new();
+    let mut collector = UnknownTokenCollector::default();
+    let track_unknown = options.on_failed_resolve == FailedResolveStrategy::Stringify;
     let skip_checksum = options.skip_checksum;
     let verbatim = options.verbatim;
+    let canonical = options.canonical;
     let on_failed_resolve = options.on_failed_resolve;
 
     let mut quoter = Quoter::default();
     let mut known_number = false;
     let mut known_date = false;
     let mut long_format = false;
+    let mut float_buf = String::new();
     while let Some(token) = reader.next()? {
+        tracker.add_token()?;
+        if track_unknown {
+            collector.observe(&token, &resolver);
+        }
+
         match token {
             jomini::binary::Token::Open => {
                 quoter.push();
-                wtr.write_array_start()?
+                wtr.write_start()?
             }
             jomini::binary::Token::Close => {
                 quoter.pop();
                 wtr.write_end()?
             }
-            jomini::binary::Token::Equal => wtr.write_operator(jomini::text::Operator::Equal)?,
+            jomini::binary::Token::Equal => wtr.write_equal()?,
             jomini::binary::Token::U32(x) => wtr.write_u32(x)?,
             jomini::binary::Token::U64(x) => wtr.write_u64(x)?,
             jomini::binary::Token::I32(x) => {
@@ -183,7 +968,7 @@ where
                     known_number = false;
                 } else if known_date {
                     if let Some(date) = Eu4Date::from_binary(x) {
-                        wtr.write_date(date.game_fmt())?;
+                        wtr.write_date(options.format_date(date))?;
                     } else if on_failed_resolve != FailedResolveStrategy::Error {
                         wtr.write_i32(x)?;
                     } else {
@@ -191,7 +976,7 @@ where
                     }
                     known_date = false;
                 } else if let Some(date) = Eu4Date::from_binary_heuristic(x) {
-                    wtr.write_date(date.game_fmt())?;
+                    wtr.write_date(options.format_date(date))?;
                 } else {
                     wtr.write_i32(x)?;
                 }
@@ -206,16 +991,23 @@ where
                 QuoteKind::UnquoteScalar => wtr.write_unquoted(x.as_bytes())?,
                 QuoteKind::QuoteScalar => wtr.write_quoted(x.as_bytes())?,
             },
-            jomini::binary::Token::F32(x) if long_format => {
-                write!(&mut wtr, "{:.6}", flavor.visit_f32(x))?
+            jomini::binary::Token::F32(x) => {
+                let precision = if long_format { 6 } else { 3 };
+                options.format_float(&mut float_buf, f64::from(flavor.visit_f32(x)), precision);
+                wtr.write_unquoted(float_buf.as_bytes())?
+            }
+            jomini::binary::Token::F64(x) => {
+                options.format_float(&mut float_buf, flavor.visit_f64(x), 5);
+                wtr.write_unquoted(float_buf.as_bytes())?
             }
-            jomini::binary::Token::F32(x) => write!(&mut wtr, "{:.3}", flavor.visit_f32(x))?,
-            jomini::binary::Token::F64(x) => write!(&mut wtr, "{:.5}", flavor.visit_f64(x))?,
             jomini::binary::Token::Rgb(x) => wtr.write_rgb(&x)?,
             jomini::binary::Token::I64(x) => wtr.write_i64(x)?,
             jomini::binary::Token::Id(x) => match resolver.resolve(x) {
                 Some(id) => {
-                    if (id == "checksum" && skip_checksum) || (id == "is_ironman" && !verbatim) {
+                    if (id == "checksum" && skip_checksum)
+                        || (id == "is_ironman" && !verbatim)
+                        || (canonical && wtr.expecting_key() && is_volatile(id, wtr.depth()))
+                    {
                         let mut next = reader.read()?;
                         if matches!(next, binary::Token::Equal) {
                             next = reader.read()?;
@@ -322,7 +1114,10 @@ where
                     }
                     _ => {
                         unknown_tokens.insert(x);
-                        write!(wtr, "__unknown_0x{:x}", x)?;
+                        if track_unknown {
+                            collector.record(x);
+                        }
+                        wtr.write_unknown(x)?;
                     }
                 },
             },
@@ -334,13 +1129,16 @@ where
         }
     }
 
-    Ok(MeltedDocument { unknown_tokens })
+    Ok(MeltedDocument {
+        unknown_tokens,
+        unknown_report: collector.finish(),
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{BasicTokenResolver, Eu4File};
+    use crate::{BasicTokenResolver, Eu4File, SegmentedResolver};
     use std::{io::Cursor, sync::LazyLock};
 
     static TOKENS: LazyLock<BasicTokenResolver> = LazyLock::new(|| {
@@ -501,4 +1299,169 @@ mod tests {
             expected
         );
     }
+
+    fn melt_formatted(options: MeltOptions) -> String {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "date";
+        values[0x21] = "player";
+        values[0x22] = "treasury";
+        values[0x23] = "country";
+        values[0x24] = "cores";
+        values[0x25] = "name";
+        let resolver = SegmentedResolver::from_parts(values, 0x30, 0x30);
+
+        let text = "EU4txt\ndate=1444.11.11\ncountry={\n\ttreasury=100.250\n\tcores={\n\t\t1 2\n\t}\n\tname=\"The\nEnd\"\n}";
+        let mut binary = Vec::new();
+        let file = Eu4File::from_slice(text.as_bytes()).unwrap();
+        file.unmelt(resolver.encoder(), &mut binary).unwrap();
+
+        let mut out = Vec::new();
+        let file = Eu4File::from_slice(&binary).unwrap();
+        file.melt(options, &resolver, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_melt_default_format() {
+        let actual = melt_formatted(MeltOptions::new());
+        let expected = "EU4txt\ndate=1444.11.11\ncountry={\n\ttreasury=100.250\n\tcores={\n\t\t1 2\n\t}\n\tname=\"The\nEnd\"\n}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_melt_custom_format() {
+        let options = MeltOptions::new()
+            .float_precision(5)
+            .trim_float_zeros(true)
+            .date_format(MeltDateFormat::Iso8601)
+            .indent(MeltIndent::Spaces(2));
+        let actual = melt_formatted(options);
+        let expected = "EU4txt\ndate=1444-11-11\ncountry={\n  treasury=100.25\n  cores={\n    1 2\n  }\n  name=\"The\nEnd\"\n}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_melt_compact_layout() {
+        let options = MeltOptions::new().layout(MeltLayout::Compact);
+        let actual = melt_formatted(options);
+        let expected =
+            "EU4txt\ndate=1444.11.11 country={treasury=100.250 cores={1 2} name=\"The\nEnd\"}";
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_format_float() {
+        let mut buf = String::new();
+        let options = MeltOptions::new().trim_float_zeros(true);
+        options.format_float(&mut buf, 2.0, 3);
+        assert_eq!(buf, "2");
+        options.format_float(&mut buf, -0.0001, 3);
+        assert_eq!(buf, "0");
+        options.float_precision(1).format_float(&mut buf, 0.25, 3);
+        assert_eq!(buf, "0.2");
+    }
+
+    #[test]
+    fn test_unknown_token_report() {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "countries";
+        values[0x21] = "mystery";
+        values[0x22] = "treasury";
+        values[0x23] = "leader";
+        values[0x24] = "wizard";
+        let encoder = SegmentedResolver::from_parts(values.clone(), 0x30, 0x30);
+
+        let text = "EU4txt\ncountries={\n\tENG={\n\t\tmystery={\n\t\t\tleader=wizard\n\t\t}\n\t\ttreasury=1.500\n\t}\n\tFRA={\n\t\tmystery={ }\n\t}\n}";
+        let mut binary = Vec::new();
+        let file = Eu4File::from_slice(text.as_bytes()).unwrap();
+        file.unmelt(encoder.encoder(), &mut binary).unwrap();
+
+        values[0x21] = "";
+        values[0x24] = "";
+        let resolver = SegmentedResolver::from_parts(values, 0x30, 0x30);
+        let mut out = Vec::new();
+        let file = Eu4File::from_slice(&binary).unwrap();
+        let doc = file
+            .melt(
+                MeltOptions::new().on_failed_resolve(FailedResolveStrategy::Stringify),
+                &resolver,
+                &mut out,
+            )
+            .unwrap();
+
+        let report = doc.unknown_token_report();
+        assert_eq!(report.len(), 2);
+        assert_eq!(
+            report.get(0x21),
+            Some(&UnknownToken {
+                token: 0x21,
+                key_occurrences: 2,
+                value_occurrences: 0,
+                path: String::from("countries.ENG"),
+                sample: Some(String::from("{ ... }")),
+            })
+        );
+        assert_eq!(
+            report.get(0x24),
+            Some(&UnknownToken {
+                token: 0x24,
+                key_occurrences: 0,
+                value_occurrences: 1,
+                path: String::from("countries.ENG.__unknown_0x21"),
+                sample: Some(String::from("leader")),
+            })
+        );
+        assert_eq!(report.tokens().map(|x| x.occurrences()).sum::<usize>(), 3);
+
+        let doc = file
+            .melt(MeltOptions::new(), &resolver, &mut Vec::new())
+            .unwrap();
+        assert!(!doc.unknown_tokens().is_empty());
+        assert!(doc.unknown_token_report().is_empty());
+    }
+
+    #[test]
+    fn test_melt_canonical() {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "date";
+        values[0x21] = "random";
+        values[0x22] = "unit";
+        values[0x23] = "countries";
+        values[0x24] = "provinces";
+        values[0x25] = "treasury";
+        values[0x26] = "name";
+        values[0x27] = "seed";
+        values[0x28] = "checksum";
+        values[0x29] = "mode";
+        let resolver = SegmentedResolver::from_parts(values, 0x30, 0x30);
+
+        let text = "EU4txt\ndate=1444.11.11\nrandom=123\nunit=4567\nmode=random\ncountries={\n\tSWE={\n\t\ttreasury=1.500\n\t}\n\tENG={\n\t\ttreasury=2.000\n\t\tseed=99\n\t}\n}\nprovinces={\n\t-10={\n\t\tname=\"b\"\n\t}\n\t-2={\n\t\tname=\"a\"\n\t}\n}\nchecksum=\"abc\"";
+        let mut binary = Vec::new();
+        let file = Eu4File::from_slice(text.as_bytes()).unwrap();
+        file.unmelt(resolver.encoder(), &mut binary).unwrap();
+
+        let mut out = Vec::new();
+        let file = Eu4File::from_slice(&binary).unwrap();
+        file.melt(MeltOptions::new().canonical(true), &resolver, &mut out)
+            .unwrap();
+
+        let expected = "EU4txt\ndate=1444.11.11\nmode=random\ncountries={\n\t\"ENG\"={\n\t\ttreasury=2\n\t}\n\tSWE={\n\t\ttreasury=1.5\n\t}\n}\nprovinces={\n\t-2={\n\t\tname=\"a\"\n\t}\n\t-10={\n\t\tname=\"b\"\n\t}\n}";
+        assert_eq!(std::str::from_utf8(&out).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_melt_canonical_text() {
+        let text = "EU4txt\ndate=1444.11.11\nrandom=123\nunit=4567\nmode=random\ncountries={\n\tSWE={\n\t\ttreasury=1.500\n\t}\n\tENG={\n\t\ttreasury=2.000\n\t\tseed=99\n\t}\n}\nprovinces={\n\t-10={\n\t\tname=\"b\"\n\t}\n\t-2={\n\t\tname=\"a\"\n\t}\n}\nchecksum=\"abc\"";
+        let mut out = Vec::new();
+        let file = Eu4File::from_slice(text.as_bytes()).unwrap();
+        file.melt(
+            MeltOptions::new().canonical(true),
+            SegmentedResolver::empty(),
+            &mut out,
+        )
+        .unwrap();
+
+        let expected = "EU4txt\ndate=1444.11.11\nmode=random\ncountries={\n\tENG={\n\t\ttreasury=2\n\t}\n\tSWE={\n\t\ttreasury=1.5\n\t}\n}\nprovinces={\n\t-2={\n\t\tname=\"a\"\n\t}\n\t-10={\n\t\tname=\"b\"\n\t}\n}";
+        assert_eq!(std::str::from_utf8(&out).unwrap(), expected);
+    }
 }
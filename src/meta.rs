@@ -0,0 +1,276 @@
+use crate::models::Meta;
+use jomini::binary::TokenResolver;
+use serde::{
+    de::{self, value::MapAccessDeserializer, DeserializeSeed, IntoDeserializer},
+    Deserialize, Deserializer,
+};
+use std::fmt;
+
+/// Number of bytes at the end of a save that are searched for the checksum
+pub(crate) const CHECKSUM_TAIL_LEN: usize = 256;
+
+/// Keys that only appear in the gamestate. Metadata is the prefix of a
+/// save, so the first of these marks the start of the gamestate. Keys that
+/// are in neither the metadata nor this list are treated as metadata, so a
+/// metadata field added by a new patch doesn't end the metadata early.
+const GAMESTATE_KEYS: &[&str] = &[
+    "speed",
+    "multiplayer_random_seed",
+    "multiplayer_random_count",
+    "current_age",
+    "next_age_progress",
+    "players_countries",
+    "gameplaysettings",
+    "start_date",
+    "map_area_data",
+    "total_military_power",
+    "average_military_power",
+    "id_counters",
+    "unit_template_id",
+    "flags",
+    "saved_event_target",
+    "institution_origin",
+    "institutions",
+    "trade",
+    "production_leader_tag",
+    "dynasty",
+    "rebel_faction",
+    "religions",
+    "religion_instance_data",
+    "fired_events",
+    "pending_events",
+    "provinces",
+    "countries",
+    "active_advisors",
+    "diplomacy",
+    "combat",
+    "active_war",
+    "previous_war",
+    "income_statistics",
+    "empire",
+    "celestial_empire",
+    "hre_leagues",
+    "trade_league",
+];
+
+/// Deserializes the metadata prefix of a save and stops reading at the
+/// start of the gamestate. A save's checksum is written at the end of the
+/// file, so the caller passes in the checksum found at the end.
+pub(crate) struct MetaSeed {
+    pub checksum: Option<String>,
+}
+
+impl<'de> DeserializeSeed<'de> for MetaSeed {
+    type Value = Meta;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Meta", &[], self)
+    }
+}
+
+impl<'de> de::Visitor<'de> for MetaSeed {
+    type Value = Meta;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("save metadata")
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let access = MetaPrefixAccess {
+            map,
+            checksum: self.checksum,
+            done: false,
+        };
+        Meta::deserialize(MapAccessDeserializer::new(access))
+    }
+}
+
+/// Yields entries of the underlying map until the gamestate starts, followed
+/// by the trailing checksum if one wasn't already encountered
+struct MetaPrefixAccess<A> {
+    map: A,
+    checksum: Option<String>,
+    done: bool,
+}
+
+impl<'de, A> de::MapAccess<'de> for MetaPrefixAccess<A>
+where
+    A: de::MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if !self.done {
+            match self.map.next_key::<String>()? {
+                Some(key) if !GAMESTATE_KEYS.contains(&key.as_str()) => {
+                    if key == "checksum" {
+                        self.checksum = None;
+                    }
+
+                    return seed.deserialize(key.into_deserializer()).map(Some);
+                }
+                _ => self.done = true,
+            }
+        }
+
+        match self.checksum {
+            Some(_) => seed.deserialize("checksum".into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if self.done {
+            let checksum = self.checksum.take().unwrap_or_default();
+            seed.deserialize(checksum.into_deserializer())
+        } else {
+            self.map.next_value_seed(seed)
+        }
+    }
+}
+
+/// Extracts the checksum from the end of a plaintext save
+pub(crate) fn text_checksum(tail: &[u8]) -> Option<String> {
+    const KEY: &[u8] = b"checksum";
+    let start = tail.windows(KEY.len()).rposition(|x| x == KEY)?;
+    let rest = tail[start + KEY.len()..].trim_ascii_start();
+    let rest = rest.strip_prefix(b"=")?.trim_ascii_start();
+    let rest = rest.strip_prefix(b"\"")?;
+    let end = rest.iter().position(|&x| x == b'"')?;
+    std::str::from_utf8(&rest[..end]).ok().map(String::from)
+}
+
+/// Extracts the checksum from the end of a binary save, where the checksum
+/// is the last field: the key's token, the equal operator, and a quoted
+/// string that runs to the end of the data.
+pub(crate) fn binary_checksum<R: TokenResolver>(tail: &[u8], resolver: &R) -> Option<String> {
+    const QUOTED: [u8; 2] = 0x000f_u16.to_le_bytes();
+    const EQUAL: [u8; 2] = 0x0001_u16.to_le_bytes();
+
+    (0..tail.len().saturating_sub(5)).rev().find_map(|i| {
+        let field = &tail[i..];
+        let (token, rest) = field.split_first_chunk::<2>()?;
+        let (equal, rest) = rest.split_first_chunk::<2>()?;
+        let (quoted, rest) = rest.split_first_chunk::<2>()?;
+        let (len, value) = rest.split_first_chunk::<2>()?;
+        let is_checksum = *equal == EQUAL
+            && *quoted == QUOTED
+            && usize::from(u16::from_le_bytes(*len)) == value.len()
+            && resolver.resolve(u16::from_le_bytes(*token)) == Some("checksum");
+        is_checksum.then(|| String::from_utf8_lossy(value).into_owned())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file::Eu4Modeller, Encoding, PdsDate, SegmentedResolver};
+
+    const META: &[u8] = br#"date=1444.11.11
+save_game="autosave.eu4"
+player="ENG"
+displayed_country_name="England"
+savegame_version={
+	first=1
+	second=35
+	third=0
+	forth=0
+	name="Lorraine"
+}
+savegame_versions={
+	"1.35.0.0"
+}
+dlc_enabled={
+	"Art of War"
+}
+multi_player=no
+not_observer=yes
+campaign_id="a1b2c3"
+campaign_length=0
+campaign_stats={
+	{ id=0 comparison=1 key="game_country" selector="ENG" localization="England" }
+}
+is_random_new_world=no
+"#;
+
+    #[test]
+    fn test_meta_prefix_text() {
+        let mut data = META.to_vec();
+        data.extend_from_slice(b"players_countries={ }\ncurrent_age=\"age_of_discovery\"\nprovinces={ this is not valid ");
+        data.extend_from_slice(b"}\nchecksum=\"abc123\"\n");
+        let checksum = text_checksum(&data[data.len().saturating_sub(CHECKSUM_TAIL_LEN)..]);
+        assert_eq!(checksum.as_deref(), Some("abc123"));
+
+        let mut modeller = Eu4Modeller::from_reader(data.as_slice(), SegmentedResolver::empty())
+            .with_encoding(Encoding::Text);
+        let meta = MetaSeed { checksum }.deserialize(&mut modeller).unwrap();
+        assert_eq!(meta.player, "ENG");
+        assert_eq!(meta.date.iso_8601().to_string(), "1444-11-11");
+        assert_eq!(meta.savegame_version.second, 35);
+        assert_eq!(meta.dlc_enabled, vec![String::from("Art of War")]);
+        assert_eq!(meta.campaign_id, "a1b2c3");
+        assert!(!meta.is_random_new_world);
+        assert_eq!(meta.checksum, "abc123");
+    }
+
+    #[test]
+    fn test_meta_prefix_own_checksum() {
+        let mut data = META.to_vec();
+        data.extend_from_slice(b"checksum=\"def456\"\n");
+        let mut modeller = Eu4Modeller::from_reader(data.as_slice(), SegmentedResolver::empty())
+            .with_encoding(Encoding::Text);
+        let seed = MetaSeed {
+            checksum: Some(String::from("abc123")),
+        };
+        let meta = seed.deserialize(&mut modeller).unwrap();
+        assert_eq!(meta.checksum, "def456");
+    }
+
+    #[test]
+    fn test_meta_prefix_unknown_key() {
+        let data = br#"date=1444.11.11
+save_game="autosave.eu4"
+player="ENG"
+displayed_country_name="England"
+savegame_version={ first=1 second=37 third=0 forth=0 name="Inca" }
+not_observer=yes
+campaign_id="a1b2c3"
+campaign_length=0
+new_meta_field={ a b }
+is_random_new_world=yes
+speed=2
+provinces={ this is not valid
+"#;
+        let mut modeller = Eu4Modeller::from_reader(&data[..], SegmentedResolver::empty())
+            .with_encoding(Encoding::Text);
+        let seed = MetaSeed {
+            checksum: Some(String::from("abc123")),
+        };
+        let meta = seed.deserialize(&mut modeller).unwrap();
+        assert!(meta.is_random_new_world);
+        assert_eq!(meta.checksum, "abc123");
+    }
+
+    #[test]
+    fn test_binary_checksum() {
+        let mut data = vec![0x00, 0x00, 0x01, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x00];
+        data.extend_from_slice(&[0x79, 0x01, 0x01, 0x00, 0x0f, 0x00, 0x03, 0x00]);
+        data.extend_from_slice(b"abc");
+        let mut resolver = std::collections::HashMap::new();
+        resolver.insert(0x0179u16, String::from("checksum"));
+        assert_eq!(binary_checksum(&data, &resolver).as_deref(), Some("abc"));
+        assert_eq!(binary_checksum(&data[..data.len() - 1], &resolver), None);
+    }
+}
@@ -4,6 +4,8 @@ use jomini::JominiDeserialize;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+pub mod borrowed;
+
 #[derive(Debug, Clone, JominiDeserialize, Serialize)]
 #[cfg_attr(feature = "tsify", derive(tsify::Tsify), tsify(into_wasm_abi))]
 pub struct Meta {
@@ -25,6 +27,11 @@ pub struct Meta {
     pub mod_enabled: Vec<String>,
     #[jomini(default)]
     pub mods_enabled_names: Vec<ModName>,
+    /// The checksum of the game data (and enabled mods) the save was
+    /// written with, as shown in the game's main menu. It is the same for
+    /// every save from an install and is not derived from the save's
+    /// contents, so it can't be recomputed from a save nor used to detect
+    /// that a save was edited.
     #[jomini(take_last)]
     pub checksum: String,
     pub savegame_version: SavegameVersion,
@@ -350,20 +357,22 @@ pub struct HRE {
     pub electors: Vec<CountryTag>,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
 #[cfg_attr(feature = "tsify", derive(tsify::Tsify))]
 pub enum TaxManpowerModifier {
+    #[default]
     Historical,
     Random,
     Equal,
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
 #[cfg_attr(feature = "tsify", derive(tsify::Tsify))]
 #[cfg_attr(feature = "specta", derive(specta::Type))]
 pub enum GameDifficulty {
     VeryEasy,
     Easy,
+    #[default]
     Normal,
     Hard,
     VeryHard,
@@ -599,7 +608,7 @@ pub struct Country {
     pub religious_unity: f32,
     #[jomini(default)]
     pub church: Option<CountryChurch>,
-    #[jomini(default)]
+    #[jomini(default, deserialize_with = "deserialize_lenient")]
     pub national_focus: NationalFocus,
     pub recalculate_strategy: bool,
     pub colors: CountryColors,
@@ -873,10 +882,11 @@ pub struct Monarch {
     pub dynasty: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "tsify", derive(tsify::Tsify))]
 pub enum LeaderKind {
     Admiral,
+    #[default]
     General,
     Explorer,
     Conquistador,
@@ -886,7 +896,7 @@ pub enum LeaderKind {
 #[cfg_attr(feature = "tsify", derive(tsify::Tsify))]
 pub struct Leader {
     pub name: String,
-    #[serde(alias = "type")]
+    #[serde(alias = "type", deserialize_with = "deserialize_lenient")]
     pub kind: LeaderKind,
     #[serde(default)]
     pub maneuver: u16,
@@ -0,0 +1,1025 @@
+//! Models that borrow their strings from the save data
+//!
+//! These mirror [`GameState`](super::GameState), [`Country`](super::Country),
+//! and [`Province`](super::Province) field for field, except that the
+//! identifiers repeated across countries and provinces are borrowed from the
+//! input instead of being allocated. These are names, cultures, religions,
+//! trade goods, trade nodes, buildings, modifiers, missions, idea groups, and
+//! flags. Strings that need to be decoded from windows-1252 (ie: names with
+//! accented characters) and the nested structures like histories and armies
+//! are owned.
+//!
+//! Use [`Eu4SliceFile::parse_save_borrowed`] to parse a save into these models
+//! and the `From` implementations to convert them into the owned models so
+//! that they can be queried.
+//!
+//! [`Eu4SliceFile::parse_save_borrowed`]: crate::file::Eu4SliceFile::parse_save_borrowed
+use super::*;
+use crate::models;
+use jomini::JominiDeserialize;
+use serde::{Deserialize, Deserializer};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[cfg(feature = "serialize")]
+use serde::Serialize;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct Eu4Save<'a> {
+    #[cfg_attr(feature = "serialize", serde(flatten))]
+    pub meta: Meta,
+
+    #[cfg_attr(feature = "serialize", serde(flatten))]
+    pub game: GameState<'a>,
+}
+
+impl From<Eu4Save<'_>> for models::Eu4Save {
+    fn from(value: Eu4Save<'_>) -> Self {
+        models::Eu4Save {
+            meta: value.meta,
+            game: models::GameState::from(value.game),
+        }
+    }
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct GameState<'a> {
+    #[jomini(default)]
+    pub players_countries: Vec<String>,
+    pub current_age: String,
+    pub start_date: Eu4Date,
+    pub map_area_data: HashMap<String, MapAreaDatum>,
+    pub military_hegemon: Option<Hegemon>,
+    pub naval_hegemon: Option<Hegemon>,
+    pub economic_hegemon: Option<Hegemon>,
+    pub trade: TradeNodes,
+    #[jomini(duplicated, alias = "rebel_faction")]
+    pub rebel_factions: Vec<RebelFaction>,
+    #[jomini(default, borrow, deserialize_with = "deserialize_cow_vec_pair")]
+    pub religions: Vec<(Cow<'a, str>, ReligionGameState)>,
+    pub religion_instance_data: HashMap<String, ReligionInstanceDatum>,
+    pub empire: Option<HRE>,
+    #[jomini(
+        default,
+        borrow,
+        deserialize_with = "deserialize_vec_pair_with_capacity::<_, _, _, 1400>"
+    )]
+    pub countries: Vec<(CountryTag, Country<'a>)>,
+    #[jomini(
+        borrow,
+        deserialize_with = "deserialize_map_with_capacity::<_, _, _, 5000>"
+    )]
+    pub provinces: HashMap<ProvinceId, Province<'a>>,
+    pub income_statistics: LedgerData,
+    pub nation_size_statistics: LedgerData,
+    pub score_statistics: LedgerData,
+    pub inflation_statistics: LedgerData,
+    #[jomini(duplicated, alias = "active_war")]
+    pub active_wars: Vec<ActiveWar>,
+    #[jomini(duplicated, alias = "previous_war")]
+    pub previous_wars: Vec<PreviousWar>,
+    #[jomini(default)]
+    pub achievement_ok: bool,
+    #[jomini(default)]
+    pub achievement: Vec<i32>,
+    #[jomini(default)]
+    pub completed_achievements: Vec<i32>,
+    #[jomini(alias = "gameplaysettings")]
+    pub gameplay_settings: GameplaySettings,
+    pub diplomacy: Diplomacy,
+    #[jomini(default)]
+    pub institutions: Vec<i32>,
+    pub random_world: Option<i32>,
+}
+
+impl From<GameState<'_>> for models::GameState {
+    fn from(value: GameState<'_>) -> Self {
+        models::GameState {
+            players_countries: value.players_countries,
+            current_age: value.current_age,
+            start_date: value.start_date,
+            map_area_data: value.map_area_data,
+            military_hegemon: value.military_hegemon,
+            naval_hegemon: value.naval_hegemon,
+            economic_hegemon: value.economic_hegemon,
+            trade: value.trade,
+            rebel_factions: value.rebel_factions,
+            religions: value
+                .religions
+                .into_iter()
+                .map(|(key, value)| (key.into_owned(), value))
+                .collect(),
+            religion_instance_data: value.religion_instance_data,
+            empire: value.empire,
+            countries: value
+                .countries
+                .into_iter()
+                .map(|(tag, country)| (tag, models::Country::from(country)))
+                .collect(),
+            provinces: value
+                .provinces
+                .into_iter()
+                .map(|(id, province)| (id, models::Province::from(province)))
+                .collect(),
+            income_statistics: value.income_statistics,
+            nation_size_statistics: value.nation_size_statistics,
+            score_statistics: value.score_statistics,
+            inflation_statistics: value.inflation_statistics,
+            active_wars: value.active_wars,
+            previous_wars: value.previous_wars,
+            achievement_ok: value.achievement_ok,
+            achievement: value.achievement,
+            completed_achievements: value.completed_achievements,
+            gameplay_settings: value.gameplay_settings,
+            diplomacy: value.diplomacy,
+            institutions: value.institutions,
+            random_world: value.random_world,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct Country<'a> {
+    pub human: bool,
+    pub was_player: bool,
+    pub has_switched_nation: bool,
+    pub is_great_power: bool,
+    pub luck: bool,
+    pub history: CountryHistory,
+    pub previous_country_tags: Vec<CountryTag>,
+    pub name: Option<Cow<'a, str>>,
+    pub government_rank: i32,
+    pub continent: Vec<i32>,
+    pub institutions: Vec<i32>,
+    pub capital: ProvinceId,
+    pub original_capital: Option<ProvinceId>,
+    pub trade_port: ProvinceId,
+    pub golden_era_date: Option<Eu4Date>,
+    pub base_tax: f32,
+    pub development: f32,
+    pub prestige: f32,
+    pub stability: f32,
+    pub treasury: f32,
+    pub inflation: f32,
+    pub corruption: f32,
+    pub raw_development: f32,
+    pub capped_development: f32,
+    pub realm_development: f32,
+    pub isolationism: i32,
+    pub manpower: f32,
+    pub max_manpower: f32,
+    pub sailors: f32,
+    pub max_sailors: f32,
+    pub overextension: f32,
+    pub innovativeness: f32,
+    pub religious_unity: f32,
+    pub church: Option<CountryChurch>,
+    pub national_focus: NationalFocus,
+    pub recalculate_strategy: bool,
+    pub colors: CountryColors,
+    pub dirty_colony: bool,
+    pub primary_culture: Option<Cow<'a, str>>,
+    pub dominant_culture: Option<Cow<'a, str>>,
+    pub accepted_cultures: Vec<Cow<'a, str>>,
+    pub blessings: Vec<Cow<'a, str>>,
+    pub religion: Option<Cow<'a, str>>,
+    pub dominant_religion: Option<Cow<'a, str>>,
+    pub technology_group: Option<Cow<'a, str>>,
+    pub unit_type: Option<Cow<'a, str>>,
+    pub tribute_type: Option<i32>,
+    pub technology: CountryTechnology,
+    pub colonial_parent: Option<CountryTag>,
+    pub ledger: CountryLedger,
+    pub loans: Vec<Loan>,
+    pub estates: Vec<Estate>,
+    pub subjects: Vec<CountryTag>,
+    pub flags: Vec<(Cow<'a, str>, Eu4Date)>,
+    pub highest_possible_fort: Option<i32>,
+    pub transfer_home_bonus: f32,
+    pub enemies: Vec<Cow<'a, str>>,
+    pub current_power_projection: f32,
+    pub great_power_score: f32,
+    pub total_war_worth: u32,
+    pub war_exhaustion: f32,
+    pub land_maintenance: f32,
+    pub naval_maintenance: f32,
+    pub colonial_maintenance: f32,
+    pub missionary_maintenance: f32,
+    pub army_tradition: f32,
+    pub navy_tradition: f32,
+    pub army_professionalism: f32,
+    pub armies: Vec<Army>,
+    pub navies: Vec<Navy>,
+    pub custom_nation_points: Option<f32>,
+    pub num_of_cities: i32,
+    pub num_of_total_ports: i32,
+    pub completed_missions: Vec<Cow<'a, str>>,
+    pub active_idea_groups: Vec<(Cow<'a, str>, u8)>,
+    pub adm_spent_indexed: Vec<(i32, i32)>,
+    pub dip_spent_indexed: Vec<(i32, i32)>,
+    pub mil_spent_indexed: Vec<(i32, i32)>,
+    pub losses: WarParticipantLosses,
+    pub decision_seed: i32,
+    pub mercenary_companies: Vec<MercenaryCompany>,
+    pub active_policies: Vec<CountryPolicy>,
+    pub monarch: Option<ObjId>,
+    pub heir: Option<ObjId>,
+    pub leaders: Vec<ObjId>,
+    pub previous_monarchs: Vec<ObjId>,
+    pub government: Option<CountryGovernment>,
+    pub powers: [i32; 3],
+    pub mercantilism: f32,
+    pub republican_tradition: f32,
+    pub devotion: f32,
+    pub meritocracy: f32,
+    pub legitimacy: f32,
+    pub absolutism: f32,
+    pub horde_unity: f32,
+    pub splendor: f32,
+    pub merchants: EnvoyGroup,
+    pub colonists: EnvoyGroup,
+    pub diplomats: EnvoyGroup,
+    pub missionaries: EnvoyGroup,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Country<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The derive can't apply `deserialize_with` to the fields of a struct
+        // with a lifetime unless they borrow too, and repeated borrowed
+        // strings need a wrapper, so those fields are unwrapped below
+        #[derive(JominiDeserialize)]
+        struct CountryRaw<'a> {
+            #[jomini(default)]
+            human: TokenBool,
+            #[jomini(default)]
+            was_player: bool,
+            #[jomini(default)]
+            has_switched_nation: bool,
+            #[jomini(default)]
+            is_great_power: bool,
+            #[jomini(default)]
+            luck: TokenBool,
+            #[jomini(default)]
+            history: CountryHistory,
+            #[jomini(duplicated)]
+            previous_country_tags: Vec<CountryTag>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            name: Option<Cow<'a, str>>,
+            government_rank: i32,
+            continent: Vec<i32>,
+            institutions: Vec<i32>,
+            capital: ProvinceId,
+            original_capital: Option<ProvinceId>,
+            trade_port: ProvinceId,
+            golden_era_date: Option<Eu4Date>,
+            #[jomini(default)]
+            base_tax: f32,
+            #[jomini(default)]
+            development: f32,
+            #[jomini(default)]
+            prestige: f32,
+            #[jomini(default)]
+            stability: f32,
+            #[jomini(default)]
+            treasury: f32,
+            #[jomini(default)]
+            inflation: f32,
+            #[jomini(default)]
+            corruption: f32,
+            #[jomini(default)]
+            raw_development: f32,
+            capped_development: f32,
+            realm_development: f32,
+            isolationism: i32,
+            #[jomini(default)]
+            manpower: f32,
+            #[jomini(default)]
+            max_manpower: f32,
+            #[jomini(default)]
+            sailors: f32,
+            #[jomini(default)]
+            max_sailors: f32,
+            #[jomini(default, alias = "overextension_percentage")]
+            overextension: f32,
+            #[jomini(default)]
+            innovativeness: f32,
+            #[jomini(default)]
+            religious_unity: f32,
+            #[jomini(default)]
+            church: Option<CountryChurch>,
+            #[jomini(default)]
+            national_focus: Lenient<NationalFocus>,
+            recalculate_strategy: bool,
+            colors: CountryColors,
+            dirty_colony: bool,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            primary_culture: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            dominant_culture: Option<Cow<'a, str>>,
+            #[jomini(borrow, duplicated, alias = "accepted_culture")]
+            accepted_cultures: Vec<CowStr<'a>>,
+            #[jomini(borrow, duplicated, alias = "blessing")]
+            blessings: Vec<CowStr<'a>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            religion: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            dominant_religion: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            technology_group: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            unit_type: Option<Cow<'a, str>>,
+            tribute_type: Option<i32>,
+            technology: CountryTechnology,
+            colonial_parent: Option<CountryTag>,
+            ledger: CountryLedger,
+            #[jomini(duplicated, alias = "loan")]
+            loans: Vec<Loan>,
+            #[jomini(duplicated, alias = "estate")]
+            estates: Vec<Estate>,
+            #[jomini(default)]
+            subjects: Vec<CountryTag>,
+            #[jomini(default, borrow, deserialize_with = "deserialize_cow_vec_pair")]
+            flags: Vec<(Cow<'a, str>, Eu4Date)>,
+            highest_possible_fort: Option<i32>,
+            transfer_home_bonus: f32,
+            #[jomini(borrow, duplicated, alias = "enemy")]
+            enemies: Vec<CowStr<'a>>,
+            #[jomini(default)]
+            current_power_projection: f32,
+            #[jomini(default)]
+            great_power_score: f32,
+            #[jomini(default)]
+            total_war_worth: u32,
+            #[jomini(default)]
+            war_exhaustion: f32,
+            #[jomini(default)]
+            land_maintenance: f32,
+            #[jomini(default)]
+            naval_maintenance: f32,
+            #[jomini(default)]
+            colonial_maintenance: f32,
+            #[jomini(default)]
+            missionary_maintenance: f32,
+            #[jomini(default)]
+            army_tradition: f32,
+            #[jomini(default)]
+            navy_tradition: f32,
+            #[jomini(default)]
+            army_professionalism: f32,
+            #[jomini(duplicated, alias = "army")]
+            armies: Vec<Army>,
+            #[jomini(duplicated, alias = "navy")]
+            navies: Vec<Navy>,
+            custom_nation_points: Option<f32>,
+            #[jomini(default)]
+            num_of_cities: i32,
+            #[jomini(default)]
+            num_of_total_ports: i32,
+            #[jomini(default, borrow, deserialize_with = "deserialize_cow_vec")]
+            completed_missions: Vec<Cow<'a, str>>,
+            #[jomini(default, borrow, deserialize_with = "deserialize_cow_vec_pair")]
+            active_idea_groups: Vec<(Cow<'a, str>, u8)>,
+            #[jomini(default)]
+            adm_spent_indexed: VecPair<i32, i32>,
+            #[jomini(default)]
+            dip_spent_indexed: VecPair<i32, i32>,
+            #[jomini(default)]
+            mil_spent_indexed: VecPair<i32, i32>,
+            #[jomini(default)]
+            losses: WarParticipantLosses,
+            #[jomini(default)]
+            decision_seed: i32,
+            #[jomini(duplicated, alias = "mercenary_company")]
+            mercenary_companies: Vec<MercenaryCompany>,
+            #[jomini(duplicated, alias = "active_policy")]
+            active_policies: Vec<CountryPolicy>,
+            monarch: Option<ObjId>,
+            heir: Option<ObjId>,
+            #[jomini(duplicated, alias = "leader")]
+            leaders: Vec<ObjId>,
+            #[jomini(duplicated, alias = "previous_monarch")]
+            previous_monarchs: Vec<ObjId>,
+            government: Option<CountryGovernment>,
+            #[jomini(default)]
+            powers: [i32; 3],
+            #[jomini(default)]
+            mercantilism: f32,
+            #[jomini(default)]
+            republican_tradition: f32,
+            #[jomini(default)]
+            devotion: f32,
+            #[jomini(default)]
+            meritocracy: f32,
+            #[jomini(default)]
+            legitimacy: f32,
+            #[jomini(default)]
+            absolutism: f32,
+            #[jomini(default)]
+            horde_unity: f32,
+            #[jomini(default)]
+            splendor: f32,
+            #[jomini(default)]
+            merchants: EnvoyGroup,
+            #[jomini(default)]
+            colonists: EnvoyGroup,
+            #[jomini(default)]
+            diplomats: EnvoyGroup,
+            #[jomini(default)]
+            missionaries: EnvoyGroup,
+        }
+
+        let raw = CountryRaw::deserialize(deserializer)?;
+        Ok(Country {
+            human: raw.human.0,
+            was_player: raw.was_player,
+            has_switched_nation: raw.has_switched_nation,
+            is_great_power: raw.is_great_power,
+            luck: raw.luck.0,
+            history: raw.history,
+            previous_country_tags: raw.previous_country_tags,
+            name: raw.name,
+            government_rank: raw.government_rank,
+            continent: raw.continent,
+            institutions: raw.institutions,
+            capital: raw.capital,
+            original_capital: raw.original_capital,
+            trade_port: raw.trade_port,
+            golden_era_date: raw.golden_era_date,
+            base_tax: raw.base_tax,
+            development: raw.development,
+            prestige: raw.prestige,
+            stability: raw.stability,
+            treasury: raw.treasury,
+            inflation: raw.inflation,
+            corruption: raw.corruption,
+            raw_development: raw.raw_development,
+            capped_development: raw.capped_development,
+            realm_development: raw.realm_development,
+            isolationism: raw.isolationism,
+            manpower: raw.manpower,
+            max_manpower: raw.max_manpower,
+            sailors: raw.sailors,
+            max_sailors: raw.max_sailors,
+            overextension: raw.overextension,
+            innovativeness: raw.innovativeness,
+            religious_unity: raw.religious_unity,
+            church: raw.church,
+            national_focus: raw.national_focus.0,
+            recalculate_strategy: raw.recalculate_strategy,
+            colors: raw.colors,
+            dirty_colony: raw.dirty_colony,
+            primary_culture: raw.primary_culture,
+            dominant_culture: raw.dominant_culture,
+            accepted_cultures: raw.accepted_cultures.into_iter().map(|x| x.0).collect(),
+            blessings: raw.blessings.into_iter().map(|x| x.0).collect(),
+            religion: raw.religion,
+            dominant_religion: raw.dominant_religion,
+            technology_group: raw.technology_group,
+            unit_type: raw.unit_type,
+            tribute_type: raw.tribute_type,
+            technology: raw.technology,
+            colonial_parent: raw.colonial_parent,
+            ledger: raw.ledger,
+            loans: raw.loans,
+            estates: raw.estates,
+            subjects: raw.subjects,
+            flags: raw.flags,
+            highest_possible_fort: raw.highest_possible_fort,
+            transfer_home_bonus: raw.transfer_home_bonus,
+            enemies: raw.enemies.into_iter().map(|x| x.0).collect(),
+            current_power_projection: raw.current_power_projection,
+            great_power_score: raw.great_power_score,
+            total_war_worth: raw.total_war_worth,
+            war_exhaustion: raw.war_exhaustion,
+            land_maintenance: raw.land_maintenance,
+            naval_maintenance: raw.naval_maintenance,
+            colonial_maintenance: raw.colonial_maintenance,
+            missionary_maintenance: raw.missionary_maintenance,
+            army_tradition: raw.army_tradition,
+            navy_tradition: raw.navy_tradition,
+            army_professionalism: raw.army_professionalism,
+            armies: raw.armies,
+            navies: raw.navies,
+            custom_nation_points: raw.custom_nation_points,
+            num_of_cities: raw.num_of_cities,
+            num_of_total_ports: raw.num_of_total_ports,
+            completed_missions: raw.completed_missions,
+            active_idea_groups: raw.active_idea_groups,
+            adm_spent_indexed: raw.adm_spent_indexed.0,
+            dip_spent_indexed: raw.dip_spent_indexed.0,
+            mil_spent_indexed: raw.mil_spent_indexed.0,
+            losses: raw.losses,
+            decision_seed: raw.decision_seed,
+            mercenary_companies: raw.mercenary_companies,
+            active_policies: raw.active_policies,
+            monarch: raw.monarch,
+            heir: raw.heir,
+            leaders: raw.leaders,
+            previous_monarchs: raw.previous_monarchs,
+            government: raw.government,
+            powers: raw.powers,
+            mercantilism: raw.mercantilism,
+            republican_tradition: raw.republican_tradition,
+            devotion: raw.devotion,
+            meritocracy: raw.meritocracy,
+            legitimacy: raw.legitimacy,
+            absolutism: raw.absolutism,
+            horde_unity: raw.horde_unity,
+            splendor: raw.splendor,
+            merchants: raw.merchants,
+            colonists: raw.colonists,
+            diplomats: raw.diplomats,
+            missionaries: raw.missionaries,
+        })
+    }
+}
+
+impl From<Country<'_>> for models::Country {
+    fn from(value: Country<'_>) -> Self {
+        models::Country {
+            human: value.human,
+            was_player: value.was_player,
+            has_switched_nation: value.has_switched_nation,
+            is_great_power: value.is_great_power,
+            luck: value.luck,
+            history: value.history,
+            previous_country_tags: value.previous_country_tags,
+            name: value.name.map(Cow::into_owned),
+            government_rank: value.government_rank,
+            continent: value.continent,
+            institutions: value.institutions,
+            capital: value.capital,
+            original_capital: value.original_capital,
+            trade_port: value.trade_port,
+            golden_era_date: value.golden_era_date,
+            base_tax: value.base_tax,
+            development: value.development,
+            prestige: value.prestige,
+            stability: value.stability,
+            treasury: value.treasury,
+            inflation: value.inflation,
+            corruption: value.corruption,
+            raw_development: value.raw_development,
+            capped_development: value.capped_development,
+            realm_development: value.realm_development,
+            isolationism: value.isolationism,
+            manpower: value.manpower,
+            max_manpower: value.max_manpower,
+            sailors: value.sailors,
+            max_sailors: value.max_sailors,
+            overextension: value.overextension,
+            innovativeness: value.innovativeness,
+            religious_unity: value.religious_unity,
+            church: value.church,
+            national_focus: value.national_focus,
+            recalculate_strategy: value.recalculate_strategy,
+            colors: value.colors,
+            dirty_colony: value.dirty_colony,
+            primary_culture: value.primary_culture.map(Cow::into_owned),
+            dominant_culture: value.dominant_culture.map(Cow::into_owned),
+            accepted_cultures: value
+                .accepted_cultures
+                .into_iter()
+                .map(Cow::into_owned)
+                .collect(),
+            blessings: value.blessings.into_iter().map(Cow::into_owned).collect(),
+            religion: value.religion.map(Cow::into_owned),
+            dominant_religion: value.dominant_religion.map(Cow::into_owned),
+            technology_group: value.technology_group.map(Cow::into_owned),
+            unit_type: value.unit_type.map(Cow::into_owned),
+            tribute_type: value.tribute_type,
+            technology: value.technology,
+            colonial_parent: value.colonial_parent,
+            ledger: value.ledger,
+            loans: value.loans,
+            estates: value.estates,
+            subjects: value.subjects,
+            flags: value
+                .flags
+                .into_iter()
+                .map(|(key, value)| (key.into_owned(), value))
+                .collect(),
+            highest_possible_fort: value.highest_possible_fort,
+            transfer_home_bonus: value.transfer_home_bonus,
+            enemies: value.enemies.into_iter().map(Cow::into_owned).collect(),
+            current_power_projection: value.current_power_projection,
+            great_power_score: value.great_power_score,
+            total_war_worth: value.total_war_worth,
+            war_exhaustion: value.war_exhaustion,
+            land_maintenance: value.land_maintenance,
+            naval_maintenance: value.naval_maintenance,
+            colonial_maintenance: value.colonial_maintenance,
+            missionary_maintenance: value.missionary_maintenance,
+            army_tradition: value.army_tradition,
+            navy_tradition: value.navy_tradition,
+            army_professionalism: value.army_professionalism,
+            armies: value.armies,
+            navies: value.navies,
+            custom_nation_points: value.custom_nation_points,
+            num_of_cities: value.num_of_cities,
+            num_of_total_ports: value.num_of_total_ports,
+            completed_missions: value
+                .completed_missions
+                .into_iter()
+                .map(Cow::into_owned)
+                .collect(),
+            active_idea_groups: value
+                .active_idea_groups
+                .into_iter()
+                .map(|(key, value)| (key.into_owned(), value))
+                .collect(),
+            adm_spent_indexed: value.adm_spent_indexed,
+            dip_spent_indexed: value.dip_spent_indexed,
+            mil_spent_indexed: value.mil_spent_indexed,
+            losses: value.losses,
+            decision_seed: value.decision_seed,
+            mercenary_companies: value.mercenary_companies,
+            active_policies: value.active_policies,
+            monarch: value.monarch,
+            heir: value.heir,
+            leaders: value.leaders,
+            previous_monarchs: value.previous_monarchs,
+            government: value.government,
+            powers: value.powers,
+            mercantilism: value.mercantilism,
+            republican_tradition: value.republican_tradition,
+            devotion: value.devotion,
+            meritocracy: value.meritocracy,
+            legitimacy: value.legitimacy,
+            absolutism: value.absolutism,
+            horde_unity: value.horde_unity,
+            splendor: value.splendor,
+            merchants: value.merchants,
+            colonists: value.colonists,
+            diplomats: value.diplomats,
+            missionaries: value.missionaries,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct Province<'a> {
+    pub flags: Vec<(Cow<'a, str>, Eu4Date)>,
+    pub name: Cow<'a, str>,
+    pub owner: Option<CountryTag>,
+    pub controller: Option<CountryTag>,
+    pub previous_controller: Option<CountryTag>,
+    pub occupying_rebel_faction: Option<ObjId>,
+    pub cores: Vec<CountryTag>,
+    pub territorial_core: Vec<CountryTag>,
+    pub claims: Vec<CountryTag>,
+    pub institutions: Vec<f32>,
+    pub exploit_date: Option<Eu4Date>,
+    pub trade: Option<Cow<'a, str>>,
+    pub original_culture: Option<Cow<'a, str>>,
+    pub culture: Option<Cow<'a, str>>,
+    pub religion: Option<Cow<'a, str>>,
+    pub original_religion: Option<Cow<'a, str>>,
+    pub trade_goods: Option<Cow<'a, str>>,
+    pub country_improve_count: HashMap<CountryTag, i32>,
+    pub latent_trade_goods: Vec<Cow<'a, str>>,
+    pub devastation: f32,
+    pub base_tax: f32,
+    pub base_production: f32,
+    pub base_manpower: f32,
+    pub capital: Option<Cow<'a, str>>,
+    pub local_autonomy: f32,
+    pub is_city: bool,
+    pub active_trade_company: bool,
+    pub center_of_trade: u8,
+    pub trade_power: f32,
+    pub hre: bool,
+    pub buildings: HashMap<Cow<'a, str>, bool>,
+    pub building_builders: HashMap<String, CountryTag>,
+    pub modifiers: Vec<Modifier<'a>>,
+    pub history: ProvinceHistory,
+    pub ub: bool,
+    pub colony_size: Option<f32>,
+    pub change_culture_construction: Option<ChangeCultureConstruction>,
+    pub centralize_state_construction: Option<CentralizeStateConstruction>,
+    pub num_centralize_state: i32,
+    pub expand_infrastructure: i32,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Province<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The derive can't apply `deserialize_with` to the fields of a struct
+        // with a lifetime unless they borrow too, so the token bools and
+        // improvement counts are read through wrappers and unwrapped below
+        #[derive(JominiDeserialize)]
+        struct ProvinceRaw<'a> {
+            #[jomini(default, borrow, deserialize_with = "deserialize_cow_vec_pair")]
+            flags: Vec<(Cow<'a, str>, Eu4Date)>,
+            #[jomini(borrow, deserialize_with = "deserialize_cow_str")]
+            name: Cow<'a, str>,
+            owner: Option<CountryTag>,
+            controller: Option<CountryTag>,
+            previous_controller: Option<CountryTag>,
+            occupying_rebel_faction: Option<ObjId>,
+            #[jomini(default)]
+            cores: Vec<CountryTag>,
+            #[jomini(duplicated)]
+            territorial_core: Vec<CountryTag>,
+            #[jomini(default)]
+            claims: Vec<CountryTag>,
+            institutions: Vec<f32>,
+            exploit_date: Option<Eu4Date>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            trade: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            original_culture: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            culture: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            religion: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            original_religion: Option<Cow<'a, str>>,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            trade_goods: Option<Cow<'a, str>>,
+            #[jomini(default)]
+            country_improve_count: AlternatingKeyValues<CountryTag, i32>,
+            #[jomini(default, borrow, deserialize_with = "deserialize_cow_vec")]
+            latent_trade_goods: Vec<Cow<'a, str>>,
+            #[jomini(default)]
+            devastation: f32,
+            #[jomini(default)]
+            base_tax: f32,
+            #[jomini(default)]
+            base_production: f32,
+            #[jomini(default)]
+            base_manpower: f32,
+            #[jomini(borrow, deserialize_with = "deserialize_option_cow_str")]
+            capital: Option<Cow<'a, str>>,
+            #[jomini(default)]
+            local_autonomy: f32,
+            #[jomini(default)]
+            is_city: bool,
+            #[jomini(default)]
+            active_trade_company: TokenBool,
+            #[jomini(default)]
+            center_of_trade: u8,
+            #[jomini(default)]
+            trade_power: f32,
+            #[jomini(default)]
+            hre: TokenBool,
+            #[jomini(default, borrow, deserialize_with = "deserialize_cow_yes_map")]
+            buildings: HashMap<Cow<'a, str>, bool>,
+            #[jomini(default)]
+            building_builders: HashMap<String, CountryTag>,
+            #[jomini(default, borrow, duplicated, alias = "modifier")]
+            modifiers: Vec<Modifier<'a>>,
+            #[jomini(default)]
+            history: ProvinceHistory,
+            #[jomini(default = "default_true")]
+            ub: bool,
+            #[jomini(alias = "colonysize")]
+            colony_size: Option<f32>,
+            change_culture_construction: Option<ChangeCultureConstruction>,
+            centralize_state_construction: Option<CentralizeStateConstruction>,
+            #[jomini(default)]
+            num_centralize_state: i32,
+            #[jomini(default)]
+            expand_infrastructure: i32,
+        }
+
+        let raw = ProvinceRaw::deserialize(deserializer)?;
+        Ok(Province {
+            flags: raw.flags,
+            name: raw.name,
+            owner: raw.owner,
+            controller: raw.controller,
+            previous_controller: raw.previous_controller,
+            occupying_rebel_faction: raw.occupying_rebel_faction,
+            cores: raw.cores,
+            territorial_core: raw.territorial_core,
+            claims: raw.claims,
+            institutions: raw.institutions,
+            exploit_date: raw.exploit_date,
+            trade: raw.trade,
+            original_culture: raw.original_culture,
+            culture: raw.culture,
+            religion: raw.religion,
+            original_religion: raw.original_religion,
+            trade_goods: raw.trade_goods,
+            country_improve_count: raw.country_improve_count.0,
+            latent_trade_goods: raw.latent_trade_goods,
+            devastation: raw.devastation,
+            base_tax: raw.base_tax,
+            base_production: raw.base_production,
+            base_manpower: raw.base_manpower,
+            capital: raw.capital,
+            local_autonomy: raw.local_autonomy,
+            is_city: raw.is_city,
+            active_trade_company: raw.active_trade_company.0,
+            center_of_trade: raw.center_of_trade,
+            trade_power: raw.trade_power,
+            hre: raw.hre.0,
+            buildings: raw.buildings,
+            building_builders: raw.building_builders,
+            modifiers: raw.modifiers,
+            history: raw.history,
+            ub: raw.ub,
+            colony_size: raw.colony_size,
+            change_culture_construction: raw.change_culture_construction,
+            centralize_state_construction: raw.centralize_state_construction,
+            num_centralize_state: raw.num_centralize_state,
+            expand_infrastructure: raw.expand_infrastructure,
+        })
+    }
+}
+
+impl From<Province<'_>> for models::Province {
+    fn from(value: Province<'_>) -> Self {
+        models::Province {
+            flags: value
+                .flags
+                .into_iter()
+                .map(|(key, value)| (key.into_owned(), value))
+                .collect(),
+            name: value.name.into_owned(),
+            owner: value.owner,
+            controller: value.controller,
+            previous_controller: value.previous_controller,
+            occupying_rebel_faction: value.occupying_rebel_faction,
+            cores: value.cores,
+            territorial_core: value.territorial_core,
+            claims: value.claims,
+            institutions: value.institutions,
+            exploit_date: value.exploit_date,
+            trade: value.trade.map(Cow::into_owned),
+            original_culture: value.original_culture.map(Cow::into_owned),
+            culture: value.culture.map(Cow::into_owned),
+            religion: value.religion.map(Cow::into_owned),
+            original_religion: value.original_religion.map(Cow::into_owned),
+            trade_goods: value.trade_goods.map(Cow::into_owned),
+            country_improve_count: value.country_improve_count,
+            latent_trade_goods: value
+                .latent_trade_goods
+                .into_iter()
+                .map(Cow::into_owned)
+                .collect(),
+            devastation: value.devastation,
+            base_tax: value.base_tax,
+            base_production: value.base_production,
+            base_manpower: value.base_manpower,
+            capital: value.capital.map(Cow::into_owned),
+            local_autonomy: value.local_autonomy,
+            is_city: value.is_city,
+            active_trade_company: value.active_trade_company,
+            center_of_trade: value.center_of_trade,
+            trade_power: value.trade_power,
+            hre: value.hre,
+            buildings: value
+                .buildings
+                .into_iter()
+                .map(|(key, value)| (key.into_owned(), value))
+                .collect(),
+            building_builders: value.building_builders,
+            modifiers: value
+                .modifiers
+                .into_iter()
+                .map(models::Modifier::from)
+                .collect(),
+            history: value.history,
+            ub: value.ub,
+            colony_size: value.colony_size,
+            change_culture_construction: value.change_culture_construction,
+            centralize_state_construction: value.centralize_state_construction,
+            num_centralize_state: value.num_centralize_state,
+            expand_infrastructure: value.expand_infrastructure,
+        }
+    }
+}
+
+#[derive(Debug, Clone, JominiDeserialize)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct Modifier<'a> {
+    #[jomini(borrow, deserialize_with = "deserialize_cow_str")]
+    pub modifier: Cow<'a, str>,
+    pub date: Eu4Date,
+}
+
+impl From<Modifier<'_>> for models::Modifier {
+    fn from(value: Modifier<'_>) -> Self {
+        models::Modifier {
+            modifier: value.modifier.into_owned(),
+            date: value.date,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Eu4File, SegmentedResolver};
+
+    /// Sorted lines of the pretty debug output, so that values can be
+    /// compared regardless of the iteration order of their hash maps
+    fn debug_lines<T: std::fmt::Debug>(value: &T) -> Vec<String> {
+        let mut lines = format!("{value:#?}")
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines
+    }
+
+    #[test]
+    fn test_borrowed_matches_owned() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"SWE\"\n\
+displayed_country_name=\"Sweden\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\nchecksum=\"abc123\"\n\
+current_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\ntrade={ }\n\
+religion_instance_data={ }\nreligions={ catholic={ } protestant={ } }\n\
+countries={ SWE={ name=\"Sweden\" government_rank=2 continent={ 1 } institutions={ 1 } capital=1 \
+trade_port=1 capped_development=10 realm_development=10 isolationism=1 recalculate_strategy=yes \
+colors={ } dirty_colony=no technology={ adm_tech=3 dip_tech=3 mil_tech=3 } ledger={ } \
+transfer_home_bonus=0 human=yes primary_culture=swedish accepted_culture=finnish \
+accepted_culture=danish religion=catholic flags={ my_flag=1444.11.11 other_flag=1445.1.1 } \
+completed_missions={ swe_mission } active_idea_groups={ innovativeness_ideas=7 } \
+adm_spent_indexed={ 1=10 } } }\n\
+provinces={ -1={ name=\"\xD6sterg\xF6tland\" institutions={ 0 } owner=\"SWE\" culture=swedish \
+religion=catholic trade_goods=grain trade=\"baltic_sea\" local_autonomy=25 hre=yes \
+buildings={ temple=yes marketplace=yes } modifier={ modifier=\"skanemarket\" date=1444.11.11 } } \
+-2={ name=\"Uppland\" institutions={ 0 } culture=swedish trade_goods=copper } }\n\
+income_statistics={ }\nnation_size_statistics={ }\nscore_statistics={ }\ninflation_statistics={ }\n\
+gameplaysettings={ setgameplayoptions={ 1 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\ndiplomacy={ }\n";
+
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        let resolver = SegmentedResolver::empty();
+        let expected = file.parse_save(&resolver).unwrap();
+        let mut buf = Vec::new();
+        let save = file.parse_save_borrowed(&resolver, &mut buf).unwrap();
+        let actual = models::Eu4Save::from(save);
+
+        assert_eq!(actual.game.countries.len(), 1);
+        assert_eq!(actual.game.provinces.len(), 2);
+        assert_eq!(debug_lines(&actual), debug_lines(&expected));
+    }
+
+    #[test]
+    fn test_borrowed_country() {
+        let data = b"name=\"Sweden\" government_rank=2 continent={ 1 } institutions={ 1 } capital=1 trade_port=1 capped_development=10 realm_development=10 isolationism=1 recalculate_strategy=yes colors={ } dirty_colony=no technology={ adm_tech=3 dip_tech=3 mil_tech=3 } ledger={ } transfer_home_bonus=0 human=yes primary_culture=swedish accepted_culture=finnish religion=catholic flags={ my_flag=1444.11.11 } completed_missions={ swe_mission } active_idea_groups={ innovativeness_ideas=7 } adm_spent_indexed={ 1=10 }";
+
+        let sweden: Country = jomini::text::de::from_windows1252_slice(data).unwrap();
+        assert!(matches!(
+            sweden.primary_culture,
+            Some(Cow::Borrowed("swedish"))
+        ));
+        assert!(matches!(
+            sweden.accepted_cultures[..],
+            [Cow::Borrowed("finnish")]
+        ));
+        assert!(matches!(sweden.flags[0].0, Cow::Borrowed("my_flag")));
+        assert!(matches!(
+            sweden.completed_missions[0],
+            Cow::Borrowed("swe_mission")
+        ));
+        assert!(sweden.human);
+        assert_eq!(sweden.technology.adm_tech, 3);
+
+        let sweden = models::Country::from(sweden);
+        assert_eq!(sweden.primary_culture.as_deref(), Some("swedish"));
+        assert_eq!(sweden.accepted_cultures, vec![String::from("finnish")]);
+        assert_eq!(sweden.capped_development, 10.0);
+        assert_eq!(
+            sweden.active_idea_groups,
+            vec![(String::from("innovativeness_ideas"), 7)]
+        );
+        assert_eq!(sweden.adm_spent_indexed, vec![(1, 10)]);
+    }
+
+    #[test]
+    fn test_borrowed_province() {
+        let data = b"name=\"\xD6sterg\xF6tland\" institutions={ 0 } owner=\"SWE\" culture=swedish religion=catholic trade_goods=grain trade=\"baltic_sea\" local_autonomy=25 hre=yes buildings={ temple=yes } modifier={ modifier=\"skanemarket\" date=1444.11.11 }";
+
+        let province: Province = jomini::text::de::from_windows1252_slice(data).unwrap();
+        assert!(matches!(province.name, Cow::Owned(_)));
+        assert_eq!(province.name, "\u{d6}sterg\u{f6}tland");
+        assert!(matches!(province.culture, Some(Cow::Borrowed("swedish"))));
+        assert!(matches!(province.trade_goods, Some(Cow::Borrowed("grain"))));
+        assert!(matches!(province.trade, Some(Cow::Borrowed("baltic_sea"))));
+        assert!(matches!(
+            province.modifiers[0].modifier,
+            Cow::Borrowed("skanemarket")
+        ));
+
+        let province = models::Province::from(province);
+        assert_eq!(province.name, "\u{d6}sterg\u{f6}tland");
+        assert_eq!(province.trade.as_deref(), Some("baltic_sea"));
+        assert_eq!(province.local_autonomy, 25.0);
+        assert!(province.hre);
+        assert_eq!(province.buildings.get("temple"), Some(&true));
+        assert_eq!(province.modifiers[0].modifier, "skanemarket");
+    }
+}
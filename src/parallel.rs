@@ -0,0 +1,230 @@
+//! Deserializes the countries and provinces of a gamestate across threads
+//!
+//! The `countries` and `provinces` blocks make up the bulk of a save. Their
+//! entries are located with a token scan, split into chunks that are
+//! deserialized concurrently, and then spliced back into the result of
+//! deserializing the remainder of the document.
+use crate::{
+    file::Eu4Modeller,
+    models::{Country, Eu4Save, GameState, Province},
+    scan::{self, Section, SectionKind},
+    CountryTag, Encoding, Eu4Error, ProvinceId,
+};
+use jomini::binary::TokenResolver;
+use serde::{
+    de::{DeserializeOwned, DeserializeSeed, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+/// The document with the countries and provinces that were decoded separately
+pub(crate) struct ParallelDocument<T> {
+    pub rest: T,
+    pub countries: Option<Vec<(CountryTag, Country)>>,
+    pub provinces: Option<HashMap<ProvinceId, Province>>,
+}
+
+impl<T> ParallelDocument<T> {
+    fn splice_into(self, game: impl FnOnce(&mut T) -> &mut GameState) -> T {
+        let mut rest = self.rest;
+        let state = game(&mut rest);
+        if let Some(countries) = self.countries {
+            state.countries = countries;
+        }
+
+        if let Some(provinces) = self.provinces {
+            state.provinces = provinces;
+        }
+        rest
+    }
+}
+
+impl ParallelDocument<Eu4Save> {
+    pub fn into_save(self) -> Eu4Save {
+        self.splice_into(|save| &mut save.game)
+    }
+}
+
+impl ParallelDocument<GameState> {
+    pub fn into_game(self) -> GameState {
+        self.splice_into(|game| game)
+    }
+}
+
+impl Section {
+    /// Split the section's entries into at most `count` contiguous chunks
+    fn chunks<'a>(&self, data: &'a [u8], count: usize) -> Vec<&'a [u8]> {
+        let per_chunk = self.entries.len().div_ceil(count.max(1)).max(1);
+        self.entries
+            .chunks(per_chunk)
+            .enumerate()
+            .map(|(i, starts)| {
+                let end = self
+                    .entries
+                    .get((i + 1) * per_chunk)
+                    .copied()
+                    .unwrap_or(self.end);
+                &data[starts[0]..end]
+            })
+            .collect()
+    }
+}
+
+/// Deserializes the key value pairs of a chunk of a section
+struct PairsSeed<K, V>(PhantomData<(K, V)>);
+
+impl<'de, K, V> DeserializeSeed<'de> for PairsSeed<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = Vec<(K, V)>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("Pairs", &[], self)
+    }
+}
+
+impl<'de, K, V> Visitor<'de> for PairsSeed<K, V>
+where
+    K: Deserialize<'de>,
+    V: Deserialize<'de>,
+{
+    type Value = Vec<(K, V)>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("key value pairs")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut result = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(entry) = map.next_entry()? {
+            result.push(entry);
+        }
+        Ok(result)
+    }
+}
+
+fn deserialize_chunk<K, V, R>(
+    chunk: &[u8],
+    encoding: Encoding,
+    resolver: &R,
+) -> Result<Vec<(K, V)>, Eu4Error>
+where
+    K: DeserializeOwned,
+    V: DeserializeOwned,
+    R: TokenResolver,
+{
+    let mut modeller = Eu4Modeller::from_reader(chunk, resolver).with_encoding(encoding);
+    PairsSeed(PhantomData).deserialize(&mut modeller)
+}
+
+/// Deserializes headerless text or binary data with the countries and
+/// provinces decoded in parallel
+pub(crate) fn deserialize<T, R>(
+    data: &[u8],
+    encoding: Encoding,
+    resolver: &R,
+) -> Result<ParallelDocument<T>, Eu4Error>
+where
+    T: DeserializeOwned + Send,
+    R: TokenResolver + Sync,
+{
+    let sections = scan::scan(data, encoding, resolver)?;
+
+    // The document without the contents of the sections decoded separately
+    let mut rest = Vec::with_capacity(data.len());
+    let mut position = 0;
+    for section in &sections {
+        rest.extend_from_slice(&data[position..section.start]);
+        position = section.end;
+    }
+    rest.extend_from_slice(&data[position..]);
+
+    let threads = std::thread::available_parallelism().map_or(1, |x| x.get());
+    std::thread::scope(|scope| {
+        let rest_handle = scope.spawn(|| {
+            let rest = rest.as_slice();
+            Eu4Modeller::from_reader(rest, resolver)
+                .with_encoding(encoding)
+                .with_reopen(move || Ok(Box::new(rest)))
+                .deserialize::<T>()
+        });
+
+        let mut country_handles = Vec::new();
+        let mut province_handles = Vec::new();
+        for section in &sections {
+            for chunk in section.chunks(data, threads) {
+                match section.kind {
+                    SectionKind::Countries => country_handles
+                        .push(scope.spawn(move || deserialize_chunk(chunk, encoding, resolver))),
+                    SectionKind::Provinces => province_handles
+                        .push(scope.spawn(move || deserialize_chunk(chunk, encoding, resolver))),
+                }
+            }
+        }
+
+        let has_section = |kind| sections.iter().any(|x| x.kind == kind);
+        let mut countries = has_section(SectionKind::Countries).then(Vec::new);
+        for handle in country_handles {
+            let chunk = handle.join().expect("country thread to not panic")?;
+            countries.get_or_insert_with(Vec::new).extend(chunk);
+        }
+
+        let mut provinces = has_section(SectionKind::Provinces).then(HashMap::new);
+        for handle in province_handles {
+            let chunk = handle.join().expect("province thread to not panic")?;
+            provinces.get_or_insert_with(HashMap::new).extend(chunk);
+        }
+
+        let rest = rest_handle.join().expect("gamestate thread to not panic")?;
+        Ok(ParallelDocument {
+            rest,
+            countries,
+            provinces,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SegmentedResolver;
+
+    #[derive(Debug, Deserialize)]
+    struct Doc {
+        date: String,
+        #[serde(default)]
+        provinces: HashMap<ProvinceId, Province>,
+        after: String,
+    }
+
+    #[test]
+    fn test_parallel_text_sections() {
+        let mut data = String::from("date=1444.11.11\nprovinces={\n");
+        for i in 1..=20 {
+            data.push_str(&format!(
+                "\t-{i}={{ name=\"p{i}\" color=rgb {{ 1 2 3 }} institutions={{ 0 }} }}\n"
+            ));
+        }
+        data.push_str("}\nafter=\"done\"");
+
+        let resolver = SegmentedResolver::empty();
+        let doc: ParallelDocument<Doc> =
+            deserialize(data.as_bytes(), Encoding::Text, &resolver).unwrap();
+        assert_eq!(doc.rest.date, "1444.11.11");
+        assert_eq!(doc.rest.after, "done");
+        assert!(doc.rest.provinces.is_empty());
+        assert!(doc.countries.is_none());
+
+        let provinces = doc.provinces.unwrap();
+        assert_eq!(provinces.len(), 20);
+        assert_eq!(provinces[&ProvinceId::from(17)].name, "p17");
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// Controls how a save is deserialized into models
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    lenient: bool,
+}
+
+impl ParseOptions {
+    /// Strict parsing, where any field that fails to deserialize fails the
+    /// entire save
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lenient parsing, where values that fail to deserialize are replaced
+    /// and reported as a [`Diagnostic`] instead of failing the entire save:
+    ///
+    /// - A scalar of the wrong type for its field or list, like text where a
+    ///   number is expected or an object where a string is expected, becomes
+    ///   the zero value of the type (`0`, `false`, or an empty string)
+    /// - An unrecognized leader type, national focus, game difficulty, or tax
+    ///   and manpower modifier falls back to its default
+    /// - An unrecognized key in a war's history is skipped
+    ///
+    /// Other failures still fail the save, like a missing field, a country
+    /// tag that isn't three letters, or a malformed date.
+    pub fn lenient() -> Self {
+        ParseOptions { lenient: true }
+    }
+
+    pub fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+}
+
+/// A field that failed to deserialize and was replaced with its default
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The path of keys to the field, like `countries.ENG.national_focus`
+    pub path: String,
+
+    /// Why the field failed to deserialize
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
@@ -0,0 +1,225 @@
+use crate::{Eu4Error, Eu4ErrorKind};
+use std::{
+    cell::{Cell, RefCell},
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// Bytes read between progress reports
+const BYTES_INTERVAL: u64 = 1 << 20;
+
+/// Tokens processed between progress reports
+const TOKENS_INTERVAL: u64 = 1 << 16;
+
+/// Snapshot of the work done so far
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Number of bytes read after decompression
+    pub bytes: u64,
+
+    /// Number of binary tokens processed while melting
+    pub tokens: u64,
+}
+
+/// Receives progress updates as a save is parsed or melted
+pub trait ProgressSink {
+    fn progress(&mut self, progress: Progress);
+}
+
+impl<F> ProgressSink for F
+where
+    F: FnMut(Progress),
+{
+    fn progress(&mut self, progress: Progress) {
+        self(progress)
+    }
+}
+
+/// A flag that aborts parsing or melting with [`Eu4ErrorKind::Cancelled`]
+/// once set. Clones share the same flag, so one can be handed to another
+/// thread to cancel work in progress.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Optional progress sink and cancellation token for long running work
+#[derive(Default)]
+pub struct ProgressHooks<'a> {
+    sink: Option<&'a mut dyn ProgressSink>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl<'a> ProgressHooks<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sink that is periodically sent the progress and once more on success
+    pub fn with_sink(self, sink: &'a mut dyn ProgressSink) -> Self {
+        ProgressHooks {
+            sink: Some(sink),
+            ..self
+        }
+    }
+
+    /// Token that is checked periodically for cancellation
+    pub fn with_cancellation(self, cancellation: CancellationToken) -> Self {
+        ProgressHooks {
+            cancellation: Some(cancellation),
+            ..self
+        }
+    }
+}
+
+/// Accumulates progress from readers and token loops that share it
+pub(crate) struct Tracker<'a> {
+    progress: Cell<Progress>,
+    reported: Cell<Progress>,
+    sink: RefCell<Option<&'a mut dyn ProgressSink>>,
+    cancellation: Option<CancellationToken>,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(hooks: ProgressHooks<'a>) -> Self {
+        Tracker {
+            progress: Cell::new(Progress::default()),
+            reported: Cell::new(Progress::default()),
+            sink: RefCell::new(hooks.sink),
+            cancellation: hooks.cancellation,
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+
+    pub fn check(&self) -> Result<(), Eu4Error> {
+        if self.is_cancelled() {
+            Err(Eu4Error::new(Eu4ErrorKind::Cancelled))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn add_bytes(&self, bytes: usize) {
+        let mut progress = self.progress.get();
+        progress.bytes += bytes as u64;
+        self.progress.set(progress);
+        if progress.bytes - self.reported.get().bytes >= BYTES_INTERVAL {
+            self.report();
+        }
+    }
+
+    /// The number of bytes read so far
+    pub fn bytes(&self) -> u64 {
+        self.progress.get().bytes
+    }
+
+    #[inline]
+    pub fn add_token(&self) -> Result<(), Eu4Error> {
+        let mut progress = self.progress.get();
+        progress.tokens += 1;
+        self.progress.set(progress);
+        if progress.tokens - self.reported.get().tokens >= TOKENS_INTERVAL {
+            self.report();
+            self.check()?;
+        }
+        Ok(())
+    }
+
+    fn report(&self) {
+        let progress = self.progress.get();
+        self.reported.set(progress);
+        if let Some(sink) = self.sink.borrow_mut().as_mut() {
+            sink.progress(progress);
+        }
+    }
+
+    /// Sends the final progress on success and converts errors caused by
+    /// cancelling into a cancellation error
+    pub fn finish<T>(&self, result: Result<T, Eu4Error>) -> Result<T, Eu4Error> {
+        match result {
+            Ok(x) => {
+                self.report();
+                Ok(x)
+            }
+            Err(_) if self.is_cancelled() => Err(Eu4Error::new(Eu4ErrorKind::Cancelled)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Counts the bytes read and fails reads once cancelled
+pub(crate) struct TrackedReader<'t, 'a, R> {
+    reader: R,
+    tracker: &'t Tracker<'a>,
+}
+
+impl<'t, 'a, R> TrackedReader<'t, 'a, R> {
+    pub fn new(reader: R, tracker: &'t Tracker<'a>) -> Self {
+        TrackedReader { reader, tracker }
+    }
+}
+
+impl<R: Read> Read for TrackedReader<'_, '_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.tracker.is_cancelled() {
+            return Err(io::Error::other("cancelled"));
+        }
+
+        let read = self.reader.read(buf)?;
+        self.tracker.add_bytes(read);
+        Ok(read)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracked_reader_progress() {
+        let data = vec![0u8; (BYTES_INTERVAL as usize) * 2 + 10];
+        let mut updates = Vec::new();
+        let mut sink = |x: Progress| updates.push(x);
+        let tracker = Tracker::new(ProgressHooks::new().with_sink(&mut sink));
+        let mut reader = TrackedReader::new(data.as_slice(), &tracker);
+        let mut out = Vec::new();
+        let result = reader.read_to_end(&mut out).map_err(Eu4Error::from);
+        tracker.finish(result).unwrap();
+        drop(tracker);
+
+        assert!(updates.len() >= 3);
+        assert_eq!(updates.last().unwrap().bytes, data.len() as u64);
+    }
+
+    #[test]
+    fn test_tracked_reader_cancel() {
+        let token = CancellationToken::new();
+        let tracker = Tracker::new(ProgressHooks::new().with_cancellation(token.clone()));
+        token.cancel();
+
+        let mut reader = TrackedReader::new(&b"abc"[..], &tracker);
+        let result = reader.read_to_end(&mut Vec::new()).map_err(Eu4Error::from);
+        let err = tracker.finish(result).unwrap_err();
+        assert!(matches!(err.kind(), Eu4ErrorKind::Cancelled));
+    }
+}
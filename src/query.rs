@@ -11,7 +11,8 @@ use std::{
     cell::OnceCell,
     cmp::Ordering,
     collections::{HashMap, HashSet},
-    num::NonZeroU16,
+    num::{NonZeroU16, NonZeroU32},
+    sync::Arc,
 };
 
 #[derive(Debug)]
@@ -228,6 +229,42 @@ pub struct ReligionLookup {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ReligionIndex(NonZeroU16);
 
+/// Maps repeated identifiers to compact ids. Each string is allocated once
+/// and shared between the id and string lookups.
+#[derive(Debug, Default)]
+pub struct StringInterner {
+    strings: Vec<Arc<str>>,
+    lookup: HashMap<Arc<str>, InternedId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct InternedId(NonZeroU32);
+
+/// The interned identifiers of a province
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternedProvince {
+    pub culture: Option<InternedId>,
+    pub religion: Option<InternedId>,
+    pub trade_goods: Option<InternedId>,
+    pub buildings: Vec<InternedId>,
+    pub modifiers: Vec<InternedId>,
+}
+
+/// The interned identifiers of a country
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternedCountry {
+    pub primary_culture: Option<InternedId>,
+    pub accepted_cultures: Vec<InternedId>,
+    pub religion: Option<InternedId>,
+}
+
+#[derive(Debug)]
+struct InternedSave {
+    interner: StringInterner,
+    provinces: HashMap<ProvinceId, InternedProvince>,
+    countries: Vec<InternedCountry>,
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Serialize)]
 pub struct Player {
     pub name: String,
@@ -405,6 +442,7 @@ pub struct Query {
     tag_ids: Vec<TagId>,
     tag_lookup: HashMap<CountryTag, TagId>,
     buildings: OnceCell<HashSet<String>>,
+    interned: OnceCell<InternedSave>,
 }
 
 impl Query {
@@ -424,6 +462,7 @@ impl Query {
             tag_ids,
             tag_lookup,
             buildings: OnceCell::default(),
+            interned: OnceCell::default(),
         }
     }
 
@@ -553,6 +592,25 @@ impl Query {
         province_religions(&self.save, lookup)
     }
 
+    /// Interner of the cultures, religions, trade goods, buildings, and
+    /// modifiers found in provinces and countries
+    pub fn interner(&self) -> &StringInterner {
+        &self.interned_save().interner
+    }
+
+    pub fn interned_province(&self, id: &ProvinceId) -> Option<&InternedProvince> {
+        self.interned_save().provinces.get(id)
+    }
+
+    pub fn interned_country(&self, tag: &CountryTag) -> Option<&InternedCountry> {
+        let tag_id = self.tag_lookup.get(tag)?;
+        self.interned_save().countries.get(tag_id.id)
+    }
+
+    fn interned_save(&self) -> &InternedSave {
+        self.interned.get_or_init(|| interned_save(&self.save))
+    }
+
     pub fn religion_lookup(&self) -> ReligionLookup {
         let mut religions = self
             .save
@@ -1142,7 +1200,7 @@ impl Query {
         province: &'a Province,
     ) -> Vec<BuildingEvent<'a>> {
         let buildings = self.built_buildings();
-        let initial_buildings = province.history.other.iter().filter_map(|(key, _event)| {
+        let initial_buildings = province.history.other.keys().filter_map(|key| {
             if buildings.contains(key) {
                 Some(BuildingEvent {
                     building: key.as_str(),
@@ -1485,6 +1543,99 @@ fn province_religions(save: &Eu4Save, lookup: &ReligionLookup) -> ProvinceReligi
     ProvinceReligions { initial, changes }
 }
 
+fn interned_save(save: &Eu4Save) -> InternedSave {
+    // Ids are assigned in province id and then building name order so that
+    // they don't depend on the iteration order of the hash maps
+    let mut interner = StringInterner::default();
+    let mut provinces = save.game.provinces.iter().collect::<Vec<_>>();
+    provinces.sort_unstable_by_key(|(id, _)| **id);
+    let provinces = provinces
+        .into_iter()
+        .map(|(&id, province)| {
+            let mut buildings = province.buildings.keys().collect::<Vec<_>>();
+            buildings.sort_unstable();
+            let interned = InternedProvince {
+                culture: province.culture.as_deref().map(|x| interner.intern(x)),
+                religion: province.religion.as_deref().map(|x| interner.intern(x)),
+                trade_goods: province.trade_goods.as_deref().map(|x| interner.intern(x)),
+                buildings: buildings.into_iter().map(|x| interner.intern(x)).collect(),
+                modifiers: province
+                    .modifiers
+                    .iter()
+                    .map(|x| interner.intern(&x.modifier))
+                    .collect(),
+            };
+            (id, interned)
+        })
+        .collect();
+
+    let countries = save
+        .game
+        .countries
+        .iter()
+        .map(|(_, country)| InternedCountry {
+            primary_culture: country
+                .primary_culture
+                .as_deref()
+                .map(|x| interner.intern(x)),
+            accepted_cultures: country
+                .accepted_cultures
+                .iter()
+                .map(|x| interner.intern(x))
+                .collect(),
+            religion: country.religion.as_deref().map(|x| interner.intern(x)),
+        })
+        .collect();
+
+    InternedSave {
+        interner,
+        provinces,
+        countries,
+    }
+}
+
+impl StringInterner {
+    /// Returns the id of the string, interning it if it hasn't been seen
+    pub fn intern(&mut self, value: &str) -> InternedId {
+        if let Some(id) = self.lookup.get(value) {
+            return *id;
+        }
+
+        let id = u32::try_from(self.strings.len() + 1)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .map(InternedId)
+            .expect("fewer than u32::MAX interned strings");
+        let value: Arc<str> = Arc::from(value);
+        self.strings.push(Arc::clone(&value));
+        self.lookup.insert(value, id);
+        id
+    }
+
+    pub fn index(&self, value: &str) -> Option<InternedId> {
+        self.lookup.get(value).copied()
+    }
+
+    pub fn resolve(&self, id: InternedId) -> &str {
+        &self.strings[id.as_usize()]
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl InternedId {
+    /// A zero based index suitable for indexing a `Vec` of `interner.len()`
+    pub fn as_usize(self) -> usize {
+        (self.0.get() - 1) as usize
+    }
+}
+
 impl ReligionLookup {
     pub fn index(&self, religion: &String) -> Option<ReligionIndex> {
         self.religions
@@ -1505,6 +1656,43 @@ impl ReligionLookup {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_string_interner() {
+        let mut interner = StringInterner::default();
+        let swedish = interner.intern("swedish");
+        let catholic = interner.intern("catholic");
+        assert_eq!(interner.intern("swedish"), swedish);
+        assert_ne!(swedish, catholic);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(interner.resolve(catholic), "catholic");
+        assert_eq!(interner.index("swedish"), Some(swedish));
+        assert_eq!(interner.index("norse"), None);
+        assert_eq!(swedish.as_usize(), 0);
+        assert_eq!(catholic.as_usize(), 1);
+    }
+
+    #[test]
+    fn test_interned_ids_are_ordered() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"SWE\"\n\
+displayed_country_name=\"Sweden\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\n\
+current_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\ntrade={ }\n\
+religion_instance_data={ }\nprovinces={ -3={ name=\"Kalmar\" culture=swedish institutions={ 0 } } \
+-2={ name=\"Oslo\" culture=norwegian institutions={ 0 } } -1={ name=\"Stockholm\" culture=swedish \
+institutions={ 0 } buildings={ temple=yes marketplace=yes } } }\nincome_statistics={ }\n\
+nation_size_statistics={ }\nscore_statistics={ }\ninflation_statistics={ }\n\
+gameplaysettings={ setgameplayoptions={ 1 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\ndiplomacy={ }\n\
+checksum=\"abc123\"\n";
+        let file = crate::Eu4File::from_slice(&data[..]).unwrap();
+        let save = file.parse_save(crate::SegmentedResolver::empty()).unwrap();
+        let query = Query::from_save(save);
+        let interner = query.interner();
+        let names = (0..interner.len())
+            .map(|i| interner.resolve(InternedId(NonZeroU32::new(i as u32 + 1).unwrap())))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["swedish", "marketplace", "temple", "norwegian"]);
+    }
+
     #[test]
     fn test_binary_search_all_start() {
         let data = [1, 1, 3, 3, 4, 4];
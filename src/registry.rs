@@ -0,0 +1,414 @@
+use crate::{
+    file::{Eu4FsFile, Eu4SliceFile},
+    models::{Meta, SavegameVersion},
+    Eu4Error, Eu4ErrorKind, SegmentedResolver, SegmentedResolverBuilder,
+};
+use rawzip::ReaderAt;
+use std::{fmt, str::FromStr};
+
+type VersionParts = [u16; 4];
+
+fn version_parts(version: &SavegameVersion) -> VersionParts {
+    [version.first, version.second, version.third, version.fourth]
+}
+
+/// An inclusive range of save versions that a token table supports
+///
+/// Bounds are written as dotted versions where omitted components match
+/// anything, so `1.30..=1.33` covers `1.30.0.0` through `1.33.x.y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionRange {
+    min: VersionParts,
+    max: VersionParts,
+}
+
+impl VersionRange {
+    /// Create a range from the given bounds (eg: `"1.30"` and `"1.33.3"`)
+    pub fn new(min: &str, max: &str) -> Result<VersionRange, Eu4Error> {
+        let min = parse_version(min, 0)?;
+        let max = parse_version(max, u16::MAX)?;
+        if min > max {
+            return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(format!(
+                "version range starts after it ends: {}",
+                Range(&min, &max)
+            ))));
+        }
+
+        Ok(VersionRange { min, max })
+    }
+
+    /// Create a range that covers the given version and all that come after
+    pub fn from_version(min: &str) -> Result<VersionRange, Eu4Error> {
+        Ok(VersionRange {
+            min: parse_version(min, 0)?,
+            max: [u16::MAX; 4],
+        })
+    }
+
+    /// Returns if the save version falls within the range
+    pub fn contains(&self, version: &SavegameVersion) -> bool {
+        let version = version_parts(version);
+        self.min <= version && version <= self.max
+    }
+}
+
+impl FromStr for VersionRange {
+    type Err = Eu4Error;
+
+    /// Parses `1.30..=1.33` or the open ended `1.34..`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once("..") {
+            Some((min, "")) => VersionRange::from_version(min),
+            Some((min, max)) => VersionRange::new(min, max.trim_start_matches('=')),
+            None => VersionRange::new(s, s),
+        }
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Range(&self.min, &self.max).fmt(f)
+    }
+}
+
+struct Range<'a>(&'a VersionParts, &'a VersionParts);
+
+impl fmt::Display for Range<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_bound(f: &mut fmt::Formatter<'_>, parts: &VersionParts, fill: u16) -> fmt::Result {
+            let len = parts.iter().rposition(|&x| x != fill).map_or(0, |x| x + 1);
+            for (i, part) in parts[..len.max(1)].iter().enumerate() {
+                if i != 0 {
+                    f.write_str(".")?;
+                }
+                write!(f, "{}", part)?;
+            }
+            Ok(())
+        }
+
+        write_bound(f, self.0, 0)?;
+        f.write_str("..")?;
+        if *self.1 != [u16::MAX; 4] {
+            f.write_str("=")?;
+            write_bound(f, self.1, u16::MAX)?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_version(s: &str, fill: u16) -> Result<VersionParts, Eu4Error> {
+    let mut parts = [fill; 4];
+    let mut components = s.trim().split('.');
+    for part in parts.iter_mut() {
+        match components.next() {
+            Some(x) => *part = x.parse().map_err(|_| invalid_version(s))?,
+            None => break,
+        }
+    }
+
+    if components.next().is_some() {
+        return Err(invalid_version(s));
+    }
+
+    Ok(parts)
+}
+
+fn invalid_version(s: &str) -> Eu4Error {
+    Eu4Error::new(Eu4ErrorKind::InvalidSyntax(format!(
+        "invalid save version: {}",
+        s
+    )))
+}
+
+/// How well the selected token table matches a save
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionFit {
+    /// The save version is within the table's range
+    Exact,
+
+    /// The save is newer than every registered table, so the most recent
+    /// table was selected. Tokens introduced since may fail to resolve.
+    Newer,
+
+    /// No table covers the save version, so the closest older table (or the
+    /// oldest table, if there is none) was selected
+    Nearest,
+}
+
+/// The token table that a [`ResolverRegistry`] selected for a save
+pub struct ResolverSelection<'a> {
+    /// The version of the save
+    pub version: SavegameVersion,
+
+    /// The versions the selected table was registered for
+    pub range: VersionRange,
+
+    /// How well the table matches the save version
+    pub fit: VersionFit,
+
+    table: &'a SegmentedResolverBuilder,
+}
+
+impl<'a> ResolverSelection<'a> {
+    /// The selected token table
+    pub fn table(&self) -> &'a SegmentedResolverBuilder {
+        self.table
+    }
+
+    /// A resolver for the selected token table
+    pub fn resolver(&self) -> SegmentedResolver<'a> {
+        self.table.resolver()
+    }
+
+    /// A description of why the selected table may not resolve every token
+    /// in the save, if it is not an exact match
+    pub fn warning(&self) -> Option<String> {
+        let v = &self.version;
+        let version = format!("{}.{}.{}.{}", v.first, v.second, v.third, v.fourth);
+        match self.fit {
+            VersionFit::Exact => None,
+            VersionFit::Newer => Some(format!(
+                "save version {} is newer than any registered token table, using {}",
+                version, self.range
+            )),
+            VersionFit::Nearest => Some(format!(
+                "no token table registered for save version {}, using {}",
+                version, self.range
+            )),
+        }
+    }
+}
+
+/// A collection of token tables for different game versions
+///
+/// Binary saves need the token table of the patch that wrote them. The
+/// registry picks the table from a save's `savegame_version`.
+///
+/// ```
+/// use eu4save::{ResolverRegistry, SegmentedResolver};
+/// let mut registry = ResolverRegistry::new();
+/// registry.register("1.30..=1.36".parse()?, SegmentedResolver::parse(&b"0x0001 date\n"[..])?);
+/// registry.register("1.37..".parse()?, SegmentedResolver::parse(&b"0x0001 date\n"[..])?);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Default)]
+pub struct ResolverRegistry {
+    tables: Vec<(VersionRange, SegmentedResolverBuilder)>,
+}
+
+impl ResolverRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a token table for the given versions
+    pub fn register(&mut self, range: VersionRange, table: SegmentedResolverBuilder) -> &mut Self {
+        self.tables.push((range, table));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.is_empty()
+    }
+
+    /// Selects the token table for a save version. Of the tables whose range
+    /// covers the version, the one with the latest start is preferred.
+    /// Returns `None` when no tables are registered.
+    pub fn select(&self, version: &SavegameVersion) -> Option<ResolverSelection<'_>> {
+        let parts = version_parts(version);
+        let exact = self
+            .tables
+            .iter()
+            .filter(|(range, _)| range.contains(version))
+            .max_by_key(|(range, _)| range.min);
+
+        let (entry, fit) = match exact {
+            Some(entry) => (entry, VersionFit::Exact),
+            None => {
+                let newest = self.tables.iter().max_by_key(|(range, _)| range.max)?;
+                if newest.0.max < parts {
+                    (newest, VersionFit::Newer)
+                } else {
+                    let nearest = self
+                        .tables
+                        .iter()
+                        .filter(|(range, _)| range.max < parts)
+                        .max_by_key(|(range, _)| range.max)
+                        .or_else(|| self.tables.iter().min_by_key(|(range, _)| range.min))?;
+                    (nearest, VersionFit::Nearest)
+                }
+            }
+        };
+
+        let (range, table) = entry;
+        Some(ResolverSelection {
+            version: version.clone(),
+            range: *range,
+            fit,
+            table,
+        })
+    }
+
+    /// Peeks at the save's metadata to select a token table. The metadata of
+    /// binary saves is read with the newest registered table, as token ids
+    /// are kept across patches and the newest table knows the most of them.
+    pub fn select_for(&self, file: &Eu4SliceFile) -> Result<ResolverSelection<'_>, Eu4Error> {
+        self.select_with(|resolver| file.parse_meta(resolver))
+    }
+
+    /// Peeks at the metadata of a save opened with
+    /// [`Eu4File::from_file`](crate::Eu4File::from_file) to select a token
+    /// table, like [`select_for`](ResolverRegistry::select_for)
+    pub fn select_for_file<R>(&self, file: &Eu4FsFile<R>) -> Result<ResolverSelection<'_>, Eu4Error>
+    where
+        R: ReaderAt,
+    {
+        self.select_with(|resolver| file.parse_meta(resolver))
+    }
+
+    fn select_with<F>(&self, parse_meta: F) -> Result<ResolverSelection<'_>, Eu4Error>
+    where
+        F: FnOnce(SegmentedResolver<'_>) -> Result<Meta, Eu4Error>,
+    {
+        let (_, newest) = self
+            .tables
+            .iter()
+            .max_by_key(|(range, _)| range.max)
+            .ok_or_else(|| Eu4Error::new(Eu4ErrorKind::NoTokenTables))?;
+
+        let meta = parse_meta(newest.resolver())?;
+        self.select(&meta.savegame_version)
+            .ok_or_else(|| Eu4Error::new(Eu4ErrorKind::NoTokenTables))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Eu4File;
+
+    fn version(first: u16, second: u16, third: u16, fourth: u16) -> SavegameVersion {
+        SavegameVersion {
+            first,
+            second,
+            third,
+            fourth,
+            name: String::new(),
+        }
+    }
+
+    fn table(token: &str) -> SegmentedResolverBuilder {
+        SegmentedResolver::parse(format!("0x0001 {}\n", token).as_bytes()).unwrap()
+    }
+
+    fn registry() -> ResolverRegistry {
+        let mut registry = ResolverRegistry::new();
+        registry
+            .register("1.30..=1.33".parse().unwrap(), table("a"))
+            .register("1.33.3..=1.34".parse().unwrap(), table("b"))
+            .register("1.36..=1.37".parse().unwrap(), table("c"));
+        registry
+    }
+
+    fn token(selection: &ResolverSelection) -> String {
+        let resolver = selection.resolver();
+        String::from(jomini::binary::TokenResolver::resolve(&resolver, 1).unwrap())
+    }
+
+    #[test]
+    fn test_version_range() {
+        let range: VersionRange = "1.30..=1.33".parse().unwrap();
+        assert!(range.contains(&version(1, 30, 0, 0)));
+        assert!(range.contains(&version(1, 33, 3, 1)));
+        assert!(!range.contains(&version(1, 29, 9, 0)));
+        assert!(!range.contains(&version(1, 34, 0, 0)));
+        assert_eq!(range.to_string(), "1.30..=1.33");
+
+        let range: VersionRange = "1.34.2..".parse().unwrap();
+        assert!(range.contains(&version(1, 37, 0, 0)));
+        assert!(!range.contains(&version(1, 34, 1, 0)));
+        assert_eq!(range.to_string(), "1.34.2..");
+
+        assert!("1.34..=1.30".parse::<VersionRange>().is_err());
+        assert!("1.x".parse::<VersionRange>().is_err());
+        assert!("1.2.3.4.5".parse::<VersionRange>().is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let registry = registry();
+
+        let selection = registry.select(&version(1, 31, 0, 0)).unwrap();
+        assert_eq!(selection.fit, VersionFit::Exact);
+        assert_eq!(token(&selection), "a");
+        assert!(selection.warning().is_none());
+
+        // Overlapping ranges prefer the more recent table
+        let selection = registry.select(&version(1, 33, 3, 0)).unwrap();
+        assert_eq!(selection.fit, VersionFit::Exact);
+        assert_eq!(token(&selection), "b");
+
+        let selection = registry.select(&version(1, 35, 1, 0)).unwrap();
+        assert_eq!(selection.fit, VersionFit::Nearest);
+        assert_eq!(token(&selection), "b");
+
+        let selection = registry.select(&version(1, 29, 0, 0)).unwrap();
+        assert_eq!(selection.fit, VersionFit::Nearest);
+        assert_eq!(token(&selection), "a");
+
+        let selection = registry.select(&version(1, 38, 0, 0)).unwrap();
+        assert_eq!(selection.fit, VersionFit::Newer);
+        assert_eq!(token(&selection), "c");
+        assert_eq!(
+            selection.warning().unwrap(),
+            "save version 1.38.0.0 is newer than any registered token table, using 1.36..=1.37"
+        );
+
+        assert!(ResolverRegistry::new()
+            .select(&version(1, 30, 0, 0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_select_for_text_save() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"ENG\"\n\
+displayed_country_name=\"England\"\nsavegame_version={ first=1 second=37 third=2 forth=0 name=\"Inca\" }\n\
+savegame_versions={ \"1.37.2.0\" }\ndlc_enabled={ }\nmulti_player=no\nnot_observer=yes\n\
+campaign_id=\"a1b2c3\"\ncampaign_length=0\ncampaign_stats={ }\nis_random_new_world=no\n\
+checksum=\"abc123\"\n";
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        let registry = registry();
+        let selection = registry.select_for(&file).unwrap();
+        assert_eq!(selection.version.name, "Inca");
+        assert_eq!(selection.fit, VersionFit::Exact);
+        assert_eq!(token(&selection), "c");
+    }
+
+    #[test]
+    fn test_select_for_file() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"ENG\"\n\
+displayed_country_name=\"England\"\nsavegame_version={ first=1 second=33 third=0 forth=0 name=\"Sunset\" }\n\
+multi_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\nchecksum=\"abc123\"\n\
+current_age=age_of_discovery\nstart_date=1444.11.11\nmap_area_data={ }\n\
+trade={ }\nreligion_instance_data={ }\nreligions={ }\nprovinces={ }\n\
+income_statistics={ }\nnation_size_statistics={ }\nscore_statistics={ }\ninflation_statistics={ }\n\
+gameplaysettings={ setgameplayoptions={ 1 0 0 0 0 0 0 0 0 0 0 0 0 0 1 } }\ndiplomacy={ }\n";
+        let path =
+            std::env::temp_dir().join(format!("eu4save-registry-{}.eu4", std::process::id()));
+        std::fs::write(&path, &data[..]).unwrap();
+        let file = Eu4File::from_file(std::fs::File::open(&path).unwrap()).unwrap();
+        let registry = registry();
+        let selection = registry.select_for_file(&file).unwrap();
+        let save = file.parse_save(&selection.resolver());
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(selection.fit, VersionFit::Exact);
+        assert_eq!(token(&selection), "a");
+        assert_eq!(save.unwrap().meta.player.as_str(), "ENG");
+
+        let empty = ResolverRegistry::new();
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        let err = empty.select_for(&file).err().unwrap();
+        assert!(matches!(err.kind(), Eu4ErrorKind::NoTokenTables));
+    }
+}
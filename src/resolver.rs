@@ -1,7 +1,15 @@
-use std::io::BufRead;
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+};
 
 use crate::{Eu4Error, Eu4ErrorKind};
 
+/// Identifies the binary token table format written by
+/// [`SegmentedResolver::write_binary`]
+const TABLE_MAGIC: &[u8; 6] = b"eu4tok";
+const TABLE_VERSION: u8 = 1;
+
 pub struct SegmentedResolver<'a> {
     values: Vec<&'a str>,
 
@@ -74,6 +82,171 @@ impl<'a> SegmentedResolver<'a> {
             upper_sequence_start: 0,
         })
     }
+
+    /// Create a resolver that borrows from the compact binary table written by
+    /// [`SegmentedResolver::write_binary`], so a table embedded with
+    /// `include_bytes!` can be used without copying.
+    pub fn from_binary(data: &'a [u8]) -> Result<SegmentedResolver<'a>, Eu4Error> {
+        let (header, mut data) = data
+            .split_first_chunk::<15>()
+            .ok_or_else(|| invalid_table("missing header"))?;
+        let (magic, header) = header.split_at(TABLE_MAGIC.len());
+        if magic != TABLE_MAGIC {
+            return Err(invalid_table("unrecognized header"));
+        } else if header[0] != TABLE_VERSION {
+            return Err(invalid_table("unsupported version"));
+        }
+
+        let lower_sequence_end = u16::from_le_bytes([header[1], header[2]]);
+        let upper_sequence_start = u16::from_le_bytes([header[3], header[4]]);
+        let count = u32::from_le_bytes([header[5], header[6], header[7], header[8]]);
+
+        let mut values = Vec::with_capacity(count.min(u32::from(u16::MAX) + 1) as usize);
+        for _ in 0..count {
+            let (len, rest) = data
+                .split_first_chunk::<2>()
+                .ok_or_else(|| invalid_table("truncated"))?;
+            let len = usize::from(u16::from_le_bytes(*len));
+            if rest.len() < len {
+                return Err(invalid_table("truncated"));
+            }
+            let (value, rest) = rest.split_at(len);
+            let value =
+                std::str::from_utf8(value).map_err(|_| invalid_table("token is not utf-8"))?;
+            values.push(value);
+            data = rest;
+        }
+
+        Ok(SegmentedResolver {
+            values,
+            lower_sequence_end,
+            upper_sequence_start,
+        })
+    }
+
+    /// Create the reverse lookup of field names to binary tokens, for use when
+    /// converting text back into binary.
+    pub fn encoder(&self) -> HashMap<&'a str, u16> {
+        let mut result = HashMap::with_capacity(self.values.len());
+        for (token, value) in self.tokens() {
+            result.entry(value).or_insert(token);
+        }
+        result
+    }
+
+    /// Iterates through the known tokens and their names in ascending order
+    pub fn tokens(&self) -> impl Iterator<Item = (u16, &'a str)> + '_ {
+        self.values
+            .iter()
+            .enumerate()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(i, value)| {
+                let token = if i < usize::from(self.lower_sequence_end) {
+                    i as u16
+                } else {
+                    (i - usize::from(self.lower_sequence_end)) as u16 + self.upper_sequence_start
+                };
+                (token, *value)
+            })
+    }
+
+    /// Writes the tokens in the line oriented text format that
+    /// [`SegmentedResolver::parse`] reads
+    pub fn write_text<W: Write>(&self, mut writer: W) -> Result<(), Eu4Error> {
+        for (token, value) in self.tokens() {
+            writeln!(writer, "0x{:04x} {}", token, value)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the tokens as a compact binary table that
+    /// [`SegmentedResolver::from_binary`] reads. The table is a header
+    /// followed by each name prefixed with its length.
+    pub fn write_binary<W: Write>(&self, mut writer: W) -> Result<(), Eu4Error> {
+        let count = self.values.len() as u32;
+        writer.write_all(TABLE_MAGIC)?;
+        writer.write_all(&[TABLE_VERSION])?;
+        writer.write_all(&self.lower_sequence_end.to_le_bytes())?;
+        writer.write_all(&self.upper_sequence_start.to_le_bytes())?;
+        writer.write_all(&count.to_le_bytes())?;
+        for value in &self.values {
+            let len =
+                u16::try_from(value.len()).map_err(|_| invalid_table("token name too long"))?;
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(value.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Compares the tokens of this table against a newer table
+    pub fn diff(&self, other: &SegmentedResolver) -> TokenDiff {
+        let mut result = TokenDiff::default();
+        let mut old = self.tokens().peekable();
+        let mut new = other.tokens().peekable();
+        loop {
+            match (old.peek().copied(), new.peek().copied()) {
+                (Some((a, before)), Some((b, after))) if a == b => {
+                    if before != after {
+                        result.changed.push(TokenChange {
+                            token: a,
+                            before: String::from(before),
+                            after: String::from(after),
+                        });
+                    }
+                    old.next();
+                    new.next();
+                }
+                (Some((a, before)), Some((b, _))) if a < b => {
+                    result.removed.push((a, String::from(before)));
+                    old.next();
+                }
+                (Some((a, before)), None) => {
+                    result.removed.push((a, String::from(before)));
+                    old.next();
+                }
+                (_, Some((b, after))) => {
+                    result.added.push((b, String::from(after)));
+                    new.next();
+                }
+                (None, None) => break,
+            }
+        }
+        result
+    }
+}
+
+fn invalid_table(msg: &str) -> Eu4Error {
+    Eu4Error::from(Eu4ErrorKind::InvalidSyntax(format!(
+        "invalid token table: {}",
+        msg
+    )))
+}
+
+/// A token that has a different name between two tables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenChange {
+    pub token: u16,
+    pub before: String,
+    pub after: String,
+}
+
+/// The differences between two token tables
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenDiff {
+    /// Tokens only in the newer table
+    pub added: Vec<(u16, String)>,
+
+    /// Tokens only in the older table
+    pub removed: Vec<(u16, String)>,
+
+    /// Tokens that were renamed
+    pub changed: Vec<TokenChange>,
+}
+
+impl TokenDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
 }
 
 impl jomini::binary::TokenResolver for SegmentedResolver<'_> {
@@ -115,4 +288,180 @@ impl SegmentedResolverBuilder {
             upper_sequence_start: self.upper_sequence_start,
         }
     }
+
+    /// Create an owned table from the binary format written by
+    /// [`SegmentedResolver::write_binary`]
+    pub fn from_binary(data: &[u8]) -> Result<SegmentedResolverBuilder, Eu4Error> {
+        Ok(SegmentedResolverBuilder::from(
+            &SegmentedResolver::from_binary(data)?,
+        ))
+    }
+
+    /// Sets the name of a token and returns the name it replaced. Tokens that
+    /// fall between the lower and upper sequence extend the lower sequence.
+    pub fn insert(&mut self, token: u16, name: String) -> Option<String> {
+        let lower_end = usize::from(self.lower_sequence_end);
+        let upper_start = usize::from(self.upper_sequence_start);
+        let has_upper = self.values.len() > lower_end;
+        let token_ind = usize::from(token);
+
+        let ind = if token_ind < lower_end {
+            token_ind
+        } else if has_upper && token_ind >= upper_start {
+            lower_end + (token_ind - upper_start)
+        } else if token == u16::MAX {
+            // The lower sequence can't extend through the last token, so
+            // start an upper sequence with it
+            self.upper_sequence_start = token;
+            lower_end
+        } else {
+            // Grow the lower sequence up to the token, which stays below the
+            // start of any upper sequence
+            let empties = token_ind + 1 - lower_end;
+            self.values.splice(
+                lower_end..lower_end,
+                std::iter::repeat_n(String::new(), empties),
+            );
+            self.lower_sequence_end = token + 1;
+            token_ind
+        };
+
+        if self.values.len() <= ind {
+            self.values.resize(ind + 1, String::new());
+        }
+
+        let previous = std::mem::replace(&mut self.values[ind], name);
+        (!previous.is_empty()).then_some(previous)
+    }
+
+    /// Layers the tokens of a patch over these tokens. Tokens that the patch
+    /// names differently are overwritten by the patch and reported.
+    pub fn merge(&mut self, patch: &SegmentedResolver) -> Vec<TokenChange> {
+        let mut conflicts = Vec::new();
+        for (token, name) in patch.tokens() {
+            match self.insert(token, String::from(name)) {
+                Some(previous) if previous != name => conflicts.push(TokenChange {
+                    token,
+                    before: previous,
+                    after: String::from(name),
+                }),
+                _ => {}
+            }
+        }
+        conflicts
+    }
+}
+
+impl From<&SegmentedResolver<'_>> for SegmentedResolverBuilder {
+    fn from(value: &SegmentedResolver<'_>) -> Self {
+        SegmentedResolverBuilder {
+            values: value.values.iter().map(|x| String::from(*x)).collect(),
+            lower_sequence_end: value.lower_sequence_end,
+            upper_sequence_start: value.upper_sequence_start,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jomini::binary::TokenResolver;
+
+    fn table(data: &str) -> SegmentedResolverBuilder {
+        SegmentedResolver::parse(data.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_binary_table_roundtrip() {
+        let values = vec!["", "date", "player", "", "savegame_version"];
+        let resolver = SegmentedResolver::from_parts(values, 3, 0x8000);
+
+        let mut data = Vec::new();
+        resolver.write_binary(&mut data).unwrap();
+        let parsed = SegmentedResolver::from_binary(&data).unwrap();
+        assert_eq!(parsed.resolve(0x1), Some("date"));
+        assert_eq!(parsed.resolve(0x2), Some("player"));
+        assert_eq!(parsed.resolve(0x8001), Some("savegame_version"));
+        assert_eq!(parsed.resolve(0x8000), None);
+        assert!(parsed.diff(&resolver).is_empty());
+
+        assert!(SegmentedResolver::from_binary(&data[..data.len() - 1]).is_err());
+        assert!(SegmentedResolver::from_binary(b"0x0001 date").is_err());
+    }
+
+    #[test]
+    fn test_text_roundtrip() {
+        let builder = table("0x0001 date\n0x0003 player\n");
+        let mut out = Vec::new();
+        builder.resolver().write_text(&mut out).unwrap();
+        assert_eq!(out, b"0x0001 date\n0x0003 player\n");
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut base = table("0x0001 date\n0x0003 player\n");
+        let patch = table("0x0003 human\n0x0005 savegame_version\n");
+        let conflicts = base.merge(&patch.resolver());
+        assert_eq!(
+            conflicts,
+            vec![TokenChange {
+                token: 3,
+                before: String::from("player"),
+                after: String::from("human"),
+            }]
+        );
+
+        let resolver = base.resolver();
+        assert_eq!(resolver.resolve(0x1), Some("date"));
+        assert_eq!(resolver.resolve(0x3), Some("human"));
+        assert_eq!(resolver.resolve(0x5), Some("savegame_version"));
+    }
+
+    #[test]
+    fn test_insert_between_sequences() {
+        let values = vec!["", "date", "savegame_version"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let mut builder = SegmentedResolverBuilder {
+            values,
+            lower_sequence_end: 2,
+            upper_sequence_start: 0x8000,
+        };
+
+        assert_eq!(builder.insert(0x10, String::from("player")), None);
+        assert_eq!(builder.insert(0x8001, String::from("speed")), None);
+        assert_eq!(builder.insert(0xffff, String::from("last")), None);
+        let resolver = builder.resolver();
+        assert_eq!(resolver.resolve(0x1), Some("date"));
+        assert_eq!(resolver.resolve(0x10), Some("player"));
+        assert_eq!(resolver.resolve(0x8000), Some("savegame_version"));
+        assert_eq!(resolver.resolve(0x8001), Some("speed"));
+        assert_eq!(resolver.resolve(0xffff), Some("last"));
+
+        assert_eq!(
+            builder.insert(0x8000, String::from("version")),
+            Some(String::from("savegame_version"))
+        );
+        let resolver = builder.resolver();
+        assert_eq!(resolver.resolve(0x8000), Some("version"));
+        assert_eq!(resolver.resolve(0x8001), Some("speed"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = table("0x0001 date\n0x0003 player\n0x0004 removed\n");
+        let new = table("0x0001 date\n0x0003 human\n0x0005 added\n");
+        let diff = old.resolver().diff(&new.resolver());
+        assert_eq!(diff.added, vec![(5, String::from("added"))]);
+        assert_eq!(diff.removed, vec![(4, String::from("removed"))]);
+        assert_eq!(
+            diff.changed,
+            vec![TokenChange {
+                token: 3,
+                before: String::from("player"),
+                after: String::from("human"),
+            }]
+        );
+    }
 }
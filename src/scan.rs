@@ -0,0 +1,188 @@
+//! Locates the `countries` and `provinces` sections of a gamestate
+//!
+//! A token scan that skips over every other container records where these
+//! sections and each of their entries start so that they can be processed
+//! separately from the remainder of the document.
+use crate::{Encoding, Eu4Error};
+use jomini::binary::TokenResolver;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SectionKind {
+    Countries,
+    Provinces,
+}
+
+impl SectionKind {
+    fn from_key(key: &[u8]) -> Option<Self> {
+        match key {
+            b"countries" => Some(SectionKind::Countries),
+            b"provinces" => Some(SectionKind::Provinces),
+            _ => None,
+        }
+    }
+}
+
+/// Byte offsets of a top level section's contents (excluding the braces) and
+/// where each of its entries start
+pub(crate) struct Section {
+    #[cfg_attr(not(feature = "parallel"), allow(dead_code))]
+    pub kind: SectionKind,
+    pub start: usize,
+    pub end: usize,
+    pub entries: Vec<usize>,
+}
+
+impl Section {
+    /// The byte range of each entry in the section
+    pub fn entry_ranges(&self) -> impl Iterator<Item = std::ops::Range<usize>> + '_ {
+        self.entries.iter().enumerate().map(|(i, &start)| {
+            let end = self.entries.get(i + 1).copied().unwrap_or(self.end);
+            start..end
+        })
+    }
+}
+
+enum Lexeme {
+    Open,
+    Close,
+    Equal,
+    Scalar(Option<SectionKind>),
+}
+
+trait Scanner {
+    fn position(&self) -> usize;
+    fn next_lexeme(&mut self) -> Result<Option<Lexeme>, Eu4Error>;
+    fn skip_container(&mut self) -> Result<(), Eu4Error>;
+}
+
+struct TextScanner<'a>(jomini::text::TokenReader<'a>);
+
+impl Scanner for TextScanner<'_> {
+    fn position(&self) -> usize {
+        self.0.position()
+    }
+
+    fn next_lexeme(&mut self) -> Result<Option<Lexeme>, Eu4Error> {
+        use jomini::text::Token;
+        let lexeme = self.0.next()?.map(|token| match token {
+            Token::Open => Lexeme::Open,
+            Token::Close => Lexeme::Close,
+            Token::Operator(_) => Lexeme::Equal,
+            Token::Unquoted(x) | Token::Quoted(x) => {
+                Lexeme::Scalar(SectionKind::from_key(x.as_bytes()))
+            }
+        });
+        Ok(lexeme)
+    }
+
+    fn skip_container(&mut self) -> Result<(), Eu4Error> {
+        Ok(self.0.skip_container()?)
+    }
+}
+
+struct BinaryScanner<'a, R> {
+    reader: jomini::binary::TokenReader<'a>,
+    resolver: &'a R,
+}
+
+impl<R: TokenResolver> Scanner for BinaryScanner<'_, R> {
+    fn position(&self) -> usize {
+        self.reader.position()
+    }
+
+    fn next_lexeme(&mut self) -> Result<Option<Lexeme>, Eu4Error> {
+        use jomini::binary::Token;
+        let resolver = self.resolver;
+        let lexeme = self.reader.next()?.map(|token| match token {
+            Token::Open => Lexeme::Open,
+            Token::Close => Lexeme::Close,
+            Token::Equal => Lexeme::Equal,
+            Token::Id(id) => Lexeme::Scalar(
+                resolver
+                    .resolve(id)
+                    .and_then(|x| SectionKind::from_key(x.as_bytes())),
+            ),
+            Token::Quoted(x) | Token::Unquoted(x) => {
+                Lexeme::Scalar(SectionKind::from_key(x.as_bytes()))
+            }
+            _ => Lexeme::Scalar(None),
+        });
+        Ok(lexeme)
+    }
+
+    fn skip_container(&mut self) -> Result<(), Eu4Error> {
+        Ok(self.reader.skip_container()?)
+    }
+}
+
+/// Reads the value of a field after the key has been read and returns if the
+/// value is a container that has yet to be read
+fn read_value<S: Scanner>(scanner: &mut S) -> Result<bool, Eu4Error> {
+    let mut lexeme = scanner.next_lexeme()?;
+    if matches!(lexeme, Some(Lexeme::Equal)) {
+        lexeme = scanner.next_lexeme()?;
+    }
+
+    Ok(matches!(lexeme, Some(Lexeme::Open)))
+}
+
+fn scan_section<S: Scanner>(scanner: &mut S, kind: SectionKind) -> Result<Section, Eu4Error> {
+    let start = scanner.position();
+    let mut entries = Vec::new();
+    loop {
+        let position = scanner.position();
+        match scanner.next_lexeme()? {
+            Some(Lexeme::Close) | None => {
+                return Ok(Section {
+                    kind,
+                    start,
+                    end: position,
+                    entries,
+                })
+            }
+
+            // The array that follows a header (eg: `rgb { 1 2 3 }`)
+            Some(Lexeme::Open) => scanner.skip_container()?,
+            Some(_) => {
+                entries.push(position);
+                if read_value(scanner)? {
+                    scanner.skip_container()?;
+                }
+            }
+        }
+    }
+}
+
+fn scan_sections<S: Scanner>(scanner: &mut S) -> Result<Vec<Section>, Eu4Error> {
+    let mut sections = Vec::new();
+    loop {
+        match scanner.next_lexeme()? {
+            None => return Ok(sections),
+            Some(Lexeme::Open) => scanner.skip_container()?,
+            Some(Lexeme::Scalar(kind)) => {
+                let is_container = read_value(scanner)?;
+                match kind {
+                    Some(kind) if is_container => sections.push(scan_section(scanner, kind)?),
+                    _ if is_container => scanner.skip_container()?,
+                    _ => {}
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Scans headerless text or binary data for the top level sections
+pub(crate) fn scan<R: TokenResolver>(
+    data: &[u8],
+    encoding: Encoding,
+    resolver: &R,
+) -> Result<Vec<Section>, Eu4Error> {
+    if matches!(encoding, Encoding::Text) {
+        let reader = jomini::text::TokenReader::from_slice(data);
+        scan_sections(&mut TextScanner(reader))
+    } else {
+        let reader = jomini::binary::TokenReader::from_slice(data);
+        scan_sections(&mut BinaryScanner { reader, resolver })
+    }
+}
@@ -0,0 +1,572 @@
+use crate::{
+    de::{deserialize_map_with_capacity, deserialize_vec_pair, deserialize_vec_pair_with_capacity},
+    models::{
+        ActiveWar, Country, Diplomacy, Hegemon, LedgerData, MapAreaDatum, Meta, PreviousWar,
+        Province, ReligionGameState, ReligionInstanceDatum, TradeNodes, HRE,
+    },
+    CountryTag, Eu4Date, ProvinceId,
+};
+#[cfg(feature = "serialize")]
+use serde::Serialize;
+use serde::{
+    de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use std::{collections::HashMap, marker::PhantomData};
+
+/// A top level section of the gamestate that can be selectively parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSection {
+    /// `countries`
+    Countries,
+
+    /// `provinces`
+    Provinces,
+
+    /// `active_war` and `previous_war`
+    Wars,
+
+    /// `diplomacy`
+    Diplomacy,
+
+    /// `trade`
+    Trade,
+
+    /// `income_statistics`, `nation_size_statistics`, `score_statistics`,
+    /// and `inflation_statistics`
+    Ledgers,
+
+    /// `religions` and `religion_instance_data`
+    Religions,
+
+    /// `empire`
+    Empire,
+
+    /// `military_hegemon`, `naval_hegemon`, and `economic_hegemon`
+    Hegemons,
+
+    /// `map_area_data`
+    MapAreas,
+}
+
+impl GameSection {
+    const fn bit(self) -> u16 {
+        1 << (self as u16)
+    }
+}
+
+/// A set of gamestate sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GameSections(u16);
+
+impl GameSections {
+    /// No sections
+    pub const fn empty() -> Self {
+        GameSections(0)
+    }
+
+    /// Every section
+    pub const fn all() -> Self {
+        GameSections(u16::MAX)
+    }
+
+    /// Return the set with the section added
+    pub const fn with(self, section: GameSection) -> Self {
+        GameSections(self.0 | section.bit())
+    }
+
+    pub const fn contains(self, section: GameSection) -> bool {
+        self.0 & section.bit() != 0
+    }
+}
+
+impl From<GameSection> for GameSections {
+    fn from(value: GameSection) -> Self {
+        GameSections::empty().with(value)
+    }
+}
+
+impl FromIterator<GameSection> for GameSections {
+    fn from_iter<T: IntoIterator<Item = GameSection>>(iter: T) -> Self {
+        iter.into_iter()
+            .fold(GameSections::empty(), GameSections::with)
+    }
+}
+
+/// A gamestate where only the requested sections are deserialized. Sections
+/// that were not requested are `None`.
+///
+/// A handful of small fields are always deserialized.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct PartialGameState {
+    pub players_countries: Vec<String>,
+    pub current_age: Option<String>,
+    pub start_date: Option<Eu4Date>,
+    pub countries: Option<Vec<(CountryTag, Country)>>,
+    pub provinces: Option<HashMap<ProvinceId, Province>>,
+    pub active_wars: Option<Vec<ActiveWar>>,
+    pub previous_wars: Option<Vec<PreviousWar>>,
+    pub diplomacy: Option<Diplomacy>,
+    pub trade: Option<TradeNodes>,
+    pub income_statistics: Option<LedgerData>,
+    pub nation_size_statistics: Option<LedgerData>,
+    pub score_statistics: Option<LedgerData>,
+    pub inflation_statistics: Option<LedgerData>,
+    pub religions: Option<Vec<(String, ReligionGameState)>>,
+    pub religion_instance_data: Option<HashMap<String, ReligionInstanceDatum>>,
+    pub empire: Option<HRE>,
+    pub military_hegemon: Option<Hegemon>,
+    pub naval_hegemon: Option<Hegemon>,
+    pub economic_hegemon: Option<Hegemon>,
+    pub map_area_data: Option<HashMap<String, MapAreaDatum>>,
+}
+
+/// The save metadata and the selected sections of the gamestate
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize))]
+pub struct PartialSave {
+    #[cfg_attr(feature = "serialize", serde(flatten))]
+    pub meta: Meta,
+
+    #[cfg_attr(feature = "serialize", serde(flatten))]
+    pub game: PartialGameState,
+}
+
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct CountriesSection(
+    #[serde(deserialize_with = "deserialize_vec_pair_with_capacity::<_, _, _, 1400>")]
+    Vec<(CountryTag, Country)>,
+);
+
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct ProvincesSection(
+    #[serde(deserialize_with = "deserialize_map_with_capacity::<_, _, _, 5000>")]
+    HashMap<ProvinceId, Province>,
+);
+
+#[derive(Deserialize)]
+#[serde(transparent)]
+struct ReligionsSection(
+    #[serde(deserialize_with = "deserialize_vec_pair")] Vec<(String, ReligionGameState)>,
+);
+
+/// Deserializes a gamestate, skipping over the unselected sections without
+/// deserializing them
+pub(crate) struct PartialGameStateSeed(pub GameSections);
+
+impl<'de> DeserializeSeed<'de> for PartialGameStateSeed {
+    type Value = PartialGameState;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("PartialGameState", &[], self)
+    }
+}
+
+impl<'de> Visitor<'de> for PartialGameStateSeed {
+    type Value = PartialGameState;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a gamestate")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        use GameSection as S;
+        let sections = self.0;
+        let mut result = PartialGameState::default();
+        if sections.contains(S::Wars) {
+            result.active_wars = Some(Vec::new());
+            result.previous_wars = Some(Vec::new());
+        }
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "players_countries" => result.players_countries = map.next_value()?,
+                "current_age" => result.current_age = Some(map.next_value()?),
+                "start_date" => result.start_date = Some(map.next_value()?),
+                "countries" if sections.contains(S::Countries) => {
+                    result.countries = Some(map.next_value::<CountriesSection>()?.0)
+                }
+                "provinces" if sections.contains(S::Provinces) => {
+                    result.provinces = Some(map.next_value::<ProvincesSection>()?.0)
+                }
+                "active_war" if sections.contains(S::Wars) => {
+                    let war = map.next_value()?;
+                    result.active_wars.get_or_insert_with(Vec::new).push(war);
+                }
+                "previous_war" if sections.contains(S::Wars) => {
+                    let war = map.next_value()?;
+                    result.previous_wars.get_or_insert_with(Vec::new).push(war);
+                }
+                "diplomacy" if sections.contains(S::Diplomacy) => {
+                    result.diplomacy = Some(map.next_value()?)
+                }
+                "trade" if sections.contains(S::Trade) => result.trade = Some(map.next_value()?),
+                "income_statistics" if sections.contains(S::Ledgers) => {
+                    result.income_statistics = Some(map.next_value()?)
+                }
+                "nation_size_statistics" if sections.contains(S::Ledgers) => {
+                    result.nation_size_statistics = Some(map.next_value()?)
+                }
+                "score_statistics" if sections.contains(S::Ledgers) => {
+                    result.score_statistics = Some(map.next_value()?)
+                }
+                "inflation_statistics" if sections.contains(S::Ledgers) => {
+                    result.inflation_statistics = Some(map.next_value()?)
+                }
+                "religions" if sections.contains(S::Religions) => {
+                    result.religions = Some(map.next_value::<ReligionsSection>()?.0)
+                }
+                "religion_instance_data" if sections.contains(S::Religions) => {
+                    result.religion_instance_data = Some(map.next_value()?)
+                }
+                "empire" if sections.contains(S::Empire) => result.empire = Some(map.next_value()?),
+                "military_hegemon" if sections.contains(S::Hegemons) => {
+                    result.military_hegemon = Some(map.next_value()?)
+                }
+                "naval_hegemon" if sections.contains(S::Hegemons) => {
+                    result.naval_hegemon = Some(map.next_value()?)
+                }
+                "economic_hegemon" if sections.contains(S::Hegemons) => {
+                    result.economic_hegemon = Some(map.next_value()?)
+                }
+                "map_area_data" if sections.contains(S::MapAreas) => {
+                    result.map_area_data = Some(map.next_value()?)
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Deserializes the value stored under a key within a top level section of
+/// the gamestate (eg: a single country), skipping over everything else
+pub(crate) struct SectionEntrySeed<K, V> {
+    section: &'static str,
+    key: K,
+    marker: PhantomData<V>,
+}
+
+impl<K, V> SectionEntrySeed<K, V> {
+    pub(crate) fn new(section: &'static str, key: K) -> Self {
+        SectionEntrySeed {
+            section,
+            key,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, K, V> DeserializeSeed<'de> for SectionEntrySeed<K, V>
+where
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    type Value = Option<V>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("SectionEntry", &[], self)
+    }
+}
+
+impl<'de, K, V> Visitor<'de> for SectionEntrySeed<K, V>
+where
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    type Value = Option<V>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "a gamestate with {}", self.section)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        // Return as soon as the section has been read so that the rest of
+        // the gamestate is never decoded
+        while let Some(key) = map.next_key::<String>()? {
+            if key == self.section {
+                return map.next_value_seed(EntrySeed {
+                    key: &self.key,
+                    marker: PhantomData::<V>,
+                });
+            }
+
+            map.next_value::<IgnoredAny>()?;
+        }
+
+        Ok(None)
+    }
+}
+
+struct EntrySeed<'a, K, V> {
+    key: &'a K,
+    marker: PhantomData<V>,
+}
+
+impl<'de, K, V> DeserializeSeed<'de> for EntrySeed<'_, K, V>
+where
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    type Value = Option<V>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, K, V> Visitor<'de> for EntrySeed<'_, K, V>
+where
+    K: Deserialize<'de> + PartialEq,
+    V: Deserialize<'de>,
+{
+    type Value = Option<V>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<K>()? {
+            if key == *self.key {
+                return map.next_value().map(Some);
+            }
+
+            map.next_value::<IgnoredAny>()?;
+        }
+
+        Ok(None)
+    }
+}
+
+/// Receives the countries, provinces, and wars of a gamestate one at a time
+/// as they are decoded, so that they never need to be held in memory all at
+/// once.
+///
+/// Every method defaults to dropping the value.
+pub trait GameStateVisitor {
+    fn visit_country(&mut self, _tag: CountryTag, _country: Country) {}
+
+    fn visit_province(&mut self, _id: ProvinceId, _province: Province) {}
+
+    fn visit_active_war(&mut self, _war: ActiveWar) {}
+
+    fn visit_previous_war(&mut self, _war: PreviousWar) {}
+}
+
+/// Streams the gamestate into a [GameStateVisitor]
+pub(crate) struct GameStateVisitorSeed<'a, V>(pub &'a mut V);
+
+impl<'de, V: GameStateVisitor> DeserializeSeed<'de> for GameStateVisitorSeed<'_, V> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct("GameState", &[], self)
+    }
+}
+
+impl<'de, V: GameStateVisitor> Visitor<'de> for GameStateVisitorSeed<'_, V> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a gamestate")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "countries" => map.next_value_seed(EntriesSeed {
+                    visitor: &mut *self.0,
+                    visit: |v: &mut V, tag, country| v.visit_country(tag, country),
+                    marker: PhantomData,
+                })?,
+                "provinces" => map.next_value_seed(EntriesSeed {
+                    visitor: &mut *self.0,
+                    visit: |v: &mut V, id, province| v.visit_province(id, province),
+                    marker: PhantomData,
+                })?,
+                "active_war" => self.0.visit_active_war(map.next_value()?),
+                "previous_war" => self.0.visit_previous_war(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Hands each key value pair of a map to the visitor
+struct EntriesSeed<'a, V, F, K, T> {
+    visitor: &'a mut V,
+    visit: F,
+    marker: PhantomData<(K, T)>,
+}
+
+impl<'de, V, F, K, T> DeserializeSeed<'de> for EntriesSeed<'_, V, F, K, T>
+where
+    F: Fn(&mut V, K, T),
+    K: Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, V, F, K, T> Visitor<'de> for EntriesSeed<'_, V, F, K, T>
+where
+    F: Fn(&mut V, K, T),
+    K: Deserialize<'de>,
+    T: Deserialize<'de>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a map")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        while let Some((key, value)) = map.next_entry::<K, T>()? {
+            (self.visit)(self.visitor, key, value);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{file::Eu4Modeller, Encoding, Eu4File, SegmentedResolver};
+
+    #[test]
+    fn test_partial_game_state() {
+        let data = b"current_age=age_of_discovery\nempire={ emperor=HAB }\ndiplomacy={ alliance={ first=ENG second=POR } }\nprovinces={ -1={ name=\"Stockholm\" } }";
+        let sections = GameSections::from_iter([GameSection::Diplomacy, GameSection::Wars]);
+        let mut modeller = Eu4Modeller::from_reader(&data[..], SegmentedResolver::empty())
+            .with_encoding(Encoding::Text);
+        let game = PartialGameStateSeed(sections)
+            .deserialize(&mut modeller)
+            .unwrap();
+
+        assert_eq!(game.current_age.as_deref(), Some("age_of_discovery"));
+        assert!(game.empire.is_none());
+        assert!(game.provinces.is_none());
+        assert_eq!(game.diplomacy.unwrap().alliances.len(), 1);
+        assert!(game.active_wars.unwrap().is_empty());
+        assert!(game.previous_wars.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_sections() {
+        let data = b"EU4txt\ndate=1444.11.11\nsave_game=\"autosave.eu4\"\nplayer=\"ENG\"\n\
+displayed_country_name=\"England\"\nsavegame_version={ first=1 second=37 third=0 forth=0 name=\"Inca\" }\n\
+dlc_enabled={ }\nmulti_player=no\nnot_observer=yes\ncampaign_id=\"a1b2c3\"\ncampaign_length=0\n\
+is_random_new_world=no\ncurrent_age=age_of_discovery\nempire={ emperor=HAB }\n\
+diplomacy={ alliance={ first=ENG second=POR } }\nchecksum=\"abc123\"\n";
+        let file = Eu4File::from_slice(&data[..]).unwrap();
+        let sections = GameSections::from_iter([GameSection::Diplomacy]);
+        let save = file
+            .parse_sections(SegmentedResolver::empty(), sections)
+            .unwrap();
+        assert_eq!(save.meta.player, "ENG");
+        assert_eq!(save.meta.checksum, "abc123");
+        assert!(save.game.empire.is_none());
+        assert_eq!(save.game.diplomacy.unwrap().alliances.len(), 1);
+    }
+
+    #[test]
+    fn test_game_state_visitor() {
+        #[derive(Default)]
+        struct Development {
+            provinces: Vec<ProvinceId>,
+            base_tax: f32,
+        }
+
+        impl GameStateVisitor for Development {
+            fn visit_province(&mut self, id: ProvinceId, province: Province) {
+                self.provinces.push(id);
+                self.base_tax += province.base_tax;
+            }
+        }
+
+        let data = b"current_age=age_of_discovery\nprovinces={ -1={ name=\"Stockholm\" base_tax=3.000 institutions={ 0 } } -2={ name=\"Uppland\" base_tax=2.000 institutions={ 0 } } }";
+        let mut modeller = Eu4Modeller::from_reader(&data[..], SegmentedResolver::empty())
+            .with_encoding(Encoding::Text);
+        let mut development = Development::default();
+        modeller.visit(&mut development).unwrap();
+        assert_eq!(
+            development.provinces,
+            vec![ProvinceId::from(1), ProvinceId::from(2)]
+        );
+        assert_eq!(development.base_tax, 5.0);
+    }
+
+    #[test]
+    fn test_section_entry() {
+        let data = b"current_age=age_of_discovery\nprovinces={ -1={ name=\"Stockholm\" institutions={ 0 } } -2={ name=\"\xD6sterg\xF6tland\" institutions={ 0 } } }\nempire={ emperor=HAB }";
+        let deserialize = |id: i32| {
+            let mut modeller = Eu4Modeller::from_reader(&data[..], SegmentedResolver::empty())
+                .with_encoding(Encoding::Text);
+            SectionEntrySeed::<ProvinceId, Province>::new("provinces", ProvinceId::from(id))
+                .deserialize(&mut modeller)
+                .unwrap()
+        };
+
+        assert_eq!(deserialize(2).unwrap().name, "Östergötland");
+        assert_eq!(deserialize(1).unwrap().name, "Stockholm");
+        assert!(deserialize(3).is_none());
+    }
+
+    #[test]
+    fn test_section_entry_stops_reading() {
+        let data = b"provinces={ -1={ name=\"Stockholm\" institutions={ 0 } } -2={ name=\"Kalmar\" } }\n} } } {";
+        let mut modeller = Eu4Modeller::from_reader(&data[..], SegmentedResolver::empty())
+            .with_encoding(Encoding::Text);
+        let province =
+            SectionEntrySeed::<ProvinceId, Province>::new("provinces", ProvinceId::from(1))
+                .deserialize(&mut modeller)
+                .unwrap();
+        assert_eq!(province.unwrap().name, "Stockholm");
+    }
+}
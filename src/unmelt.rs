@@ -0,0 +1,337 @@
+use crate::{Eu4Date, Eu4Error, Eu4ErrorKind};
+use jomini::{
+    binary::{Rgb, Token},
+    text::Operator,
+    Scalar, TextTape, TextToken,
+};
+use std::{collections::HashMap, io::Write};
+
+/// Maps a field name to the 16bit binary token that represents it. The
+/// inverse of a [TokenResolver](jomini::binary::TokenResolver).
+pub trait TokenEncoder {
+    /// Return the binary token of the field if known
+    fn encode(&self, name: &str) -> Option<u16>;
+}
+
+impl<T: TokenEncoder + ?Sized> TokenEncoder for &'_ T {
+    fn encode(&self, name: &str) -> Option<u16> {
+        (**self).encode(name)
+    }
+}
+
+impl TokenEncoder for HashMap<String, u16> {
+    fn encode(&self, name: &str) -> Option<u16> {
+        self.get(name).copied()
+    }
+}
+
+impl TokenEncoder for HashMap<&str, u16> {
+    fn encode(&self, name: &str) -> Option<u16> {
+        self.get(name).copied()
+    }
+}
+
+/// Converts EU4txt data (without the header) into the body of an EU4bin
+/// document.
+///
+/// Since text does not record the binary type of a value, the following
+/// rules are used to pick one:
+///
+/// - quoted values and unknown unquoted values are written as quoted strings
+/// - `yes` and `no` are booleans
+/// - dates are encoded as 32bit integers
+/// - integers are 32bit when they fit, otherwise 64bit
+/// - decimals with 4 or 5 fractional digits are the 64bit Q49.15 fixed point
+///   format, and all other decimals are the 32bit fixed point format
+pub(crate) fn unmelt<Writer, Encoder>(
+    input: &[u8],
+    mut output: Writer,
+    encoder: Encoder,
+) -> Result<(), Eu4Error>
+where
+    Writer: Write,
+    Encoder: TokenEncoder,
+{
+    let tape = TextTape::from_slice(input).map_err(Eu4ErrorKind::Parse)?;
+    let tokens = tape.tokens();
+    let mut unmelter = Unmelter {
+        tokens,
+        output: &mut output,
+        encoder,
+    };
+    unmelter.write_object(0, tokens.len())
+}
+
+struct Unmelter<'a, 'b, W, E> {
+    tokens: &'a [TextToken<'b>],
+    output: W,
+    encoder: E,
+}
+
+impl<W, E> Unmelter<'_, '_, W, E>
+where
+    W: Write,
+    E: TokenEncoder,
+{
+    fn write(&mut self, token: Token) -> Result<(), Eu4Error> {
+        token.write(&mut self.output)?;
+        Ok(())
+    }
+
+    fn write_object(&mut self, mut ind: usize, end: usize) -> Result<(), Eu4Error> {
+        while ind < end {
+            self.write_key(&self.tokens[ind])?;
+            ind += 1;
+
+            if let Some(TextToken::Operator(op)) = self.tokens.get(ind) {
+                if *op != Operator::Equal {
+                    return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(format!(
+                        "{} operator is not supported in EU4 files",
+                        op.symbol()
+                    ))));
+                }
+                ind += 1;
+            }
+
+            self.write(Token::Equal)?;
+            ind = self.write_value(ind)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_array(&mut self, mut ind: usize, end: usize) -> Result<(), Eu4Error> {
+        while ind < end {
+            ind = self.write_value(ind)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_key(&mut self, token: &TextToken) -> Result<(), Eu4Error> {
+        match token {
+            TextToken::Unquoted(scalar) => {
+                let token = match self.encoder.encode(&scalar_str(*scalar)) {
+                    Some(id) => Token::Id(id),
+                    None => number_token(*scalar).unwrap_or(Token::Quoted(*scalar)),
+                };
+                self.write(token)
+            }
+            TextToken::Quoted(scalar) => self.write(Token::Quoted(*scalar)),
+            _ => Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(String::from(
+                "expected object key to be a scalar",
+            )))),
+        }
+    }
+
+    /// Writes the value at the given index and returns the index of the next token
+    fn write_value(&mut self, ind: usize) -> Result<usize, Eu4Error> {
+        match &self.tokens[ind] {
+            TextToken::Object { end, mixed: false } => {
+                self.write(Token::Open)?;
+                self.write_object(ind + 1, *end)?;
+                self.write(Token::Close)?;
+                Ok(end + 1)
+            }
+            TextToken::Array { end, mixed: false } => {
+                self.write(Token::Open)?;
+                self.write_array(ind + 1, *end)?;
+                self.write(Token::Close)?;
+                Ok(end + 1)
+            }
+            TextToken::Unquoted(scalar) => {
+                let token = match number_token(*scalar) {
+                    Some(x) => x,
+                    None => match scalar.as_bytes() {
+                        b"yes" => Token::Bool(true),
+                        b"no" => Token::Bool(false),
+                        _ => match self.encoder.encode(&scalar_str(*scalar)) {
+                            Some(id) => Token::Id(id),
+                            None => Token::Quoted(*scalar),
+                        },
+                    },
+                };
+                self.write(token)?;
+                Ok(ind + 1)
+            }
+            TextToken::Quoted(scalar) => {
+                self.write(Token::Quoted(*scalar))?;
+                Ok(ind + 1)
+            }
+            TextToken::Header(header) if header.as_bytes() == b"rgb" => {
+                let TextToken::Array { end, .. } = self.tokens[ind + 1] else {
+                    return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(String::from(
+                        "expected rgb header to be followed by an array",
+                    ))));
+                };
+
+                let channels = self.tokens[ind + 2..end]
+                    .iter()
+                    .map(|x| {
+                        x.as_scalar()
+                            .and_then(|s| s.to_u64().ok())
+                            .and_then(|s| u32::try_from(s).ok())
+                    })
+                    .collect::<Option<Vec<_>>>();
+
+                let rgb = match channels.as_deref() {
+                    Some(&[r, g, b]) => Rgb { r, g, b, a: None },
+                    Some(&[r, g, b, a]) => Rgb {
+                        r,
+                        g,
+                        b,
+                        a: Some(a),
+                    },
+                    _ => {
+                        return Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(String::from(
+                            "expected rgb to contain 3 or 4 integer channels",
+                        ))))
+                    }
+                };
+
+                self.write(Token::Rgb(rgb))?;
+                Ok(end + 1)
+            }
+            _ => Err(Eu4Error::new(Eu4ErrorKind::InvalidSyntax(String::from(
+                "unsupported syntax encountered when converting text to binary",
+            )))),
+        }
+    }
+}
+
+fn scalar_str(scalar: Scalar) -> std::borrow::Cow<str> {
+    String::from_utf8_lossy(scalar.as_bytes())
+}
+
+/// Returns the binary token for a scalar if it represents a date or number
+fn number_token(scalar: Scalar) -> Option<Token<'static>> {
+    let data = scalar.as_bytes();
+    let (first, rest) = data.split_first()?;
+    if !first.is_ascii_digit() && *first != b'-' {
+        return None;
+    }
+
+    if let Ok(x) = scalar.to_i64() {
+        return match i32::try_from(x) {
+            Ok(x) => Some(Token::I32(x)),
+            Err(_) => Some(Token::I64(x)),
+        };
+    }
+
+    if let Ok(x) = scalar.to_u64() {
+        return Some(Token::U64(x));
+    }
+
+    if let Ok(date) = Eu4Date::parse(data) {
+        return Some(Token::I32(date.to_binary()));
+    }
+
+    let x = scalar.to_f64().ok()?;
+    let fractional_digits = rest
+        .iter()
+        .position(|&b| b == b'.')
+        .map_or(0, |dot| rest.len() - dot - 1);
+    if matches!(fractional_digits, 4 | 5) {
+        let val = (x * 32768.0).round() as i64;
+        Some(Token::F64(val.to_le_bytes()))
+    } else {
+        let val = (x * 1000.0).round() as i32;
+        Some(Token::F32(val.to_le_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        file::{Eu4FileEntryName, Eu4ZipWriter},
+        Eu4File, MeltOptions, SegmentedResolver,
+    };
+    use std::io::Cursor;
+
+    fn resolver() -> SegmentedResolver<'static> {
+        let mut values = vec![""; 0x30];
+        values[0x20] = "date";
+        values[0x21] = "player";
+        values[0x22] = "treasury";
+        values[0x23] = "provinces";
+        values[0x24] = "owner";
+        values[0x25] = "color";
+        values[0x26] = "cores";
+        values[0x27] = "hre";
+        values[0x28] = "inflation";
+        values[0x29] = "catholic";
+        values[0x2a] = "religion";
+        SegmentedResolver::from_parts(values, 0x30, 0x30)
+    }
+
+    #[test]
+    fn test_unmelt_melt_roundtrip() {
+        let resolver = resolver();
+        let encoder = resolver.encoder();
+        let text = "EU4txt\ndate=1444.11.11\nplayer=\"ENG\"\ntreasury=100.250\ninflation=2.49860\nprovinces={\n\t-1={\n\t\towner=\"ENG\"\n\t\tcores={\n\t\t\tENG FRA\n\t\t}\n\t\thre=yes\n\t\treligion=catholic\n\t\tcolor=rgb {\n\t\t\t10 20 30\n\t\t}\n\t}\n}";
+        let file = Eu4File::from_slice(text.as_bytes()).unwrap();
+        let mut binary = Vec::new();
+        file.unmelt(&encoder, &mut binary).unwrap();
+        assert!(binary.starts_with(b"EU4bin"));
+
+        let file = Eu4File::from_slice(&binary).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        file.melt(MeltOptions::new(), &resolver, &mut out).unwrap();
+        assert_eq!(std::str::from_utf8(out.get_ref()).unwrap(), text);
+    }
+
+    #[test]
+    fn test_unmelt_text_zip() {
+        let resolver = resolver();
+        let mut zip = Vec::new();
+        let mut writer = Eu4ZipWriter::new(&mut zip);
+        writer
+            .write_entry(Eu4FileEntryName::Meta, &b"EU4txt\ndate=1444.11.11\n"[..])
+            .unwrap();
+        writer
+            .write_entry(
+                Eu4FileEntryName::Gamestate,
+                &b"EU4txt\nplayer=\"ENG\"\n"[..],
+            )
+            .unwrap();
+        writer
+            .write_entry(Eu4FileEntryName::Ai, &b"EU4txt\ntreasury=1.500\n"[..])
+            .unwrap();
+        writer.finish().unwrap();
+
+        let file = Eu4File::from_slice(&zip).unwrap();
+        let mut binary = Vec::new();
+        file.unmelt(resolver.encoder(), &mut binary).unwrap();
+
+        let file = Eu4File::from_slice(&binary).unwrap();
+        let mut out = Vec::new();
+        file.melt(MeltOptions::new(), &resolver, &mut out).unwrap();
+        assert_eq!(
+            std::str::from_utf8(&out).unwrap(),
+            "EU4txt\ndate=1444.11.11\nplayer=\"ENG\"\ntreasury=1.500"
+        );
+    }
+
+    #[test]
+    fn test_unmelt_tokens() {
+        let resolver = resolver();
+        let encoder = resolver.encoder();
+        let mut out = Vec::new();
+        unmelt(b"date=1444.11.11 unknown=abc", &mut out, &encoder).unwrap();
+
+        let mut expected = Vec::new();
+        for token in [
+            Token::Id(0x20),
+            Token::Equal,
+            Token::I32(Eu4Date::from_ymd(1444, 11, 11).to_binary()),
+            Token::Quoted(Scalar::new(b"unknown")),
+            Token::Equal,
+            Token::Quoted(Scalar::new(b"abc")),
+        ] {
+            token.write(&mut expected).unwrap();
+        }
+
+        assert_eq!(out, expected);
+    }
+}
@@ -60,7 +60,7 @@ fn test_eu4_bin() {
     assert_eq!(expected_histories, histories);
 
     let mut output = Vec::new();
-    file.melt(MeltOptions::new(), &TOKENS.resolver(), &mut output)
+    file.melt(MeltOptions::new(), TOKENS.resolver(), &mut output)
         .unwrap();
     let checksum = HighwayHasher::default().hash256(output.as_slice());
     insta::assert_snapshot!(format!("{:016x}{:016x}{:016x}{:016x}", checksum[0], checksum[1], checksum[2], checksum[3]), @"83441f7282de4f211b37fba460ff71c55c91c7552e68ac8ac56f4700a124fa7a");
@@ -250,13 +250,13 @@ fn test_roundtrip_melt() {
     let mut out = Cursor::new(Vec::new());
     file.melt(
         MeltOptions::new().on_failed_resolve(FailedResolveStrategy::Error),
-        &TOKENS.resolver(),
+        TOKENS.resolver(),
         &mut out,
     )
     .unwrap();
 
     let file = Eu4File::from_slice(out.get_ref().as_slice()).unwrap();
-    let save = file.parse_save(&TOKENS.resolver()).unwrap();
+    let save = file.parse_save(TOKENS.resolver()).unwrap();
     assert_eq!(file.encoding(), Encoding::Text);
     assert_eq!(save.meta.player, "BHA");
 }
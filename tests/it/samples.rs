@@ -1,15 +1,19 @@
 use crate::utils;
 use eu4save::{
+    campaign::Campaign,
+    diff::SaveDiff,
     file::{Eu4FileEntryName, Eu4FsFileKind},
     models::{CountryEvent, Meta},
     query::{
         BuildingConstruction, BuildingEvent, NationEvent, NationEventKind, NationEvents,
         PlayerHistory, Query,
     },
-    Encoding, Eu4Date, Eu4File, MeltOptions, PdsDate, ProvinceId, SegmentedResolver,
+    sections::{GameSection, GameSections},
+    CancellationToken, CountryTag, Encoding, Eu4Date, Eu4ErrorKind, Eu4File, MeltOptions,
+    ParseOptions, PdsDate, Progress, ProgressHooks, ProvinceId, SegmentedResolver,
 };
 use highway::{HighwayHash, HighwayHasher};
-use std::{collections::HashMap, error::Error};
+use std::{collections::HashMap, error::Error, io::Read};
 
 #[test]
 fn test_eu4_text() -> Result<(), Box<dyn Error>> {
@@ -75,7 +79,7 @@ fn test_eu4_text() -> Result<(), Box<dyn Error>> {
     assert_eq!(histories, expected_histories);
 
     let mut output = Vec::new();
-    file.melt(MeltOptions::new(), &SegmentedResolver::empty(), &mut output)?;
+    file.melt(MeltOptions::new(), SegmentedResolver::empty(), &mut output)?;
     let checksum = HighwayHasher::default().hash256(output.as_slice());
     insta::assert_snapshot!(format!("{:016x}{:016x}{:016x}{:016x}", checksum[0], checksum[1], checksum[2], checksum[3]), @"30850b29ed85cde28d82e17d80bdea0f75dcca9cec40709bd53872f4924ad764");
 
@@ -103,7 +107,7 @@ fn test_eu4_compressed_text_raw() -> Result<(), Box<dyn Error>> {
     };
 
     let mut meta_entry = zip.get(Eu4FileEntryName::Meta)?;
-    let meta: Meta = meta_entry.deserialize(&SegmentedResolver::empty())?;
+    let meta: Meta = meta_entry.deserialize(SegmentedResolver::empty())?;
 
     assert_eq!(file.encoding(), Encoding::TextZip);
     assert_eq!(meta.player, "ENG");
@@ -308,7 +312,7 @@ pub fn parse_multiplayer_saves() -> Result<(), Box<dyn Error>> {
     );
 
     let mut output = Vec::new();
-    file.melt(MeltOptions::new(), &SegmentedResolver::empty(), &mut output)?;
+    file.melt(MeltOptions::new(), SegmentedResolver::empty(), &mut output)?;
     let checksum = HighwayHasher::default().hash256(output.as_slice());
     insta::assert_snapshot!(format!("{:016x}{:016x}{:016x}{:016x}", checksum[0], checksum[1], checksum[2], checksum[3]), @"c894bda05e79a131dad5419e4e909e3f2d58df1aea1922f938aaa70a52e44f8f");
 
@@ -387,3 +391,259 @@ fn fix_crash_on_long_country_tag_debug_mode() {
     let file = Eu4File::from_file(file).unwrap();
     let _save = file.parse_save(&SegmentedResolver::empty());
 }
+
+#[test]
+fn test_save_diff_identical() -> Result<(), Box<dyn Error>> {
+    let file = utils::request_file("eng-txt.eu4");
+    let file = Eu4File::from_file(file)?;
+    let save = file.parse_save(&SegmentedResolver::empty())?;
+
+    let diff = SaveDiff::compare(&save, &save)?;
+    assert!(diff.provinces.is_empty());
+    assert!(diff.new_relationships.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_campaign_series() -> Result<(), Box<dyn Error>> {
+    // HSN tag switched to NPL in 1701 and then to BYZ in 1706
+    let file = utils::request_file("mp_Uesugi.eu4");
+    let file = Eu4File::from_file(file)?;
+    let later = file.parse_save(&SegmentedResolver::empty())?;
+
+    // Recreate how the save looked while the country was still NPL. The
+    // original NPL entry holds the history of the annexed BYZ, so drop it.
+    let byz: CountryTag = "BYZ".parse()?;
+    let npl: CountryTag = "NPL".parse()?;
+    let mut earlier = later.clone();
+    earlier.meta.date = Eu4Date::from_ymd(1703, 1, 1);
+    earlier.game.countries.retain(|(tag, _)| *tag != npl);
+    for (tag, country) in earlier.game.countries.iter_mut() {
+        if *tag == byz {
+            *tag = npl;
+            country.treasury += 100.0;
+        }
+    }
+
+    let campaign = Campaign::new(vec![later, earlier])?;
+    let series = campaign.country_series(&byz);
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[0].date, Eu4Date::from_ymd(1703, 1, 1));
+    assert_eq!(series[0].tag, npl);
+    assert_eq!(series[1].tag, byz);
+    assert_eq!(series[0].treasury, series[1].treasury + 100.0);
+    assert!(campaign.country_series(&npl).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_parse_sections() -> Result<(), Box<dyn Error>> {
+    let data = utils::request_file("eng.txt.compressed.eu4");
+    let file = Eu4File::from_file(data)?;
+    let sections = GameSections::from_iter([GameSection::Diplomacy, GameSection::Empire]);
+    let save = file.parse_sections(&SegmentedResolver::empty(), sections)?;
+    assert_eq!(save.meta.player, "ENG");
+    assert!(save.game.countries.is_none());
+    assert!(save.game.provinces.is_none());
+    assert!(save.game.diplomacy.is_some());
+    assert!(save.game.empire.is_some());
+    Ok(())
+}
+
+#[test]
+fn test_parse_country_and_province() -> Result<(), Box<dyn Error>> {
+    let mut data = Vec::new();
+    utils::request_file("eng.txt.compressed.eu4").read_to_end(&mut data)?;
+    let file = Eu4File::from_slice(&data)?;
+    let save = file.parse_save(SegmentedResolver::empty())?;
+    let query = Query::from_save(save);
+
+    let tag = "ENG".parse()?;
+    let country = file.parse_country(&tag, SegmentedResolver::empty())?;
+    assert_eq!(
+        country.map(|x| x.treasury),
+        query.country(&tag).map(|x| x.treasury)
+    );
+
+    let london = file.parse_province(&ProvinceId::from(236), SegmentedResolver::empty())?;
+    assert_eq!(london.map(|x| x.name), Some(String::from("London")));
+    Ok(())
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_parse_save_parallel() -> Result<(), Box<dyn Error>> {
+    let mut data = Vec::new();
+    utils::request_file("eng.txt.compressed.eu4").read_to_end(&mut data)?;
+    let file = Eu4File::from_slice(&data)?;
+    let expected = file.parse_save(SegmentedResolver::empty())?;
+    let actual = file.parse_save_parallel(SegmentedResolver::empty())?;
+    assert_eq!(actual.meta.player, expected.meta.player);
+    assert_eq!(actual.game.countries.len(), expected.game.countries.len());
+    assert_eq!(actual.game.provinces.len(), expected.game.provinces.len());
+
+    let tags = |save: &eu4save::models::Eu4Save| -> Vec<_> {
+        save.game.countries.iter().map(|(tag, _)| *tag).collect()
+    };
+    assert_eq!(tags(&actual), tags(&expected));
+    Ok(())
+}
+
+#[test]
+fn test_parse_save_borrowed() -> Result<(), Box<dyn Error>> {
+    for name in ["eng-txt.eu4", "eng.txt.compressed.eu4"] {
+        let mut data = Vec::new();
+        utils::request_file(name).read_to_end(&mut data)?;
+        let file = Eu4File::from_slice(&data)?;
+        let resolver = SegmentedResolver::empty();
+
+        let expected = file.parse_save(&resolver)?;
+        let mut buf = Vec::new();
+        let save = file.parse_save_borrowed(&resolver, &mut buf)?;
+        assert_eq!(save.meta.player, expected.meta.player);
+
+        let (tag, country) = &save.game.countries[0];
+        let (expected_tag, expected_country) = &expected.game.countries[0];
+        assert_eq!(tag, expected_tag);
+        assert_eq!(
+            country.primary_culture.as_deref(),
+            expected_country.primary_culture.as_deref()
+        );
+
+        let save = eu4save::models::Eu4Save::from(save);
+        assert_eq!(save.game.countries.len(), expected.game.countries.len());
+        assert_eq!(save.game.provinces.len(), expected.game.provinces.len());
+        for (id, province) in &expected.game.provinces {
+            let actual = &save.game.provinces[id];
+            assert_eq!(actual.culture, province.culture);
+            assert_eq!(actual.trade_goods, province.trade_goods);
+            assert_eq!(actual.buildings, province.buildings);
+        }
+
+        // Hash maps iterate in a different order in each save, so compare
+        // the sorted lines of their debug output
+        let debug_lines = |save: &eu4save::models::Eu4Save| {
+            let mut lines = format!("{save:#?}")
+                .lines()
+                .map(String::from)
+                .collect::<Vec<_>>();
+            lines.sort_unstable();
+            lines
+        };
+        assert!(debug_lines(&save) == debug_lines(&expected));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_query_interner() -> Result<(), Box<dyn Error>> {
+    let file = utils::request_file("eng-txt.eu4");
+    let file = Eu4File::from_file(file)?;
+    let save = file.parse_save(&SegmentedResolver::empty())?;
+    let query = Query::from_save(save);
+
+    let london = query.interned_province(&ProvinceId::from(236)).unwrap();
+    let culture = london.culture.map(|x| query.interner().resolve(x));
+    let expected = query.save().game.provinces[&ProvinceId::from(236)]
+        .culture
+        .as_deref();
+    assert_eq!(culture, expected);
+
+    let fort = query.interner().index("fort_15th").unwrap();
+    assert!(london.buildings.contains(&fort));
+
+    let england = query.interned_country(&"ENG".parse()?).unwrap();
+    assert_eq!(england.primary_culture, london.culture);
+    Ok(())
+}
+
+#[test]
+fn test_parse_meta() -> Result<(), Box<dyn Error>> {
+    for name in ["eng-txt.eu4", "eng.txt.compressed.eu4"] {
+        let mut data = Vec::new();
+        utils::request_file(name).read_to_end(&mut data)?;
+        let file = Eu4File::from_slice(&data)?;
+        let meta = file.parse_meta(SegmentedResolver::empty())?;
+        let save = file.parse_save(SegmentedResolver::empty())?;
+        assert_eq!(meta.player, save.meta.player);
+        assert_eq!(meta.date, save.meta.date);
+        assert_eq!(meta.dlc_enabled, save.meta.dlc_enabled);
+        assert_eq!(meta.checksum, save.meta.checksum);
+
+        let fs_meta =
+            Eu4File::from_file(utils::request_file(name))?.parse_meta(SegmentedResolver::empty())?;
+        assert_eq!(fs_meta.checksum, save.meta.checksum);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_melt_progress_and_cancel() -> Result<(), Box<dyn Error>> {
+    let mut data = Vec::new();
+    utils::request_file("eng.txt.compressed.eu4").read_to_end(&mut data)?;
+    let file = Eu4File::from_slice(&data)?;
+
+    let mut last = Progress::default();
+    let mut sink = |x: Progress| last = x;
+    let hooks = ProgressHooks::new().with_sink(&mut sink);
+    let mut out = Vec::new();
+    file.melt_with_progress(
+        MeltOptions::new(),
+        SegmentedResolver::empty(),
+        &mut out,
+        hooks,
+    )?;
+    assert!(last.bytes > 0);
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let hooks = ProgressHooks::new().with_cancellation(token);
+    let err = file
+        .melt_with_progress(
+            MeltOptions::new(),
+            SegmentedResolver::empty(),
+            Vec::new(),
+            hooks,
+        )
+        .unwrap_err();
+    assert!(matches!(err.kind(), Eu4ErrorKind::Cancelled));
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let hooks = ProgressHooks::new().with_cancellation(token);
+    let err = Eu4File::from_file(utils::request_file("eng.txt.compressed.eu4"))?
+        .parse_save_with_progress(&SegmentedResolver::empty(), hooks)
+        .unwrap_err();
+    assert!(matches!(err.kind(), Eu4ErrorKind::Cancelled));
+    Ok(())
+}
+
+#[test]
+fn test_lenient_parse() -> Result<(), Box<dyn Error>> {
+    let mut data = Vec::new();
+    utils::request_file("eng-txt.eu4").read_to_end(&mut data)?;
+
+    let file = Eu4File::from_slice(&data)?;
+    let (save, diagnostics) =
+        file.parse_save_with(SegmentedResolver::empty(), ParseOptions::lenient())?;
+    assert_eq!(save.meta.player, "ENG");
+    assert!(diagnostics.is_empty());
+
+    let needle = b"type=general";
+    let pos = data
+        .windows(needle.len())
+        .position(|x| x == needle)
+        .unwrap();
+    data[pos..pos + needle.len()].copy_from_slice(b"type=wizard_");
+
+    let file = Eu4File::from_slice(&data)?;
+    let err = file.parse_save(SegmentedResolver::empty()).unwrap_err();
+    assert!(err.path().is_some_and(|x| x.ends_with("type")));
+
+    let (save, diagnostics) =
+        file.parse_save_with(SegmentedResolver::empty(), ParseOptions::lenient())?;
+    assert_eq!(save.meta.player, "ENG");
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].path.ends_with("type"));
+    Ok(())
+}